@@ -0,0 +1,3 @@
+fn main() {
+    witnesscalc_adapter::build_and_link("../build/Tally/Tally_cpp/cpp_dat");
+}
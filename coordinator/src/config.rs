@@ -0,0 +1,61 @@
+use config::{Config, File};
+use serde::{Deserialize, Deserializer};
+use solana_sdk::signature::Keypair;
+use solana_tools::solana_transactor::RpcEntry;
+use solana_tools::utils::deserialize_keypair;
+use std::path::PathBuf;
+use tracing::debug;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct CoordinatorConfig {
+    pub solana: SolanaConfig,
+    /// Secret scalar of the coordinator BabyJubJub key, decimal.
+    #[serde(deserialize_with = "deserialize_scalar")]
+    pub sk: [u8; 32],
+    pub votes_source: VotesSource,
+    /// Polls to tally besides the ones discovered through the indexer.
+    #[serde(default)]
+    pub polls: Vec<u64>,
+    pub polling_interval_secs: u64,
+}
+
+impl CoordinatorConfig {
+    pub(super) fn from_path(config_path: PathBuf) -> Self {
+        debug!("Reading config from path {:?}", config_path);
+        let config = Config::builder()
+            .add_source(File::from(config_path))
+            .add_source(config::Environment::with_prefix("COORDINATOR").separator("_"))
+            .build()
+            .expect("Failed to build envs");
+
+        config
+            .try_deserialize()
+            .expect("Failed to deserialize config")
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct SolanaConfig {
+    #[serde(deserialize_with = "deserialize_keypair")]
+    pub keypair: Keypair,
+    pub read_rpcs: Vec<RpcEntry>,
+    pub write_rpcs: Vec<RpcEntry>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum VotesSource {
+    /// The `votes` table of the indexer database at `DATABASE_URL`.
+    Indexer,
+    /// Transaction logs of the poll account.
+    Chain,
+}
+
+fn deserialize_scalar<'de, D>(deserializer: D) -> Result<[u8; 32], D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    let n: ruint::aliases::U256 = s.parse().map_err(serde::de::Error::custom)?;
+    Ok(n.to_be_bytes())
+}
@@ -0,0 +1,318 @@
+use anchor_lang::{system_program, AccountDeserialize, InstructionData, ToAccountMetas};
+use anon_vote::state::{Poll, PollResult, Tally};
+use ark_bn254::Fr;
+use ark_ff::Zero;
+use solana_sdk::{
+    commitment_config::CommitmentConfig, instruction::Instruction, pubkey::Pubkey,
+    signature::Keypair, signer::Signer, transaction::TransactionError,
+};
+use solana_tools::solana_transactor::{
+    ix_compiler::InstructionBundle, RpcPool, SolanaTransactor, TransactorError,
+};
+use sqlx::PgPool;
+use thiserror::Error;
+use tracing::{debug, info, warn};
+
+use crate::{
+    crypto::{fr_from_be, fr_to_be, poseidon, BASE8},
+    prover::{compress_proof, prove_tally, TallyPublicInputs, MAX_BATCH},
+    tally::{derive_salt, Message, TallyError, TallyState},
+    utils::now,
+    votes::{fetch_from_chain, fetch_from_indexer, prefix_matching, VotesError},
+};
+
+const TALLY_CU: u32 = 200_000;
+
+#[derive(Debug, Error)]
+pub enum CoordinatorError {
+    #[error("Poll {0} not found")]
+    PollNotFound(u64),
+    #[error("Poll {0} is encrypted to another coordinator key")]
+    WrongCoordinatorKey(u64),
+    #[error("Fetched messages don't match the poll running hash")]
+    VotesIncomplete,
+    #[error("Proof public inputs don't match the tally state")]
+    PublicInputsMismatch,
+    #[error("Tally deadline of poll {0} has passed")]
+    TallyDeadlinePassed(u64),
+    #[error("Solana transaction error: {0}")]
+    TransactionError(#[from] TransactionError),
+    #[error("Solana transactor error: {0}")]
+    TransactorError(#[from] TransactorError),
+    #[error("Anchor error: {0}")]
+    Anchor(#[from] anchor_lang::error::Error),
+    #[error("Votes error: {0}")]
+    Votes(#[from] VotesError),
+    #[error("Tally error: {0}")]
+    Tally(#[from] TallyError),
+    #[error("Prover error: {0}")]
+    Prover(#[from] anyhow::Error),
+}
+
+pub struct Coordinator {
+    payer: Keypair,
+    sk: [u8; 32],
+    rpc_pool: RpcPool,
+    transactor: SolanaTransactor,
+    /// Indexer database, messages are read from the chain if absent.
+    db: Option<PgPool>,
+}
+
+impl Coordinator {
+    pub fn new(
+        payer: Keypair,
+        sk: [u8; 32],
+        rpc_pool: RpcPool,
+        transactor: SolanaTransactor,
+        db: Option<PgPool>,
+    ) -> Self {
+        Self {
+            payer,
+            sk,
+            rpc_pool,
+            transactor,
+            db,
+        }
+    }
+
+    /// Untallied polls addressed to this coordinator known to the indexer.
+    pub async fn discover_polls(&self) -> Result<Vec<u64>, CoordinatorError> {
+        let Some(db) = &self.db else {
+            return Ok(vec![]);
+        };
+        let key = BASE8.mul(&self.sk).to_be();
+        let rows: Vec<i64> = sqlx::query_scalar(
+            r#"
+            SELECT poll_id
+            FROM polls
+            WHERE coord_x = $1 AND coord_y = $2 AND tally IS NULL AND NOT failed
+            ORDER BY voting_end_time
+            "#,
+        )
+        .bind(&key.x[..])
+        .bind(&key.y[..])
+        .fetch_all(db)
+        .await
+        .map_err(VotesError::from)?;
+        Ok(rows.into_iter().map(|id| id as u64).collect())
+    }
+
+    /// Brings the poll to a finished tally, resuming from whatever is
+    /// already committed on chain. Returns `false` if the poll can't be
+    /// tallied yet.
+    pub async fn process_poll(&self, poll_id: u64) -> Result<bool, CoordinatorError> {
+        let poll_address = find_poll(poll_id);
+        let Some(poll) = self.fetch_account::<Poll>(poll_address).await? else {
+            // a closed poll was tallied or failed
+            return match self
+                .fetch_account::<PollResult>(find_result(poll_id))
+                .await?
+            {
+                Some(_) => Ok(true),
+                None => Err(CoordinatorError::PollNotFound(poll_id)),
+            };
+        };
+
+        if !poll.tally.is_empty() {
+            return Ok(true);
+        }
+        if now() >= poll.tally_deadline {
+            return Err(CoordinatorError::TallyDeadlinePassed(poll_id));
+        }
+        let key = BASE8.mul(&self.sk).to_be();
+        if (poll.coordinator_key.x, poll.coordinator_key.y) != (key.x, key.y) {
+            return Err(CoordinatorError::WrongCoordinatorKey(poll_id));
+        }
+        if now() <= poll.voting_end_time {
+            debug!(poll_id, "Voting is not over yet");
+            return Ok(false);
+        }
+
+        let mut messages = match &self.db {
+            Some(db) => fetch_from_indexer(db, poll_id).await?,
+            None => fetch_from_chain(&self.rpc_pool, poll_address, poll_id).await?,
+        };
+        let n_messages = prefix_matching(&messages, fr_from_be(&poll.running_msg_hash))
+            .ok_or(CoordinatorError::VotesIncomplete)?;
+        messages.truncate(n_messages);
+        info!(poll_id, n_messages, "Tallying poll");
+
+        let tally_address = find_tally(poll_id, self.payer.pubkey());
+        let resumed = match self.fetch_account::<Tally>(tally_address).await? {
+            Some(tally) => self.resume(poll_id, &messages, &tally)?,
+            None => None,
+        };
+        let mut state = match resumed {
+            Some(state) => state,
+            None => {
+                let state = TallyState::new(self.sk, derive_salt(&self.sk, poll_id, Fr::zero()));
+                self.create_tally(poll_id, state.tally_hash()).await?;
+                state
+            }
+        };
+
+        for chunk in messages[state.processed..].chunks(MAX_BATCH) {
+            let h_after = chunk
+                .iter()
+                .fold(state.running_msg_hash, |h, msg| poseidon(&[h, msg.hash()]));
+            let inputs = state.process_batch(chunk, derive_salt(&self.sk, poll_id, h_after))?;
+
+            let circom = tokio::task::spawn_blocking(move || prove_tally(&inputs))
+                .await
+                .expect("prover task panicked")?;
+            let pub_inputs = TallyPublicInputs::from(&circom.pub_inputs);
+            if pub_inputs.root_after != state.root()
+                || pub_inputs.running_msg_hash_after != fr_to_be(state.running_msg_hash)
+                || pub_inputs.tally_hash_after != state.tally_hash()
+            {
+                return Err(CoordinatorError::PublicInputsMismatch);
+            }
+
+            let ix = Instruction::new_with_bytes(
+                anon_vote::ID,
+                &anon_vote::instruction::TallyBatch {
+                    proof: compress_proof(circom.proof),
+                    root_after: pub_inputs.root_after,
+                    running_msg_hash_after: pub_inputs.running_msg_hash_after,
+                    tally_hash_after: pub_inputs.tally_hash_after,
+                }
+                .data(),
+                anon_vote::accounts::TallyBatch {
+                    tally: tally_address,
+                }
+                .to_account_metas(None),
+            );
+            self.send(ix).await?;
+            debug!(poll_id, processed = state.processed, "Tallied batch");
+        }
+
+        let ix = Instruction::new_with_bytes(
+            anon_vote::ID,
+            &anon_vote::instruction::FinishTally {
+                tally: state.tally[..poll.n_choices as usize].to_vec(),
+                tally_salt: state.salt,
+            }
+            .data(),
+            anon_vote::accounts::FinishTally {
+                payer: self.payer.pubkey(),
+                tally: tally_address,
+                poll: poll_address,
+            }
+            .to_account_metas(None),
+        );
+        self.send(ix).await?;
+        info!(poll_id, tally = ?&state.tally[..poll.n_choices as usize], "Finished tally");
+
+        Ok(true)
+    }
+
+    /// Rebuilds the state committed to by an existing `Tally` account.
+    /// Returns `None` if it wasn't produced by this coordinator, in which
+    /// case it has to be reset.
+    fn resume(
+        &self,
+        poll_id: u64,
+        messages: &[Message],
+        tally: &Tally,
+    ) -> Result<Option<TallyState>, CoordinatorError> {
+        let running_msg_hash = fr_from_be(&tally.running_msg_hash);
+        let Some(processed) = prefix_matching(messages, running_msg_hash) else {
+            warn!(poll_id, "Tally account doesn't follow the poll messages");
+            return Ok(None);
+        };
+
+        let mut state = TallyState::new(self.sk, derive_salt(&self.sk, poll_id, Fr::zero()));
+        state.replay(
+            &messages[..processed],
+            derive_salt(&self.sk, poll_id, running_msg_hash),
+        )?;
+
+        if state.root() != tally.root || state.tally_hash() != tally.tally_hash {
+            warn!(poll_id, "Tally account doesn't match the replayed state");
+            return Ok(None);
+        }
+
+        info!(poll_id, processed, "Resuming tally");
+        Ok(Some(state))
+    }
+
+    /// Creates the `Tally` account, or resets the existing one.
+    async fn create_tally(
+        &self,
+        poll_id: u64,
+        initial_tally_hash: [u8; 32],
+    ) -> Result<(), CoordinatorError> {
+        let ix = Instruction::new_with_bytes(
+            anon_vote::ID,
+            &anon_vote::instruction::CreateTally {
+                poll_id,
+                initial_tally_hash,
+            }
+            .data(),
+            anon_vote::accounts::CreateTally {
+                payer: self.payer.pubkey(),
+                tally: find_tally(poll_id, self.payer.pubkey()),
+                poll: find_poll(poll_id),
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+        );
+        self.send(ix).await
+    }
+
+    async fn send(&self, ix: Instruction) -> Result<(), CoordinatorError> {
+        let ix = InstructionBundle::new(ix, TALLY_CU, None, vec![]);
+        let tx_results = self
+            .transactor
+            .send_all_instructions(
+                Option::<&str>::None,
+                &[ix],
+                &[&self.payer],
+                self.payer.pubkey(),
+                1,
+                None,
+                false,
+            )
+            .await?;
+        for tx_result in tx_results {
+            tx_result.status?;
+        }
+        Ok(())
+    }
+
+    async fn fetch_account<T: AccountDeserialize>(
+        &self,
+        address: Pubkey,
+    ) -> Result<Option<T>, CoordinatorError> {
+        let account = self
+            .rpc_pool
+            .with_read_rpc_loop(
+                |rpc| async move {
+                    rpc.get_account_with_commitment(&address, CommitmentConfig::confirmed())
+                        .await
+                },
+                CommitmentConfig::confirmed(),
+            )
+            .await
+            .value;
+        Ok(account
+            .map(|a| T::try_deserialize(&mut &a.data[..]))
+            .transpose()?)
+    }
+}
+
+fn find_poll(poll_id: u64) -> Pubkey {
+    Pubkey::find_program_address(&[b"POLL", &poll_id.to_le_bytes()], &anon_vote::ID).0
+}
+
+fn find_result(poll_id: u64) -> Pubkey {
+    Pubkey::find_program_address(&[b"RESULT", &poll_id.to_le_bytes()], &anon_vote::ID).0
+}
+
+fn find_tally(poll_id: u64, owner: Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"TALLY", &poll_id.to_le_bytes(), &owner.to_bytes()],
+        &anon_vote::ID,
+    )
+    .0
+}
@@ -0,0 +1,240 @@
+//! BabyJubJub and Poseidon cipher primitives matching circomlib and
+//! zk-kit's `poseidon-cipher`, which the Vote and Tally circuits use.
+
+use ark_bn254::Fr;
+use ark_ff::{AdditiveGroup, BigInteger, Field, PrimeField, Zero};
+use light_poseidon::{
+    parameters::bn254_x5::get_poseidon_parameters, Poseidon, PoseidonHasher, PoseidonParameters,
+};
+use std::sync::LazyLock;
+
+pub const LIMBS: usize = 6;
+pub const CT_LEN: usize = LIMBS.div_ceil(3) * 3 + 1;
+
+static PERM_PARAMS: LazyLock<PoseidonParameters<Fr>> =
+    LazyLock::new(|| get_poseidon_parameters::<Fr>(4).expect("poseidon t=4 parameters"));
+
+pub fn fr_from_be(bytes: &[u8; 32]) -> Fr {
+    Fr::from_be_bytes_mod_order(bytes)
+}
+
+pub fn fr_to_be(x: Fr) -> [u8; 32] {
+    let bytes = x.into_bigint().to_bytes_be();
+    let mut res = [0; 32];
+    res[32 - bytes.len()..].copy_from_slice(&bytes);
+    res
+}
+
+pub fn poseidon(inputs: &[Fr]) -> Fr {
+    Poseidon::<Fr>::new_circom(inputs.len())
+        .expect("poseidon init")
+        .hash(inputs)
+        .expect("poseidon hash")
+}
+
+/// Full Poseidon permutation of width 4, the same as circomlib's
+/// `PoseidonEx(3, 4)` with `initialState = state[0]`.
+pub fn poseidon_perm(mut state: [Fr; 4]) -> [Fr; 4] {
+    let p = &*PERM_PARAMS;
+    let half_rounds = p.full_rounds / 2;
+    let all_rounds = p.full_rounds + p.partial_rounds;
+
+    for round in 0..all_rounds {
+        for (i, s) in state.iter_mut().enumerate() {
+            *s += p.ark[round * p.width + i];
+        }
+        if round < half_rounds || round >= half_rounds + p.partial_rounds {
+            for s in &mut state {
+                *s = s.pow([p.alpha]);
+            }
+        } else {
+            state[0] = state[0].pow([p.alpha]);
+        }
+        let prev = state;
+        for (i, s) in state.iter_mut().enumerate() {
+            *s = prev
+                .iter()
+                .zip(&p.mds[i])
+                .fold(Fr::zero(), |acc, (a, m)| acc + *a * *m);
+        }
+    }
+
+    state
+}
+
+fn cipher_initial_state(key: &Point, nonce: u64) -> [Fr; 4] {
+    let two_128 = Fr::from(2u8).pow([128]);
+    [
+        Fr::zero(),
+        key.x,
+        key.y,
+        Fr::from(nonce) + Fr::from(LIMBS as u64) * two_128,
+    ]
+}
+
+/// Decrypts a `CT_LEN`-element ciphertext into `LIMBS` field elements.
+/// Returns `None` if the authentication tag or the padding doesn't match.
+pub fn poseidon_decrypt(ciphertext: &[Fr; CT_LEN], key: &Point, nonce: u64) -> Option<[Fr; LIMBS]> {
+    let mut state = cipher_initial_state(key, nonce);
+    let mut msg = [Fr::zero(); CT_LEN - 1];
+
+    for (block, ct) in ciphertext[..CT_LEN - 1].chunks(3).enumerate() {
+        state = poseidon_perm(state);
+        for j in 0..3 {
+            msg[block * 3 + j] = ct[j] - state[1 + j];
+            state[1 + j] = ct[j];
+        }
+    }
+
+    if msg[LIMBS..].iter().any(|x| !x.is_zero()) {
+        return None;
+    }
+
+    state = poseidon_perm(state);
+    if ciphertext[CT_LEN - 1] != state[1] {
+        return None;
+    }
+
+    Some(msg[..LIMBS].try_into().unwrap())
+}
+
+#[cfg(test)]
+pub fn poseidon_encrypt(msg: &[Fr; LIMBS], key: &Point, nonce: u64) -> [Fr; CT_LEN] {
+    let mut state = cipher_initial_state(key, nonce);
+    let mut padded = [Fr::zero(); CT_LEN - 1];
+    padded[..LIMBS].copy_from_slice(msg);
+    let mut ciphertext = [Fr::zero(); CT_LEN];
+
+    for (block, m) in padded.chunks(3).enumerate() {
+        state = poseidon_perm(state);
+        for j in 0..3 {
+            state[1 + j] += m[j];
+            ciphertext[block * 3 + j] = state[1 + j];
+        }
+    }
+
+    state = poseidon_perm(state);
+    ciphertext[CT_LEN - 1] = state[1];
+    ciphertext
+}
+
+/// A point on the BabyJubJub curve in twisted Edwards form.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Point {
+    pub x: Fr,
+    pub y: Fr,
+}
+
+const A: u64 = 168700;
+const D: u64 = 168696;
+
+pub static BASE8: LazyLock<Point> = LazyLock::new(|| Point {
+    x: "5299619240641551281634865583518297030282874472190772894086521144482721001553"
+        .parse()
+        .unwrap(),
+    y: "16950150798460657717958625567821834550301663161624707787222815936182638968203"
+        .parse()
+        .unwrap(),
+});
+
+impl Point {
+    pub const IDENTITY: Self = Self {
+        x: Fr::ZERO,
+        y: Fr::ONE,
+    };
+
+    pub fn from_be(p: &anon_vote::state::Point) -> Self {
+        Self {
+            x: fr_from_be(&p.x),
+            y: fr_from_be(&p.y),
+        }
+    }
+
+    pub fn to_be(self) -> anon_vote::state::Point {
+        anon_vote::state::Point {
+            x: fr_to_be(self.x),
+            y: fr_to_be(self.y),
+        }
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        let x1x2 = self.x * other.x;
+        let y1y2 = self.y * other.y;
+        let dxy = Fr::from(D) * x1x2 * y1y2;
+        Self {
+            x: (self.x * other.y + self.y * other.x) * (Fr::ONE + dxy).inverse().unwrap(),
+            y: (y1y2 - Fr::from(A) * x1x2) * (Fr::ONE - dxy).inverse().unwrap(),
+        }
+    }
+
+    /// Scalar multiplication by a big-endian scalar.
+    pub fn mul(&self, scalar: &[u8; 32]) -> Self {
+        let mut res = Self::IDENTITY;
+        for byte in scalar {
+            for bit in (0..8).rev() {
+                res = res.add(&res);
+                if (byte >> bit) & 1 == 1 {
+                    res = res.add(self);
+                }
+            }
+        }
+        res
+    }
+}
+
+/// Shared key between the coordinator's secret scalar and an ephemeral key.
+pub fn ecdh(sk: &[u8; 32], eph_key: &Point) -> Point {
+    eph_key.mul(sk)
+}
+
+pub fn msg_hash(eph_key: &Point, nonce: u64, ciphertext: &[Fr; CT_LEN]) -> Fr {
+    let mut inputs = Vec::with_capacity(3 + CT_LEN);
+    inputs.extend([eph_key.x, eph_key.y, Fr::from(nonce)]);
+    inputs.extend(ciphertext);
+    poseidon(&inputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_perm_matches_hash() {
+        let inputs = [Fr::from(1u8), Fr::from(2u8), Fr::from(3u8)];
+        let state = poseidon_perm([Fr::zero(), inputs[0], inputs[1], inputs[2]]);
+        assert_eq!(state[0], poseidon(&inputs));
+    }
+
+    #[test]
+    fn test_base8() {
+        let g = Point {
+            x: "995203441582195749578291179787384436505546430278305826713579947235728471134"
+                .parse()
+                .unwrap(),
+            y: "5472060717959818805561601436314318772137091100104008585924551046643952123905"
+                .parse()
+                .unwrap(),
+        };
+        let mut eight = [0; 32];
+        eight[31] = 8;
+        assert_eq!(g.mul(&eight), *BASE8);
+    }
+
+    #[test]
+    fn test_ecdh_and_cipher_roundtrip() {
+        let mut sk = [0; 32];
+        sk[20..].copy_from_slice(&[7; 12]);
+        let mut eph_sk = [0; 32];
+        eph_sk[24..].copy_from_slice(&[3; 8]);
+
+        let pk = BASE8.mul(&sk);
+        let eph_key = BASE8.mul(&eph_sk);
+        let shared = ecdh(&eph_sk, &pk);
+        assert_eq!(shared, ecdh(&sk, &eph_key));
+
+        let msg = [1u8, 2, 3, 4, 5, 6].map(Fr::from);
+        let ct = poseidon_encrypt(&msg, &shared, 42);
+        assert_eq!(poseidon_decrypt(&ct, &shared, 42), Some(msg));
+        assert_eq!(poseidon_decrypt(&ct, &shared, 43), None);
+    }
+}
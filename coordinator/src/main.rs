@@ -0,0 +1,83 @@
+use clap::Parser;
+use solana_sdk::signer::Signer;
+use solana_tools::solana_transactor::{RpcPool, SolanaTransactor};
+use sqlx::postgres::PgPoolOptions;
+use std::{collections::BTreeSet, env, path::PathBuf, time::Duration};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    config::{CoordinatorConfig, VotesSource},
+    coordinator::{Coordinator, CoordinatorError},
+};
+
+mod config;
+mod coordinator;
+mod crypto;
+mod prover;
+mod tally;
+mod utils;
+mod votes;
+
+#[derive(Parser)]
+#[clap(author, version, about, long_about = None)]
+pub struct Cli {
+    #[arg(long, short, help = "Common config path")]
+    config: PathBuf,
+}
+
+#[tokio::main]
+async fn main() {
+    dotenvy::dotenv().ok();
+    tracing_subscriber::fmt::init();
+
+    let cli = Cli::parse_from(env::args());
+    let config = CoordinatorConfig::from_path(cli.config);
+    debug!("Public key {}", config.solana.keypair.pubkey());
+
+    let db = match config.votes_source {
+        VotesSource::Indexer => {
+            let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+            let db = PgPoolOptions::new()
+                .max_connections(4)
+                .connect(&database_url)
+                .await
+                .expect("Failed to connect to the indexer database");
+            Some(db)
+        }
+        VotesSource::Chain => None,
+    };
+
+    let rpc_pool = RpcPool::new(&config.solana.read_rpcs, &config.solana.write_rpcs)
+        .expect("RPC pool failed to initialize");
+    let transactor = SolanaTransactor::start(rpc_pool.clone())
+        .await
+        .expect("Failed to start solana transactor");
+    let coordinator = Coordinator::new(config.solana.keypair, config.sk, rpc_pool, transactor, db);
+
+    let mut done = BTreeSet::new();
+    loop {
+        let mut polls: BTreeSet<u64> = config.polls.iter().copied().collect();
+        match coordinator.discover_polls().await {
+            Ok(discovered) => polls.extend(discovered),
+            Err(err) => error!("Failed to discover polls: {err}"),
+        }
+
+        let pending: Vec<_> = polls.difference(&done).copied().collect();
+        for poll_id in pending {
+            match coordinator.process_poll(poll_id).await {
+                Ok(true) => {
+                    info!(poll_id, "Poll is tallied");
+                    done.insert(poll_id);
+                }
+                Ok(false) => {}
+                Err(err @ CoordinatorError::TallyDeadlinePassed(_)) => {
+                    warn!(poll_id, "{err}");
+                    done.insert(poll_id);
+                }
+                Err(err) => error!(poll_id, "Failed to tally: {err}"),
+            }
+        }
+
+        tokio::time::sleep(Duration::from_secs(config.polling_interval_secs)).await;
+    }
+}
@@ -0,0 +1,130 @@
+use anon_vote::state::CompressedProof;
+use ark_serialize::CanonicalSerialize;
+use circom_prover::{
+    prover::{circom::Proof, CircomProof, ProofLib, PublicInputs},
+    witness::WitnessFn,
+    CircomProver,
+};
+use core::ops::Neg;
+use serde::Serialize;
+
+use crate::{
+    crypto::CT_LEN,
+    utils::{ser_arr2_be32_as_dec, ser_arr_be32_as_dec, ser_arr_bool_as_u8, ser_be32_as_dec},
+};
+
+fn prove<I: Serialize>(
+    zkey_path: String,
+    witness_fn: fn(&str) -> anyhow::Result<Vec<u8>>,
+    inputs: &I,
+) -> anyhow::Result<CircomProof> {
+    let input_str = serde_json::to_string(inputs).unwrap();
+
+    CircomProver::prove(
+        ProofLib::Rapidsnark,
+        WitnessFn::WitnessCalc(witness_fn),
+        input_str,
+        zkey_path,
+    )
+}
+
+pub fn compress_proof(proof: Proof) -> CompressedProof {
+    let mut a = [0; 32];
+    let mut b = [0; 64];
+    let mut c = [0; 32];
+
+    proof
+        .a
+        .to_bn254()
+        .neg()
+        .serialize_compressed(&mut a[..])
+        .unwrap();
+    proof.b.to_bn254().serialize_compressed(&mut b[..]).unwrap();
+    proof.c.to_bn254().serialize_compressed(&mut c[..]).unwrap();
+
+    a.reverse();
+    b.reverse();
+    c.reverse();
+
+    CompressedProof { a, b, c }
+}
+
+witnesscalc_adapter::witness!(Tally);
+
+pub const STATE_DEPTH: usize = 64;
+pub const MAX_CHOICES: usize = 8;
+pub const MAX_BATCH: usize = 6;
+
+#[allow(non_snake_case)]
+#[derive(Clone, Serialize)]
+pub struct TallyInputs {
+    #[serde(serialize_with = "ser_be32_as_dec")]
+    pub Root_before: [u8; 32],
+    #[serde(serialize_with = "ser_be32_as_dec")]
+    pub H_before: [u8; 32],
+    #[serde(serialize_with = "ser_be32_as_dec")]
+    pub TallyHash_before: [u8; 32],
+    pub BatchLen: u64,
+    pub Tally_before: [u64; MAX_CHOICES],
+    pub TallySalt_before: u64,
+    pub TallySalt_after: u64,
+    #[serde(serialize_with = "ser_be32_as_dec")]
+    pub SK: [u8; 32],
+    #[serde(serialize_with = "ser_arr2_be32_as_dec")]
+    pub EphKey: [[[u8; 32]; 2]; MAX_BATCH],
+    pub Nonce: [u64; MAX_BATCH],
+    #[serde(serialize_with = "ser_arr2_be32_as_dec")]
+    pub CT: [[[u8; 32]; CT_LEN]; MAX_BATCH],
+    #[serde(serialize_with = "ser_arr2_be32_as_dec")]
+    pub Siblings: [[[u8; 32]; STATE_DEPTH]; MAX_BATCH],
+    #[serde(serialize_with = "ser_arr_be32_as_dec")]
+    pub PrevChoice: [[u8; 32]; MAX_BATCH],
+    #[serde(serialize_with = "ser_arr2_be32_as_dec")]
+    pub RevotingKeyOldActual: [[[u8; 32]; 2]; MAX_BATCH],
+    #[serde(serialize_with = "ser_arr_bool_as_u8")]
+    pub NoAux: [bool; MAX_BATCH],
+    #[serde(serialize_with = "ser_arr_be32_as_dec")]
+    pub AuxKey: [[u8; 32]; MAX_BATCH],
+    #[serde(serialize_with = "ser_arr_be32_as_dec")]
+    pub AuxValue: [[u8; 32]; MAX_BATCH],
+    #[serde(serialize_with = "ser_arr_bool_as_u8")]
+    pub IsPrevEmpty: [bool; MAX_BATCH],
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct TallyPublicInputs {
+    pub root_after: [u8; 32],
+    pub running_msg_hash_after: [u8; 32],
+    pub tally_hash_after: [u8; 32],
+    pub root_before: [u8; 32],
+    pub running_msg_hash_before: [u8; 32],
+    pub tally_hash_before: [u8; 32],
+}
+
+impl From<&PublicInputs> for TallyPublicInputs {
+    fn from(pub_inputs: &PublicInputs) -> Self {
+        assert_eq!(pub_inputs.0.len(), 6);
+        let mut public_inputs = [[0; 32]; 6];
+        for (dst, input) in public_inputs.iter_mut().zip(&pub_inputs.0) {
+            let bytes = input.to_bytes_be();
+            dst[32 - bytes.len()..].copy_from_slice(&bytes);
+        }
+
+        TallyPublicInputs {
+            root_after: public_inputs[0],
+            running_msg_hash_after: public_inputs[1],
+            tally_hash_after: public_inputs[2],
+            root_before: public_inputs[3],
+            running_msg_hash_before: public_inputs[4],
+            tally_hash_before: public_inputs[5],
+        }
+    }
+}
+
+pub fn prove_tally(inputs: &TallyInputs) -> anyhow::Result<CircomProof> {
+    prove(
+        "build/Tally/groth16_pkey.zkey".into(),
+        Tally_witness,
+        inputs,
+    )
+}
@@ -0,0 +1,309 @@
+use ark_bn254::Fr;
+use ark_ff::{BigInteger, PrimeField, Zero};
+use core::convert::Infallible;
+use smt_circom::{store::MemStore, CircomProof, SparseMerkleTree};
+use std::collections::HashMap;
+
+use crate::{
+    crypto::{ecdh, fr_to_be, msg_hash, poseidon, poseidon_decrypt, Point, CT_LEN},
+    prover::{TallyInputs, MAX_BATCH, MAX_CHOICES, STATE_DEPTH},
+};
+
+/// A `VoteEvent` as seen by the coordinator.
+#[derive(Clone, Debug)]
+pub struct Message {
+    pub eph_key: Point,
+    pub nonce: u64,
+    pub ciphertext: [Fr; CT_LEN],
+}
+
+impl Message {
+    pub fn hash(&self) -> Fr {
+        msg_hash(&self.eph_key, self.nonce, &self.ciphertext)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Leaf {
+    choice: Fr,
+    revoting_key: [Fr; 2],
+}
+
+impl Leaf {
+    fn hash(&self) -> [u8; 32] {
+        fr_to_be(poseidon(&[
+            self.choice,
+            self.revoting_key[0],
+            self.revoting_key[1],
+        ]))
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TallyError {
+    #[error("Merkle tree error: {0}")]
+    Smt(#[from] smt_circom::Error<Infallible>),
+    #[error("Message {0} doesn't decrypt with the coordinator key")]
+    Undecryptable(usize),
+}
+
+/// Witness data of a single message, before padding into a batch.
+struct MessageWitness {
+    eph_key: [[u8; 32]; 2],
+    nonce: u64,
+    ciphertext: [[u8; 32]; CT_LEN],
+    siblings: [[u8; 32]; STATE_DEPTH],
+    prev_choice: [u8; 32],
+    revoting_key_old_actual: [[u8; 32]; 2],
+    no_aux: bool,
+    aux_key: [u8; 32],
+    aux_value: [u8; 32],
+    is_prev_empty: bool,
+}
+
+/// Off-chain mirror of the state committed to by the on-chain `Tally`
+/// account: the nullifier tree, the running message hash and the tally.
+pub struct TallyState {
+    sk: [u8; 32],
+    tree: SparseMerkleTree<STATE_DEPTH, MemStore>,
+    leaves: HashMap<[u8; 32], Leaf>,
+    pub tally: [u64; MAX_CHOICES],
+    pub running_msg_hash: Fr,
+    pub salt: u64,
+    /// Number of messages processed so far.
+    pub processed: usize,
+}
+
+impl TallyState {
+    pub fn new(sk: [u8; 32], initial_salt: u64) -> Self {
+        Self {
+            sk,
+            tree: SparseMerkleTree::new(MemStore::new()).unwrap(),
+            leaves: HashMap::new(),
+            tally: [0; MAX_CHOICES],
+            running_msg_hash: Fr::zero(),
+            salt: initial_salt,
+            processed: 0,
+        }
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.tree.root().unwrap()
+    }
+
+    pub fn tally_hash(&self) -> [u8; 32] {
+        tally_hash(self.salt, &self.tally)
+    }
+
+    /// Applies a message the same way the Tally circuit does. The Vote proof
+    /// checks that every message decrypts, so one that doesn't means the key
+    /// is wrong, and no Tally proof could be made for it.
+    fn apply(&mut self, msg: &Message) -> Result<MessageWitness, TallyError> {
+        let shared_key = ecdh(&self.sk, &msg.eph_key);
+        let [nu, choice, old0, old1, new0, new1] =
+            poseidon_decrypt(&msg.ciphertext, &shared_key, msg.nonce)
+                .ok_or(TallyError::Undecryptable(self.processed))?;
+
+        const {
+            assert!(STATE_DEPTH.is_multiple_of(8));
+        }
+        let mut idx = fr_to_be(nu);
+        idx[..32 - STATE_DEPTH / 8].fill(0);
+
+        let prev = self.leaves.get(&idx).copied();
+        let is_prev_empty = prev.is_none();
+        let prev = prev.unwrap_or_default();
+        let is_valid = prev.revoting_key == [old0, old1];
+
+        let CircomProof {
+            siblings,
+            is_old0,
+            old_key,
+            old_value,
+            ..
+        } = self.tree.get_proof(idx).unwrap();
+
+        if is_valid {
+            let new = Leaf {
+                choice,
+                revoting_key: [new0, new1],
+            };
+            if is_prev_empty {
+                self.tree.add(idx, new.hash())?;
+            } else {
+                self.tree.update(idx, new.hash())?;
+            }
+            self.leaves.insert(idx, new);
+
+            if let Some(prev) = choice_index(prev.choice) {
+                self.tally[prev] -= 1;
+            }
+            if let Some(new) = choice_index(choice) {
+                self.tally[new] += 1;
+            }
+        }
+
+        self.running_msg_hash = poseidon(&[self.running_msg_hash, msg.hash()]);
+        self.processed += 1;
+
+        Ok(MessageWitness {
+            eph_key: [fr_to_be(msg.eph_key.x), fr_to_be(msg.eph_key.y)],
+            nonce: msg.nonce,
+            ciphertext: msg.ciphertext.map(fr_to_be),
+            siblings,
+            prev_choice: fr_to_be(prev.choice),
+            revoting_key_old_actual: prev.revoting_key.map(fr_to_be),
+            no_aux: is_old0,
+            aux_key: old_key,
+            aux_value: old_value,
+            is_prev_empty,
+        })
+    }
+
+    /// Applies up to `MAX_BATCH` messages and returns the circuit inputs
+    /// proving the transition. `salt_after` becomes the new tally salt.
+    pub fn process_batch(
+        &mut self,
+        messages: &[Message],
+        salt_after: u64,
+    ) -> Result<TallyInputs, TallyError> {
+        assert!(!messages.is_empty() && messages.len() <= MAX_BATCH);
+
+        let root_before = self.root();
+        let h_before = fr_to_be(self.running_msg_hash);
+        let tally_hash_before = self.tally_hash();
+        let tally_before = self.tally;
+        let salt_before = self.salt;
+
+        let mut witnesses = Vec::with_capacity(MAX_BATCH);
+        for msg in messages {
+            witnesses.push(self.apply(msg)?);
+        }
+        self.salt = salt_after;
+
+        // disabled slots only have to be well-formed, so repeat the last one
+        let w = |i: usize| &witnesses[i.min(witnesses.len() - 1)];
+
+        Ok(TallyInputs {
+            Root_before: root_before,
+            H_before: h_before,
+            TallyHash_before: tally_hash_before,
+            BatchLen: messages.len() as u64,
+            Tally_before: tally_before,
+            TallySalt_before: salt_before,
+            TallySalt_after: salt_after,
+            SK: self.sk,
+            EphKey: core::array::from_fn(|i| w(i).eph_key),
+            Nonce: core::array::from_fn(|i| w(i).nonce),
+            CT: core::array::from_fn(|i| w(i).ciphertext),
+            Siblings: core::array::from_fn(|i| w(i).siblings),
+            PrevChoice: core::array::from_fn(|i| w(i).prev_choice),
+            RevotingKeyOldActual: core::array::from_fn(|i| w(i).revoting_key_old_actual),
+            NoAux: core::array::from_fn(|i| w(i).no_aux),
+            AuxKey: core::array::from_fn(|i| w(i).aux_key),
+            AuxValue: core::array::from_fn(|i| w(i).aux_value),
+            IsPrevEmpty: core::array::from_fn(|i| w(i).is_prev_empty),
+        })
+    }
+
+    /// Applies messages without building circuit inputs, used to catch up
+    /// with a `Tally` account that is already partially processed.
+    pub fn replay(&mut self, messages: &[Message], salt_after: u64) -> Result<(), TallyError> {
+        for msg in messages {
+            self.apply(msg)?;
+        }
+        self.salt = salt_after;
+        Ok(())
+    }
+}
+
+/// Maps a decrypted choice to its tally slot, the circuit ignores choices
+/// outside of `1..=MAX_CHOICES`.
+fn choice_index(choice: Fr) -> Option<usize> {
+    let bytes = choice.into_bigint().to_bytes_be();
+    if bytes[..bytes.len() - 1].iter().any(|&b| b != 0) {
+        return None;
+    }
+    match *bytes.last().unwrap() as usize {
+        c @ 1..=MAX_CHOICES => Some(c - 1),
+        _ => None,
+    }
+}
+
+pub fn tally_hash(salt: u64, tally: &[u64; MAX_CHOICES]) -> [u8; 32] {
+    let mut preimage = Vec::with_capacity(1 + MAX_CHOICES);
+    preimage.push(Fr::from(salt));
+    preimage.extend(tally.iter().map(|&x| Fr::from(x)));
+    fr_to_be(poseidon(&preimage))
+}
+
+/// Salt committed to after processing messages up to `running_msg_hash`.
+/// It's derived from the secret scalar so that a restarted coordinator
+/// arrives at the same values, and it's hidden until `finish_tally`.
+pub fn derive_salt(sk: &[u8; 32], poll_id: u64, running_msg_hash: Fr) -> u64 {
+    let h = fr_to_be(poseidon(&[
+        Fr::from_be_bytes_mod_order(sk),
+        Fr::from(poll_id),
+        running_msg_hash,
+    ]));
+    u64::from_be_bytes(h[24..].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{poseidon_encrypt, BASE8};
+
+    fn scalar(x: u64) -> [u8; 32] {
+        let mut res = [0; 32];
+        res[24..].copy_from_slice(&x.to_be_bytes());
+        res
+    }
+
+    fn message(pk: &Point, eph_sk: u64, plaintext: [u64; 6]) -> Message {
+        let eph_sk = scalar(eph_sk);
+        let eph_key = BASE8.mul(&eph_sk);
+        let shared_key = ecdh(&eph_sk, pk);
+        Message {
+            eph_key,
+            nonce: eph_sk[31] as u64,
+            ciphertext: poseidon_encrypt(&plaintext.map(Fr::from), &shared_key, eph_sk[31] as u64),
+        }
+    }
+
+    #[test]
+    fn test_revoting() {
+        let sk = scalar(123456789);
+        let pk = BASE8.mul(&sk);
+
+        let messages = [
+            // first vote for 2 with revoting key 5
+            message(&pk, 11, [1000, 2, 0, 0, 5, 5]),
+            // another voter votes for 3
+            message(&pk, 12, [2000, 3, 0, 0, 7, 7]),
+            // revote for 1 with a wrong old key is ignored
+            message(&pk, 13, [1000, 1, 4, 4, 6, 6]),
+            // revote for 1 with the right old key
+            message(&pk, 14, [1000, 1, 5, 5, 6, 6]),
+            // out of range choice withdraws the vote
+            message(&pk, 15, [2000, 9, 7, 7, 8, 8]),
+        ];
+
+        let mut state = TallyState::new(sk, 42);
+        let inputs = state.process_batch(&messages[..3], 43).unwrap();
+        assert_eq!(inputs.BatchLen, 3);
+        assert_eq!(inputs.IsPrevEmpty[..3], [true, true, false]);
+        assert_eq!(inputs.EphKey[3], inputs.EphKey[2]);
+        assert_eq!(state.tally, [0, 1, 1, 0, 0, 0, 0, 0]);
+
+        state.process_batch(&messages[3..], 44).unwrap();
+        assert_eq!(state.tally, [1, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(state.processed, 5);
+
+        let mut replayed = TallyState::new(sk, 42);
+        replayed.replay(&messages, 44).unwrap();
+        assert_eq!(replayed.root(), state.root());
+        assert_eq!(replayed.running_msg_hash, state.running_msg_hash);
+        assert_eq!(replayed.tally_hash(), state.tally_hash());
+    }
+}
@@ -0,0 +1,56 @@
+use ruint::aliases::U256;
+use serde::{ser::SerializeSeq, Serializer};
+
+pub fn ser_be32_as_dec<S>(v: &[u8; 32], s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let n = U256::from_be_bytes(*v);
+    s.serialize_str(&n.to_string())
+}
+
+pub fn ser_arr_be32_as_dec<S>(v: &[[u8; 32]], s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let mut seq = s.serialize_seq(Some(v.len()))?;
+    for b in v {
+        let n = U256::from_be_bytes(*b);
+        seq.serialize_element(&n.to_string())?;
+    }
+    seq.end()
+}
+
+pub fn ser_arr2_be32_as_dec<S, const N: usize>(v: &[[[u8; 32]; N]], s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let mut seq = s.serialize_seq(Some(v.len()))?;
+    for row in v {
+        let row: Vec<_> = row
+            .iter()
+            .map(|b| U256::from_be_bytes(*b).to_string())
+            .collect();
+        seq.serialize_element(&row)?;
+    }
+    seq.end()
+}
+
+pub fn ser_arr_bool_as_u8<S>(v: &[bool], s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let mut seq = s.serialize_seq(Some(v.len()))?;
+    for b in v {
+        seq.serialize_element(&(*b as u8))?;
+    }
+    seq.end()
+}
+
+/// Unix time of the system clock.
+pub fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
@@ -0,0 +1,177 @@
+use anon_vote::events::VoteEvent;
+use ark_bn254::Fr;
+use ark_ff::Zero;
+use parse_logs::parse_logs;
+use solana_client::{
+    rpc_client::GetConfirmedSignaturesForAddress2Config, rpc_config::RpcTransactionConfig,
+};
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
+use solana_tools::solana_transactor::RpcPool;
+use solana_transaction_status::{option_serializer::OptionSerializer, UiTransactionEncoding};
+use sqlx::PgPool;
+use std::str::FromStr;
+
+use crate::{
+    crypto::{fr_from_be, poseidon, Point, CT_LEN},
+    tally::Message,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum VotesError {
+    #[error("Database error: {0}")]
+    Sqlx(#[from] sqlx::Error),
+    #[error("Failed to parse transaction logs")]
+    ParseLogs,
+    #[error("Malformed vote row")]
+    MalformedRow,
+}
+
+impl From<&VoteEvent> for Message {
+    fn from(e: &VoteEvent) -> Self {
+        Message {
+            eph_key: Point::from_be(&e.eph_key),
+            nonce: e.nonce,
+            ciphertext: e.ciphertext.map(|x| fr_from_be(&x)),
+        }
+    }
+}
+
+/// A `votes` row of the indexer, checked against the schema at runtime as
+/// the indexer owns the migrations.
+#[derive(sqlx::FromRow)]
+struct VoteRow {
+    eph_x: Vec<u8>,
+    eph_y: Vec<u8>,
+    nonce: i64,
+    ciphertext: Vec<u8>,
+}
+
+/// Reads the poll's messages in chain order from the indexer database, the
+/// ones indexed before `msg_index` was kept coming first.
+pub async fn fetch_from_indexer(db: &PgPool, poll_id: u64) -> Result<Vec<Message>, VotesError> {
+    let rows: Vec<VoteRow> = sqlx::query_as(
+        r#"
+        SELECT eph_x, eph_y, nonce, ciphertext
+        FROM votes
+        WHERE poll_id = $1
+        ORDER BY msg_index NULLS FIRST, id
+        "#,
+    )
+    .bind(poll_id as i64)
+    .fetch_all(db)
+    .await?;
+
+    rows.into_iter()
+        .map(|r| {
+            let eph_x: [u8; 32] = r.eph_x.try_into().map_err(|_| VotesError::MalformedRow)?;
+            let eph_y: [u8; 32] = r.eph_y.try_into().map_err(|_| VotesError::MalformedRow)?;
+            if r.ciphertext.len() != CT_LEN * 32 {
+                return Err(VotesError::MalformedRow);
+            }
+            let ciphertext: [Fr; CT_LEN] = core::array::from_fn(|i| {
+                fr_from_be(r.ciphertext[i * 32..(i + 1) * 32].try_into().unwrap())
+            });
+            Ok(Message {
+                eph_key: Point {
+                    x: fr_from_be(&eph_x),
+                    y: fr_from_be(&eph_y),
+                },
+                nonce: r.nonce as u64,
+                ciphertext,
+            })
+        })
+        .collect()
+}
+
+/// Reads the poll's messages, oldest first, from the `VoteEvent`s of
+/// successful transactions touching the poll account.
+pub async fn fetch_from_chain(
+    rpc_pool: &RpcPool,
+    poll: Pubkey,
+    poll_id: u64,
+) -> Result<Vec<Message>, VotesError> {
+    let mut signatures = Vec::new();
+    let mut before = None;
+    loop {
+        let chunk = rpc_pool
+            .with_read_rpc_loop(
+                |rpc| async move {
+                    let args = GetConfirmedSignaturesForAddress2Config {
+                        before,
+                        until: None,
+                        limit: None,
+                        commitment: Some(CommitmentConfig::confirmed()),
+                    };
+
+                    rpc.get_signatures_for_address_with_config(&poll, args)
+                        .await
+                },
+                CommitmentConfig::confirmed(),
+            )
+            .await;
+        let Some(last) = chunk.last() else {
+            break;
+        };
+        before = Some(Signature::from_str(&last.signature).unwrap());
+        signatures.extend(
+            chunk
+                .into_iter()
+                .filter(|s| s.err.is_none())
+                .map(|s| Signature::from_str(&s.signature).unwrap()),
+        );
+    }
+
+    let program = anon_vote::ID.to_string();
+    let mut messages = Vec::new();
+    for signature in signatures.into_iter().rev() {
+        let tx = rpc_pool
+            .with_read_rpc_loop(
+                |rpc| async move {
+                    rpc.get_transaction_with_config(
+                        &signature,
+                        RpcTransactionConfig {
+                            encoding: Some(UiTransactionEncoding::Base64),
+                            commitment: Some(CommitmentConfig::confirmed()),
+                            max_supported_transaction_version: Some(0),
+                        },
+                    )
+                    .await
+                },
+                CommitmentConfig::confirmed(),
+            )
+            .await;
+        let Some(meta) = tx.transaction.meta else {
+            continue;
+        };
+        let OptionSerializer::Some(logs) = meta.log_messages else {
+            continue;
+        };
+        let logs: Vec<_> = logs.iter().map(String::as_str).collect();
+        let events: Vec<VoteEvent> =
+            parse_logs(&logs, &program).map_err(|_| VotesError::ParseLogs)?;
+        messages.extend(
+            events
+                .iter()
+                .filter(|e| e.poll_id == poll_id)
+                .map(Message::from),
+        );
+    }
+
+    Ok(messages)
+}
+
+/// Returns the shortest prefix of `messages` whose running hash equals
+/// `running_msg_hash`, or `None` if there's no such prefix.
+pub fn prefix_matching(messages: &[Message], running_msg_hash: Fr) -> Option<usize> {
+    let mut h = Fr::zero();
+    if h == running_msg_hash {
+        return Some(0);
+    }
+    for (i, msg) in messages.iter().enumerate() {
+        h = poseidon(&[h, msg.hash()]);
+        if h == running_msg_hash {
+            return Some(i + 1);
+        }
+    }
+    None
+}
@@ -54,7 +54,9 @@ import {
   createPoll,
   createTally,
   cuLimitInstruction,
+  type FeeToken,
   fetchPlatformConfig,
+  findAssociatedTokenAccount,
   findPoll,
   finishTally,
   type InstructionWithCu,
@@ -1675,6 +1677,8 @@ type PollDetail = {
   fee: string;
   platform_fee: string;
   fee_destination: string;
+  /** SPL token the fees are paid in, lamports if null. */
+  fee_token: { mint: string; vault: string; token_program: string } | null;
   description_url: string;
   census_url: string;
   tally: number[] | null;
@@ -1682,6 +1686,16 @@ type PollDetail = {
   choices: string[];
 };
 
+function pollFeeToken(poll: PollDetail): FeeToken | undefined {
+  return poll.fee_token
+    ? {
+      mint: new PublicKey(poll.fee_token.mint),
+      vault: new PublicKey(poll.fee_token.vault),
+      tokenProgram: new PublicKey(poll.fee_token.token_program),
+    }
+    : undefined;
+}
+
 type PollPage = { items: PollItem[]; total: number };
 const POLL_PAGE_LIMIT = 20;
 
@@ -2015,6 +2029,7 @@ type RelayRequestBody = {
   state_id: string;
   cu_limit?: number;
   accounts: RelayAccountMeta[];
+  fee_vault?: string;
 };
 
 const VotePage: React.FC<{ pollId: bigint }> = ({ pollId }) => {
@@ -2211,13 +2226,15 @@ const VotePage: React.FC<{ pollId: bigint }> = ({ pollId }) => {
 
       let RelayerId = 0n;
       let relayerNu;
+      let relayerKey: PublicKey | undefined;
       if (useRelayer) {
         setStage("Fetching relayer information…");
         const relayerConfig = await fetchRelayerConfig(connection);
         if (!relayerConfig) {
           throw new Error("Relayer not initialized");
         }
-        const relayerIdBuf = relayerConfig.relayer.feeKey.toBuffer();
+        relayerKey = relayerConfig.relayer.feeKey;
+        const relayerIdBuf = relayerKey.toBuffer();
         relayerIdBuf[0] &= (1 << 5) - 1;
         RelayerId = BigInt("0x" + relayerIdBuf.toString("hex"));
         relayerNu = F.toObject(poseidon([sigHash, RelayerId]));
@@ -2286,6 +2303,11 @@ const VotePage: React.FC<{ pollId: bigint }> = ({ pollId }) => {
         const dataHex = bytesToHex(dataU8);
 
         const platform = await fetchPlatformConfig(connection);
+        const feeToken = pollFeeToken(poll);
+        const tokenAccount = (owner: PublicKey) =>
+          findAssociatedTokenAccount(owner, feeToken!.mint, feeToken!.tokenProgram);
+        // absent optional accounts are passed as the program id
+        const placeholder = PROGRAM_ID.toBase58();
 
         const accounts: RelayAccountMeta[] = [
           {
@@ -2301,7 +2323,31 @@ const VotePage: React.FC<{ pollId: bigint }> = ({ pollId }) => {
           {
             is_signer: false,
             is_writable: true,
-            pubkey: platform!.feeDestination.toBase58(),
+            pubkey: (feeToken
+              ? tokenAccount(platform!.feeDestination)
+              : platform!.feeDestination).toBase58(),
+          },
+          {
+            is_signer: false,
+            is_writable: !!feeToken,
+            pubkey: feeToken?.vault.toBase58() ?? placeholder,
+          },
+          {
+            is_signer: false,
+            is_writable: false,
+            pubkey: feeToken?.tokenProgram.toBase58() ?? placeholder,
+          },
+          {
+            is_signer: false,
+            is_writable: false,
+            pubkey: feeToken?.mint.toBase58() ?? placeholder,
+          },
+          {
+            is_signer: false,
+            is_writable: !!feeToken,
+            pubkey: feeToken
+              ? tokenAccount(relayerKey!).toBase58()
+              : placeholder,
           },
         ];
 
@@ -2314,6 +2360,7 @@ const VotePage: React.FC<{ pollId: bigint }> = ({ pollId }) => {
           state_id: String(PollId),
           cu_limit: 200_000,
           accounts,
+          fee_vault: feeToken?.vault.toBase58(),
         };
 
         setStage("Submitting to relayer…");
@@ -2363,6 +2410,7 @@ const VotePage: React.FC<{ pollId: bigint }> = ({ pollId }) => {
           },
           platformFeeDestination: platform!.feeDestination,
           pollFeeDestination: new PublicKey(poll.fee_destination),
+          feeToken: pollFeeToken(poll),
         });
 
         const tx = new Transaction().add(
@@ -0,0 +1,137 @@
+import {
+  Connection,
+  Keypair,
+  PublicKey,
+  sendAndConfirmTransaction,
+  SystemProgram,
+  Transaction,
+  TransactionInstruction,
+} from "@solana/web3.js";
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  findAssociatedTokenAccount,
+  TOKEN_PROGRAM_ID,
+} from "@lincot/anon-vote-sdk";
+
+const MINT_LEN = 82;
+
+export const TOKEN_2022_PROGRAM_ID = new PublicKey(
+  "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb",
+);
+
+/** Creates a mint of `programId` with `authority` as its mint authority. */
+export async function createMint(
+  connection: Connection,
+  payer: Keypair,
+  authority: PublicKey,
+  decimals: number,
+  programId = TOKEN_PROGRAM_ID,
+): Promise<PublicKey> {
+  const mint = new Keypair();
+  const lamports = await connection.getMinimumBalanceForRentExemption(
+    MINT_LEN,
+  );
+
+  // InitializeMint2 without a freeze authority
+  const data = Buffer.alloc(35);
+  data.writeUInt8(20, 0);
+  data.writeUInt8(decimals, 1);
+  authority.toBuffer().copy(data, 2);
+
+  await sendAndConfirmTransaction(
+    connection,
+    new Transaction().add(
+      SystemProgram.createAccount({
+        fromPubkey: payer.publicKey,
+        newAccountPubkey: mint.publicKey,
+        space: MINT_LEN,
+        lamports,
+        programId,
+      }),
+      new TransactionInstruction({
+        programId,
+        keys: [{ pubkey: mint.publicKey, isSigner: false, isWritable: true }],
+        data,
+      }),
+    ),
+    [payer, mint],
+  );
+
+  return mint.publicKey;
+}
+
+/** Creates the associated token accounts of `owners` for `mint`. */
+export async function createTokenAccounts(
+  connection: Connection,
+  payer: Keypair,
+  owners: PublicKey[],
+  mint: PublicKey,
+): Promise<PublicKey[]> {
+  const tx = new Transaction();
+  const accounts = owners.map((owner) =>
+    findAssociatedTokenAccount(owner, mint)
+  );
+  owners.forEach((owner, i) =>
+    tx.add(
+      new TransactionInstruction({
+        programId: ASSOCIATED_TOKEN_PROGRAM_ID,
+        keys: [
+          { pubkey: payer.publicKey, isSigner: true, isWritable: true },
+          { pubkey: accounts[i], isSigner: false, isWritable: true },
+          { pubkey: owner, isSigner: false, isWritable: false },
+          { pubkey: mint, isSigner: false, isWritable: false },
+          {
+            pubkey: SystemProgram.programId,
+            isSigner: false,
+            isWritable: false,
+          },
+          { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+        ],
+        // CreateIdempotent
+        data: Buffer.from([1]),
+      }),
+    )
+  );
+  await sendAndConfirmTransaction(connection, tx, [payer]);
+
+  return accounts;
+}
+
+/** Mints `amount` to `destination`, `authority` being the mint authority. */
+export async function mintTo(
+  connection: Connection,
+  authority: Keypair,
+  mint: PublicKey,
+  destination: PublicKey,
+  amount: bigint,
+): Promise<void> {
+  const data = Buffer.alloc(9);
+  data.writeUInt8(7, 0);
+  data.writeBigUInt64LE(amount, 1);
+
+  await sendAndConfirmTransaction(
+    connection,
+    new Transaction().add(
+      new TransactionInstruction({
+        programId: TOKEN_PROGRAM_ID,
+        keys: [
+          { pubkey: mint, isSigner: false, isWritable: true },
+          { pubkey: destination, isSigner: false, isWritable: true },
+          { pubkey: authority.publicKey, isSigner: true, isWritable: false },
+        ],
+        data,
+      }),
+    ),
+    [authority],
+  );
+}
+
+export async function tokenBalance(
+  connection: Connection,
+  account: PublicKey,
+): Promise<bigint> {
+  return BigInt(
+    (await connection.getTokenAccountBalance(account, "confirmed")).value
+      .amount,
+  );
+}
@@ -0,0 +1,3 @@
+ALTER TABLE polls
+  ADD tally_deadline BIGINT NOT NULL DEFAULT 0,
+  ADD failed         BOOLEAN NOT NULL DEFAULT FALSE;  -- not tallied before the deadline
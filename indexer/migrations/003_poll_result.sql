@@ -0,0 +1,2 @@
+ALTER TABLE polls
+  ADD result_account TEXT;  -- `PollResult` address once the poll is closed
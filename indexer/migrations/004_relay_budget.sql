@@ -0,0 +1,3 @@
+ALTER TABLE polls
+  ADD relay_fee    BIGINT NOT NULL DEFAULT 0,  -- most a relayed message costs the poll
+  ADD relay_budget BIGINT NOT NULL DEFAULT 0;  -- lamports left to pay relayers
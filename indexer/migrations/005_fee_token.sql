@@ -0,0 +1,4 @@
+ALTER TABLE polls
+  ADD fee_mint      TEXT,  -- SPL mint the fees are paid in, lamports if NULL
+  ADD fee_vault     TEXT,  -- token account of the poll paying relayers
+  ADD token_program TEXT;
@@ -0,0 +1,14 @@
+ALTER TABLE voter_polls ADD leaf_index BIGINT; -- position in the census
+
+-- census tree nodes having leaves under them, leaves being level 0
+CREATE TABLE census_nodes (
+  poll_id BIGINT   NOT NULL REFERENCES polls(poll_id) ON DELETE CASCADE,
+  level   SMALLINT NOT NULL,
+  idx     BIGINT   NOT NULL,
+  hash    BYTEA    NOT NULL, -- 32
+  PRIMARY KEY (poll_id, level, idx)
+);
+
+-- ingest existing censuses again to fill the above
+DELETE FROM voter_polls vp USING polls p WHERE p.poll_id = vp.poll_id AND p.census_valid;
+UPDATE polls SET census_valid = NULL WHERE census_valid;
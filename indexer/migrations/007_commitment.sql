@@ -0,0 +1,34 @@
+-- transactions whose events were applied
+CREATE TABLE indexed_txs (
+  seq       BIGSERIAL PRIMARY KEY,
+  signature TEXT    UNIQUE NOT NULL,
+  slot      BIGINT  NOT NULL,
+  finalized BOOLEAN NOT NULL,
+  events    BYTEA   -- borsh `Vec<IndexerEvent>` until finalized, replayed after a rollback
+);
+
+-- on-chain poll fields as they were before a transaction not finalized yet
+-- changed them
+CREATE TABLE poll_undo (
+  seq            BIGINT  NOT NULL REFERENCES indexed_txs(seq) ON DELETE CASCADE,
+  poll_id        BIGINT  NOT NULL REFERENCES polls(poll_id) ON DELETE CASCADE,
+  tally          BIGINT[],
+  failed         BOOLEAN NOT NULL,
+  result_account TEXT,
+  relay_budget   BIGINT  NOT NULL,
+  PRIMARY KEY (seq, poll_id)
+);
+
+ALTER TABLE polls
+  ADD signature TEXT,  -- creating transaction
+  ADD finalized BOOLEAN NOT NULL DEFAULT TRUE;
+ALTER TABLE votes
+  ADD signature TEXT,
+  ADD finalized BOOLEAN NOT NULL DEFAULT TRUE;
+
+CREATE INDEX polls_signature_idx ON polls (signature);
+CREATE INDEX votes_signature_idx ON votes (signature);
+
+-- reading resumes from the finalized cursor
+INSERT INTO cursors (stream, last_sig)
+SELECT 'finalized', last_sig FROM cursors WHERE stream = 'confirmed';
@@ -0,0 +1,13 @@
+-- `running_msg_hash` chain of a poll, NULL for votes indexed before it was
+-- kept and the ones after them, and until the vote before is indexed
+ALTER TABLE votes
+  ADD msg_hash         BYTEA,  -- 32
+  ADD msg_index        BIGINT, -- position in the chain
+  ADD running_msg_hash BYTEA;  -- 32, chain up to and including this vote
+
+CREATE INDEX votes_poll_id_msg_hash_idx ON votes (poll_id, msg_hash);
+CREATE INDEX votes_poll_id_msg_index_idx ON votes (poll_id, msg_index);
+
+ALTER TABLE polls
+  ADD msg_chain_verified       BIGINT NOT NULL DEFAULT 0, -- messages matching the poll account
+  ADD msg_chain_invalid_reason TEXT;
@@ -0,0 +1,8 @@
+ALTER TABLE polls
+  ADD votes_final_at TIMESTAMPTZ, -- the tally or failure was finalized
+  ADD votes_archive  TEXT;        -- archive file the votes were moved to
+
+-- votes of these were deleted right away
+UPDATE polls p SET votes_final_at = now()
+WHERE (tally IS NOT NULL OR failed)
+  AND NOT EXISTS (SELECT 1 FROM poll_undo u WHERE u.poll_id = p.poll_id);
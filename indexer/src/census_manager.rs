@@ -1,6 +1,7 @@
 use bytes::BytesMut;
 use core::{fmt::Write, time::Duration};
 use futures_util::StreamExt;
+use smt_circom::census::{CensusError, CensusTree, Node};
 use sqlx::{Arguments, PgPool};
 use thiserror::Error;
 use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
@@ -9,6 +10,8 @@ use tokio_stream::wrappers::UnboundedReceiverStream;
 #[derive(Debug, Clone)]
 pub struct CensusCmd {
     pub poll_id: i64,
+    /// Root the census has to reproduce, as checked by the Vote circuits.
+    pub census_root: [u8; 32],
     pub url: String,
     pub expected_voters: u64,
 }
@@ -55,6 +58,7 @@ impl CensusManager {
                         &pg_pool,
                         &reqwest_client,
                         cmd.poll_id,
+                        cmd.census_root,
                         cmd.url,
                         cmd.expected_voters,
                     )
@@ -73,6 +77,7 @@ impl CensusManager {
         pg_pool: &PgPool,
         reqwest_client: &reqwest::Client,
         poll_id: i64,
+        census_root: [u8; 32],
         url: String,
         expected_voters: u64,
     ) -> Result<(), CensusManagerError> {
@@ -111,21 +116,32 @@ impl CensusManager {
         let mut buf = BytesMut::new();
         let mut batch: Vec<[u8; 32]> = Vec::with_capacity(10_000);
         let mut total: u64 = 0;
+        let mut tree = CensusTree::default();
 
         // TODO does stream honor timeout?
         while let Some(chunk) = stream.next().await {
             let chunk = chunk?;
             buf.extend_from_slice(&chunk);
-            if total + buf.len() as u64 / 32 > expected_voters {
+            if total + (batch.len() + buf.len() / 32) as u64 > expected_voters {
                 Self::mark_bad(pg_pool, poll_id, "too many voters").await?;
                 return Ok(());
             }
             while buf.len() >= 32 {
                 let mut leaf = [0u8; 32];
                 leaf.copy_from_slice(&buf.split_to(32));
+                if let Err(err) = tree.push(&leaf) {
+                    let index = total + batch.len() as u64;
+                    let reason = match err {
+                        CensusError::Full => format!("leaf {index} doesn't fit the tree"),
+                        _ => format!("leaf {index} is not a field element"),
+                    };
+                    Self::mark_bad(pg_pool, poll_id, &reason).await?;
+                    return Ok(());
+                }
                 batch.push(leaf);
                 if batch.len() >= 10_000 {
-                    Self::insert_leaves_batch(pg_pool, poll_id, &batch).await?;
+                    Self::insert_leaves_batch(pg_pool, poll_id, total, &batch).await?;
+                    Self::insert_nodes(pg_pool, poll_id, &tree.take_nodes()).await?;
                     total += batch.len() as u64;
                     batch.clear();
                 }
@@ -137,7 +153,7 @@ impl CensusManager {
             return Ok(());
         }
         if !batch.is_empty() {
-            Self::insert_leaves_batch(pg_pool, poll_id, &batch).await?;
+            Self::insert_leaves_batch(pg_pool, poll_id, total, &batch).await?;
             total += batch.len() as u64;
         }
 
@@ -151,6 +167,22 @@ impl CensusManager {
             return Ok(());
         }
 
+        let root = tree.finish();
+        if root != census_root {
+            Self::mark_bad(
+                pg_pool,
+                poll_id,
+                &format!(
+                    "census root {} doesn't match the poll's {}",
+                    hex::encode(root),
+                    hex::encode(census_root)
+                ),
+            )
+            .await?;
+            return Ok(());
+        }
+        Self::insert_nodes(pg_pool, poll_id, &tree.take_nodes()).await?;
+
         sqlx::query!(
             r#"UPDATE polls SET census_valid=TRUE WHERE poll_id=$1"#,
             poll_id
@@ -175,34 +207,59 @@ impl CensusManager {
     async fn insert_leaves_batch(
         pg_pool: &PgPool,
         poll_id: i64,
+        first_index: u64,
         leaves: &[[u8; 32]],
     ) -> Result<(), CensusManagerError> {
-        let mut sql = String::from("INSERT INTO voter_polls (poll_id, key_hash) VALUES ");
+        let mut sql =
+            String::from("INSERT INTO voter_polls (poll_id, key_hash, leaf_index) VALUES ");
         let mut args = sqlx::postgres::PgArguments::default();
         for (i, leaf) in leaves.iter().enumerate() {
             if i > 0 {
                 sql.push(',');
             }
-            let p1 = 2 * i + 1;
-            let p2 = 2 * i + 2;
-            write!(&mut sql, "(${p1}, ${p2})").unwrap();
+            let p1 = 3 * i + 1;
+            let p2 = 3 * i + 2;
+            let p3 = 3 * i + 3;
+            write!(&mut sql, "(${p1}, ${p2}, ${p3})").unwrap();
             args.add(poll_id)
                 .map_err(|_| CensusManagerError::SqlxArgs)?;
             args.add(&leaf[..])
                 .map_err(|_| CensusManagerError::SqlxArgs)?;
+            args.add((first_index + i as u64) as i64)
+                .map_err(|_| CensusManagerError::SqlxArgs)?;
         }
         sql.push_str(" ON CONFLICT DO NOTHING");
         sqlx::query_with(&sql, args).execute(pg_pool).await?;
         Ok(())
     }
 
+    async fn insert_nodes(pg_pool: &PgPool, poll_id: i64, nodes: &[Node]) -> sqlx::Result<()> {
+        let levels: Vec<i16> = nodes.iter().map(|n| n.level as i16).collect();
+        let indices: Vec<i64> = nodes.iter().map(|n| n.index as i64).collect();
+        let hashes: Vec<&[u8]> = nodes.iter().map(|n| &n.hash[..]).collect();
+        sqlx::query!(
+            r#"
+            INSERT INTO census_nodes (poll_id, level, idx, hash)
+            SELECT $1, * FROM UNNEST($2::SMALLINT[], $3::BIGINT[], $4::BYTEA[])
+            ON CONFLICT (poll_id, level, idx) DO UPDATE SET hash = EXCLUDED.hash
+            "#,
+            poll_id,
+            &levels,
+            &indices,
+            &hashes as &[&[u8]],
+        )
+        .execute(pg_pool)
+        .await?;
+        Ok(())
+    }
+
     pub(crate) async fn enqueue_unfinished(
         pool: &PgPool,
         census_sender: &UnboundedSender<CensusCmd>,
     ) -> sqlx::Result<()> {
         let rows = sqlx::query!(
             r#"
-        SELECT poll_id, census_url, expected_voters
+        SELECT poll_id, census_root, census_url, expected_voters
         FROM polls
         WHERE census_valid IS NULL OR census_valid = FALSE
         "#
@@ -213,6 +270,7 @@ impl CensusManager {
         for row in rows {
             let cmd = CensusCmd {
                 poll_id: row.poll_id,
+                census_root: row.census_root.try_into().expect("census_root is 32 bytes"),
                 url: row.census_url,
                 expected_voters: row.expected_voters as u64,
             };
@@ -9,6 +9,8 @@ pub(crate) struct IndexerConfig {
     pub addrs: String,
     pub solana: SolanaReaderConfig,
     pub ssl: SslConfig,
+    #[serde(default)]
+    pub vote_retention: VoteRetention,
 }
 
 impl IndexerConfig {
@@ -38,3 +40,20 @@ pub(crate) struct SslConfig {
     pub key: PathBuf,
     pub cert: PathBuf,
 }
+
+/// What happens to the votes of a poll once its tally or failure is
+/// finalized.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(tag = "policy", rename_all = "snake_case")]
+pub(crate) enum VoteRetention {
+    #[default]
+    KeepAll,
+    KeepDays {
+        days: u32,
+    },
+    /// Moved to a gzipped file per poll in `dir`, still served by the export
+    /// endpoint.
+    Archive {
+        dir: PathBuf,
+    },
+}
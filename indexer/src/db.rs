@@ -1,78 +1,340 @@
-use anon_vote::events::{CreatePollEvent, FinishTallyEvent, VoteEvent};
+use anchor_lang::AnchorDeserialize;
+use anon_vote::events::{
+    ClosePollEvent, CreatePollEvent, FinishTallyEvent, PollFailedEvent, PollFundedEvent, VoteEvent,
+};
 use core::mem::transmute;
+use solana_sdk::signature::Signature;
 use sqlx::{postgres::PgQueryResult, PgPool, Postgres, Transaction};
 use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
-use tracing::info;
+use tracing::{info, warn};
 
-use crate::{census_manager::CensusCmd, description_manager::DescriptionCmd, IndexerEvent};
+use crate::{
+    census_manager::CensusCmd,
+    description_manager::DescriptionCmd,
+    indexer_event_processor::{Commitment, TxEvents},
+    msg_chain::next_running_msg_hash,
+    IndexerEvent,
+};
 
+/// Applies events of confirmed transactions right away, recording how to
+/// undo them until they're finalized, so that dropped transactions can be
+/// rolled back.
 pub struct DbManager {
     pool: PgPool,
-    event_receiver: UnboundedReceiver<(String, Vec<IndexerEvent>)>,
+    event_receiver: UnboundedReceiver<TxEvents<IndexerEvent>>,
+    dropped_receiver: UnboundedReceiver<Signature>,
     census_sender: UnboundedSender<CensusCmd>,
     description_sender: UnboundedSender<DescriptionCmd>,
 }
 
+/// Sequence number in `indexed_txs` of a transaction not finalized yet.
+type Pending = Option<i64>;
+
 impl DbManager {
     pub async fn new(
         pool: PgPool,
-        event_receiver: UnboundedReceiver<(String, Vec<IndexerEvent>)>,
+        event_receiver: UnboundedReceiver<TxEvents<IndexerEvent>>,
+        dropped_receiver: UnboundedReceiver<Signature>,
         census_sender: UnboundedSender<CensusCmd>,
         description_sender: UnboundedSender<DescriptionCmd>,
     ) -> sqlx::Result<Self> {
         Ok(Self {
             pool,
             event_receiver,
+            dropped_receiver,
             census_sender,
             description_sender,
         })
     }
 
-    pub async fn get_cursor(&self) -> sqlx::Result<Option<String>> {
+    pub async fn get_cursor(&self, commitment: Commitment) -> sqlx::Result<Option<String>> {
         let row = sqlx::query!(
             r#"SELECT last_sig FROM cursors WHERE stream = $1"#,
-            "confirmed"
+            commitment.stream()
         )
         .fetch_optional(&self.pool)
         .await?;
         Ok(row.map(|r| r.last_sig))
     }
 
+    /// Undoes transactions that weren't finalized, to have them read again
+    /// from the finalized cursor.
+    pub async fn revert_pending(pool: &PgPool) -> sqlx::Result<()> {
+        let mut tx = pool.begin().await?;
+        let rows = sqlx::query!(
+            "SELECT seq, signature FROM indexed_txs WHERE NOT finalized ORDER BY seq DESC"
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        for row in rows {
+            Self::undo(&mut tx, row.seq, &row.signature).await?;
+        }
+        tx.commit().await
+    }
+
     pub async fn execute(&mut self) -> sqlx::Result<()> {
-        while let Some((signature, events)) = self.event_receiver.recv().await {
-            info!("Processed events {events:?}");
-            let mut tx = self.pool.begin().await?;
-            let n_create_polls = events
-                .iter()
-                .filter(|e| matches!(e, IndexerEvent::CreatePoll { .. }))
-                .count();
-            let mut census_cmds = Vec::with_capacity(n_create_polls);
-            let mut description_cmds = Vec::with_capacity(n_create_polls);
-            for event in events {
-                self.on_event(&mut tx, &mut census_cmds, &mut description_cmds, event)
-                    .await?;
-            }
-            Self::upsert_cursor(&mut tx, &signature).await?;
-            tx.commit().await?;
-            for cmd in census_cmds {
-                if let Err(err) = self.census_sender.send(cmd) {
-                    tracing::error!("Failed to send census command through the channel: {err}");
+        loop {
+            tokio::select! {
+                Some(tx_events) = self.event_receiver.recv() => self.on_tx(tx_events).await?,
+                Some(signature) = self.dropped_receiver.recv() => {
+                    self.on_dropped(&signature.to_string()).await?
                 }
+                else => break,
             }
-            for cmd in description_cmds {
-                if let Err(err) = self.description_sender.send(cmd) {
-                    tracing::error!(
-                        "Failed to send description command through the channel: {err}"
-                    );
+        }
+
+        Ok(())
+    }
+
+    async fn on_tx(&self, tx_events: TxEvents<IndexerEvent>) -> sqlx::Result<()> {
+        let TxEvents {
+            commitment,
+            signature,
+            slot,
+            events,
+        } = tx_events;
+        info!("Processed {commitment:?} events {events:?}");
+        let mut tx = self.pool.begin().await?;
+        let mut census_cmds = Vec::new();
+        let mut description_cmds = Vec::new();
+        let indexed = sqlx::query!(
+            "SELECT seq, finalized FROM indexed_txs WHERE signature = $1",
+            signature
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        match indexed {
+            None => {
+                self.apply(
+                    &mut tx,
+                    &mut census_cmds,
+                    &mut description_cmds,
+                    &signature,
+                    slot,
+                    commitment == Commitment::Finalized,
+                    events,
+                )
+                .await?
+            }
+            Some(row) if !row.finalized && commitment == Commitment::Finalized => {
+                Self::promote(&mut tx, row.seq, &signature).await?
+            }
+            Some(_) => {}
+        }
+        Self::upsert_cursor(&mut tx, commitment, &signature).await?;
+        tx.commit().await?;
+        self.send_cmds(census_cmds, description_cmds);
+        Ok(())
+    }
+
+    /// Undoes the dropped transaction and the ones applied after it, then
+    /// applies the latter again.
+    async fn on_dropped(&self, signature: &str) -> sqlx::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        let Some(dropped_seq) = sqlx::query_scalar!(
+            "SELECT seq FROM indexed_txs WHERE signature = $1 AND NOT finalized",
+            signature
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        else {
+            return Ok(());
+        };
+        warn!("Rolling back dropped transaction {signature}");
+
+        let rows = sqlx::query!(
+            r#"
+        SELECT seq, signature, slot, events
+        FROM indexed_txs
+        WHERE seq >= $1 AND NOT finalized
+        ORDER BY seq DESC
+        "#,
+            dropped_seq
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        for row in &rows {
+            Self::undo(&mut tx, row.seq, &row.signature).await?;
+        }
+
+        let mut census_cmds = Vec::new();
+        let mut description_cmds = Vec::new();
+        for row in rows.into_iter().rev().filter(|r| r.signature != signature) {
+            let events = Self::decode_events(row.events);
+            self.apply(
+                &mut tx,
+                &mut census_cmds,
+                &mut description_cmds,
+                &row.signature,
+                row.slot as u64,
+                false,
+                events,
+            )
+            .await?;
+        }
+        tx.commit().await?;
+        self.send_cmds(census_cmds, description_cmds);
+        Ok(())
+    }
+
+    fn decode_events(events: Option<Vec<u8>>) -> Vec<IndexerEvent> {
+        Vec::<IndexerEvent>::try_from_slice(
+            &events.expect("events are kept until the transaction is finalized"),
+        )
+        .expect("events are stored borsh-encoded")
+    }
+
+    fn send_cmds(&self, census_cmds: Vec<CensusCmd>, description_cmds: Vec<DescriptionCmd>) {
+        for cmd in census_cmds {
+            if let Err(err) = self.census_sender.send(cmd) {
+                tracing::error!("Failed to send census command through the channel: {err}");
+            }
+        }
+        for cmd in description_cmds {
+            if let Err(err) = self.description_sender.send(cmd) {
+                tracing::error!("Failed to send description command through the channel: {err}");
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn apply(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        census_cmds: &mut Vec<CensusCmd>,
+        description_cmds: &mut Vec<DescriptionCmd>,
+        signature: &str,
+        slot: u64,
+        finalized: bool,
+        events: Vec<IndexerEvent>,
+    ) -> sqlx::Result<()> {
+        let seq = sqlx::query_scalar!(
+            r#"
+        INSERT INTO indexed_txs (signature, slot, finalized, events)
+        VALUES ($1, $2, $3, $4)
+        RETURNING seq
+        "#,
+            signature,
+            slot as i64,
+            finalized,
+            (!finalized).then(|| anchor_lang::prelude::borsh::to_vec(&events).unwrap())
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+        let pending = (!finalized).then_some(seq);
+        for event in events {
+            self.on_event(tx, census_cmds, description_cmds, signature, pending, event)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Marks the changes of a finalized transaction as such, and does what
+    /// was held off until then.
+    async fn promote(
+        tx: &mut Transaction<'_, Postgres>,
+        seq: i64,
+        signature: &str,
+    ) -> sqlx::Result<()> {
+        let events = sqlx::query_scalar!("SELECT events FROM indexed_txs WHERE seq = $1", seq)
+            .fetch_one(&mut **tx)
+            .await?;
+        for event in Self::decode_events(events) {
+            match event {
+                IndexerEvent::FinishTally(FinishTallyEvent { poll_id, .. })
+                | IndexerEvent::PollFailed(PollFailedEvent { poll_id, .. }) => {
+                    Self::retire_votes(tx, poll_id).await?;
                 }
+                _ => {}
             }
         }
+        sqlx::query!(
+            "UPDATE indexed_txs SET finalized = TRUE, events = NULL WHERE seq = $1",
+            seq
+        )
+        .execute(&mut **tx)
+        .await?;
+        sqlx::query!(
+            "UPDATE polls SET finalized = TRUE WHERE signature = $1",
+            signature
+        )
+        .execute(&mut **tx)
+        .await?;
+        sqlx::query!(
+            "UPDATE votes SET finalized = TRUE WHERE signature = $1",
+            signature
+        )
+        .execute(&mut **tx)
+        .await?;
+        sqlx::query!("DELETE FROM poll_undo WHERE seq = $1", seq)
+            .execute(&mut **tx)
+            .await?;
+        Ok(())
+    }
 
+    async fn undo(
+        tx: &mut Transaction<'_, Postgres>,
+        seq: i64,
+        signature: &str,
+    ) -> sqlx::Result<()> {
+        sqlx::query!(
+            r#"
+        UPDATE polls p
+        SET tally = u.tally, failed = u.failed, result_account = u.result_account,
+            relay_budget = u.relay_budget
+        FROM poll_undo u
+        WHERE u.seq = $1 AND p.poll_id = u.poll_id
+        "#,
+            seq
+        )
+        .execute(&mut **tx)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM votes WHERE signature = $1 AND NOT finalized",
+            signature
+        )
+        .execute(&mut **tx)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM polls WHERE signature = $1 AND NOT finalized",
+            signature
+        )
+        .execute(&mut **tx)
+        .await?;
+        sqlx::query!("DELETE FROM indexed_txs WHERE seq = $1", seq)
+            .execute(&mut **tx)
+            .await?;
+        Ok(())
+    }
+
+    /// Keeps the on-chain fields of a poll as they were before the first
+    /// change by a pending transaction.
+    async fn save_undo(
+        tx: &mut Transaction<'_, Postgres>,
+        pending: Pending,
+        poll_id: u64,
+    ) -> sqlx::Result<()> {
+        let Some(seq) = pending else {
+            return Ok(());
+        };
+        sqlx::query!(
+            r#"
+        INSERT INTO poll_undo (seq, poll_id, tally, failed, result_account, relay_budget)
+        SELECT $1, poll_id, tally, failed, result_account, relay_budget
+        FROM polls WHERE poll_id = $2
+        ON CONFLICT (seq, poll_id) DO NOTHING
+        "#,
+            seq,
+            poll_id as i64
+        )
+        .execute(&mut **tx)
+        .await?;
         Ok(())
     }
 
     async fn upsert_cursor(
         tx: &mut Transaction<'_, Postgres>,
+        commitment: Commitment,
         last_sig: &str,
     ) -> sqlx::Result<PgQueryResult> {
         sqlx::query!(
@@ -83,7 +345,7 @@ impl DbManager {
         SET last_sig = EXCLUDED.last_sig
         WHERE cursors.last_sig <> EXCLUDED.last_sig
         "#,
-            "confirmed",
+            commitment.stream(),
             last_sig
         )
         .execute(&mut **tx)
@@ -95,15 +357,20 @@ impl DbManager {
         tx: &mut Transaction<'_, Postgres>,
         census_cmds: &mut Vec<CensusCmd>,
         description_cmds: &mut Vec<DescriptionCmd>,
+        signature: &str,
+        pending: Pending,
         event: IndexerEvent,
     ) -> sqlx::Result<PgQueryResult> {
         match event {
             IndexerEvent::CreatePoll(event) => {
-                self.on_create_poll(tx, census_cmds, description_cmds, event)
+                self.on_create_poll(tx, census_cmds, description_cmds, signature, pending, event)
                     .await
             }
-            IndexerEvent::Vote(event) => Self::on_vote(tx, event).await,
-            IndexerEvent::FinishTally(event) => Self::on_finish_tally(tx, event).await,
+            IndexerEvent::Vote(event) => Self::on_vote(tx, signature, pending, event).await,
+            IndexerEvent::FinishTally(event) => Self::on_finish_tally(tx, pending, event).await,
+            IndexerEvent::PollFailed(event) => Self::on_poll_failed(tx, pending, event).await,
+            IndexerEvent::ClosePoll(event) => Self::on_close_poll(tx, pending, event).await,
+            IndexerEvent::PollFunded(event) => Self::on_poll_funded(tx, pending, event).await,
         }
     }
 
@@ -112,6 +379,8 @@ impl DbManager {
         tx: &mut Transaction<'_, Postgres>,
         census_cmds: &mut Vec<CensusCmd>,
         description_cmds: &mut Vec<DescriptionCmd>,
+        signature: &str,
+        pending: Pending,
         e: CreatePollEvent,
     ) -> sqlx::Result<PgQueryResult> {
         let res = sqlx::query!(
@@ -119,9 +388,10 @@ impl DbManager {
         INSERT INTO polls (
           poll_id, n_choices, census_root, coord_x, coord_y,
           voting_start_time, voting_end_time, fee, platform_fee, expected_voters,
-          fee_destination, description_url, census_url
+          fee_destination, description_url, census_url, tally_deadline, relay_fee,
+          relay_budget, fee_mint, fee_vault, token_program, signature, finalized
         )
-        VALUES ($1,$2,$3,$4,$5,$6,$7,$8,$9,$10,$11,$12,$13)
+        VALUES ($1,$2,$3,$4,$5,$6,$7,$8,$9,$10,$11,$12,$13,$14,$15,$16,$17,$18,$19,$20,$21)
         ON CONFLICT (poll_id) DO NOTHING
         "#,
             e.poll_id as i64,
@@ -136,13 +406,22 @@ impl DbManager {
             e.n_voters as i64,
             e.fee_destination.to_string(),
             e.description_url,
-            &e.census_url
+            &e.census_url,
+            e.tally_deadline as i64,
+            e.relay_fee as i64,
+            e.relay_budget as i64,
+            e.fee_token.as_ref().map(|t| t.mint.to_string()),
+            e.fee_token.as_ref().map(|t| t.vault.to_string()),
+            e.fee_token.as_ref().map(|t| t.token_program.to_string()),
+            signature,
+            pending.is_none()
         )
         .execute(&mut **tx)
         .await?;
 
         census_cmds.push(CensusCmd {
             poll_id: e.poll_id as i64,
+            census_root: e.census_root,
             url: e.census_url,
             expected_voters: e.n_voters,
         });
@@ -158,20 +437,122 @@ impl DbManager {
 
     async fn on_vote(
         tx: &mut Transaction<'_, Postgres>,
+        signature: &str,
+        pending: Pending,
         e: VoteEvent,
     ) -> sqlx::Result<PgQueryResult> {
+        // the chain is unknown past a vote indexed before it was kept, and
+        // until the vote before this one is indexed
+        let running_before = match e.msg_index {
+            0 => Some([0; 32]),
+            index => Self::running_msg_hash_at(tx, e.poll_id, index - 1).await?,
+        };
+        let running_msg_hash =
+            running_before.and_then(|running| next_running_msg_hash(&running, &e.msg_hash));
         sqlx::query!(
             r#"
         INSERT INTO votes (
-          poll_id, eph_x, eph_y, nonce, ciphertext
+          poll_id, eph_x, eph_y, nonce, ciphertext, signature, finalized,
+          msg_hash, msg_index, running_msg_hash
         )
-        VALUES ($1,$2,$3,$4,$5)
+        VALUES ($1,$2,$3,$4,$5,$6,$7,$8,$9,$10)
         "#,
             e.poll_id as i64,
             &e.eph_key.x,
             &e.eph_key.y,
             e.nonce as i64,
             &unsafe { transmute::<[[u8; 32]; 7], [u8; 224]>(e.ciphertext) },
+            signature,
+            pending.is_none(),
+            &e.msg_hash,
+            e.msg_index as i64,
+            running_msg_hash.as_ref().map(|running| &running[..]),
+        )
+        .execute(&mut **tx)
+        .await?;
+        if let Some(running) = running_msg_hash {
+            Self::extend_msg_chain(tx, e.poll_id, e.msg_index, running).await?;
+        }
+
+        Self::update_relay_budget(tx, pending, e.poll_id, e.relay_budget).await
+    }
+
+    /// `running_msg_hash` after the poll's message at `msg_index`, if known.
+    async fn running_msg_hash_at(
+        tx: &mut Transaction<'_, Postgres>,
+        poll_id: u64,
+        msg_index: u64,
+    ) -> sqlx::Result<Option<[u8; 32]>> {
+        let running = sqlx::query_scalar!(
+            "SELECT running_msg_hash FROM votes WHERE poll_id = $1 AND msg_index = $2",
+            poll_id as i64,
+            msg_index as i64
+        )
+        .fetch_optional(&mut **tx)
+        .await?;
+        Ok(running.flatten().and_then(|h| h.try_into().ok()))
+    }
+
+    /// Fills in the chain of the votes indexed before the one at
+    /// `msg_index`, as when a rolled back vote is indexed again.
+    async fn extend_msg_chain(
+        tx: &mut Transaction<'_, Postgres>,
+        poll_id: u64,
+        mut msg_index: u64,
+        mut running: [u8; 32],
+    ) -> sqlx::Result<()> {
+        loop {
+            msg_index += 1;
+            let Some(next) = sqlx::query!(
+                r#"
+            SELECT id, msg_hash FROM votes
+            WHERE poll_id = $1 AND msg_index = $2 AND running_msg_hash IS NULL
+            "#,
+                poll_id as i64,
+                msg_index as i64
+            )
+            .fetch_optional(&mut **tx)
+            .await?
+            else {
+                return Ok(());
+            };
+            let Some(next_running) = next
+                .msg_hash
+                .and_then(|h| <[u8; 32]>::try_from(h).ok())
+                .and_then(|h| next_running_msg_hash(&running, &h))
+            else {
+                return Ok(());
+            };
+            sqlx::query!(
+                "UPDATE votes SET running_msg_hash = $2 WHERE id = $1",
+                next.id,
+                &next_running[..]
+            )
+            .execute(&mut **tx)
+            .await?;
+            running = next_running;
+        }
+    }
+
+    async fn on_poll_funded(
+        tx: &mut Transaction<'_, Postgres>,
+        pending: Pending,
+        e: PollFundedEvent,
+    ) -> sqlx::Result<PgQueryResult> {
+        Self::update_relay_budget(tx, pending, e.poll_id, e.relay_budget).await
+    }
+
+    async fn update_relay_budget(
+        tx: &mut Transaction<'_, Postgres>,
+        pending: Pending,
+        poll_id: u64,
+        relay_budget: u64,
+    ) -> sqlx::Result<PgQueryResult> {
+        Self::save_undo(tx, pending, poll_id).await?;
+        sqlx::query!(
+            "UPDATE polls SET relay_budget = $2 WHERE poll_id = $1",
+            poll_id as i64,
+            relay_budget as i64,
         )
         .execute(&mut **tx)
         .await
@@ -179,8 +560,10 @@ impl DbManager {
 
     async fn on_finish_tally(
         tx: &mut Transaction<'_, Postgres>,
+        pending: Pending,
         e: FinishTallyEvent,
     ) -> sqlx::Result<PgQueryResult> {
+        Self::save_undo(tx, pending, e.poll_id).await?;
         sqlx::query!(
             r#"
         UPDATE polls
@@ -193,8 +576,168 @@ impl DbManager {
         .execute(&mut **tx)
         .await?;
 
-        sqlx::query!("DELETE FROM votes WHERE poll_id = $1", e.poll_id as i64)
-            .execute(&mut **tx)
+        Self::retire_votes_once_final(tx, pending, e.poll_id).await
+    }
+
+    async fn on_poll_failed(
+        tx: &mut Transaction<'_, Postgres>,
+        pending: Pending,
+        e: PollFailedEvent,
+    ) -> sqlx::Result<PgQueryResult> {
+        Self::save_undo(tx, pending, e.poll_id).await?;
+        sqlx::query!(
+            "UPDATE polls SET failed = TRUE WHERE poll_id = $1",
+            e.poll_id as i64
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Self::retire_votes_once_final(tx, pending, e.poll_id).await
+    }
+
+    async fn on_close_poll(
+        tx: &mut Transaction<'_, Postgres>,
+        pending: Pending,
+        e: ClosePollEvent,
+    ) -> sqlx::Result<PgQueryResult> {
+        Self::save_undo(tx, pending, e.poll_id).await?;
+        sqlx::query!(
+            "UPDATE polls SET result_account = $2 WHERE poll_id = $1",
+            e.poll_id as i64,
+            e.result.to_string(),
+        )
+        .execute(&mut **tx)
+        .await
+    }
+
+    /// Votes aren't needed after the tally, what happens to them is up to the
+    /// retention policy, but a pending tally may still be rolled back.
+    async fn retire_votes_once_final(
+        tx: &mut Transaction<'_, Postgres>,
+        pending: Pending,
+        poll_id: u64,
+    ) -> sqlx::Result<PgQueryResult> {
+        if pending.is_some() {
+            return Ok(PgQueryResult::default());
+        }
+        Self::retire_votes(tx, poll_id).await
+    }
+
+    async fn retire_votes(
+        tx: &mut Transaction<'_, Postgres>,
+        poll_id: u64,
+    ) -> sqlx::Result<PgQueryResult> {
+        sqlx::query!(
+            "UPDATE polls SET votes_final_at = now() WHERE poll_id = $1",
+            poll_id as i64
+        )
+        .execute(&mut **tx)
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::prelude::Pubkey;
+    use tokio::sync::mpsc::unbounded_channel;
+
+    fn create_poll() -> IndexerEvent {
+        IndexerEvent::CreatePoll(CreatePollEvent {
+            poll_id: 1,
+            n_choices: 2,
+            coordinator_key: Default::default(),
+            census_root: [0; 32],
+            voting_start_time: 0,
+            voting_end_time: 1,
+            tally_deadline: 2,
+            platform_fee: 0,
+            relay_fee: 0,
+            relay_budget: 0,
+            fee: 0,
+            fee_destination: Pubkey::default(),
+            fee_token: None,
+            n_voters: 3,
+            description_url: String::new(),
+            census_url: String::new(),
+        })
+    }
+
+    fn vote(msg_index: u64) -> IndexerEvent {
+        let mut msg_hash = [0; 32];
+        msg_hash[31] = msg_index as u8 + 1;
+        IndexerEvent::Vote(VoteEvent {
+            poll_id: 1,
+            eph_key: Default::default(),
+            nonce: msg_index,
+            ciphertext: [[0; 32]; 7],
+            msg_hash,
+            msg_index,
+            relay_budget: 0,
+        })
+    }
+
+    fn confirmed(signature: &str, events: Vec<IndexerEvent>) -> TxEvents<IndexerEvent> {
+        TxEvents {
+            commitment: Commitment::Confirmed,
+            signature: signature.to_owned(),
+            slot: 1,
+            events,
+        }
+    }
+
+    async fn chain(pool: &PgPool) -> Vec<(Option<i64>, Option<Vec<u8>>)> {
+        sqlx::query!(
+            "SELECT msg_index, running_msg_hash FROM votes WHERE poll_id = 1 ORDER BY msg_index"
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| (row.msg_index, row.running_msg_hash))
+        .collect()
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn test_rollback_replay(pool: PgPool) {
+        let (_event_sender, event_receiver) = unbounded_channel();
+        let (_dropped_sender, dropped_receiver) = unbounded_channel();
+        let (census_sender, _census_receiver) = unbounded_channel();
+        let (description_sender, _description_receiver) = unbounded_channel();
+        let db = DbManager::new(
+            pool.clone(),
+            event_receiver,
+            dropped_receiver,
+            census_sender,
+            description_sender,
+        )
+        .await
+        .unwrap();
+
+        db.on_tx(confirmed("poll", vec![create_poll()]))
             .await
+            .unwrap();
+        for (i, signature) in ["a", "b", "c"].into_iter().enumerate() {
+            db.on_tx(confirmed(signature, vec![vote(i as u64)]))
+                .await
+                .unwrap();
+        }
+        let mut running = [0; 32];
+        let expected: Vec<_> = (0..3)
+            .map(|i| {
+                let IndexerEvent::Vote(e) = vote(i) else {
+                    unreachable!()
+                };
+                running = next_running_msg_hash(&running, &e.msg_hash).unwrap();
+                (Some(i as i64), Some(running.to_vec()))
+            })
+            .collect();
+        assert_eq!(chain(&pool).await, expected);
+
+        // `c` is applied again without `b`, which lands later
+        db.on_dropped("b").await.unwrap();
+        assert_eq!(chain(&pool).await, [expected[0].clone(), (Some(2), None)]);
+        db.on_tx(confirmed("b", vec![vote(1)])).await.unwrap();
+        assert_eq!(chain(&pool).await, expected);
     }
 }
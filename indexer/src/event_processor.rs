@@ -1,8 +1,6 @@
 use solana_sdk::pubkey::Pubkey;
 use solana_tools::solana_logs::LogsBunch;
 
-use crate::parse_logs;
-
 pub trait EventListener {
     type Event: parse_logs::Event;
 
@@ -1,4 +1,9 @@
-use anon_vote::events::{CreatePollEvent, FinishTallyEvent, VoteEvent};
+use anon_vote::events::{
+    ClosePollEvent, CreatePollEvent, FinishTallyEvent, PollFailedEvent, PollFundedEvent, VoteEvent,
+};
+// for the borsh derives
+use anchor_lang::prelude::borsh;
+use parse_logs::Event;
 use solana_client::rpc_client::SerializableTransaction;
 use solana_tools::solana_logs::solana_event_listener::LogsBunch;
 use solana_transaction_status::option_serializer::OptionSerializer;
@@ -7,13 +12,16 @@ use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
 use tracing::{error, warn};
 
 use super::solana_reader::Tx;
-use crate::{event_processor::EventListener, parse_logs::Event};
+use crate::event_processor::EventListener;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, anchor_lang::AnchorSerialize, anchor_lang::AnchorDeserialize)]
 pub(crate) enum IndexerEvent {
     CreatePoll(CreatePollEvent),
     Vote(VoteEvent),
     FinishTally(FinishTallyEvent),
+    PollFailed(PollFailedEvent),
+    ClosePoll(ClosePollEvent),
+    PollFunded(PollFundedEvent),
 }
 
 impl Event for IndexerEvent {
@@ -24,6 +32,12 @@ impl Event for IndexerEvent {
             Some(Self::CreatePoll(event))
         } else if let Some(event) = FinishTallyEvent::deserialize(log) {
             Some(Self::FinishTally(event))
+        } else if let Some(event) = PollFailedEvent::deserialize(log) {
+            Some(Self::PollFailed(event))
+        } else if let Some(event) = ClosePollEvent::deserialize(log) {
+            Some(Self::ClosePoll(event))
+        } else if let Some(event) = PollFundedEvent::deserialize(log) {
+            Some(Self::PollFunded(event))
         } else {
             warn!("Unknown event {log}");
             None
@@ -31,17 +45,45 @@ impl Event for IndexerEvent {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Commitment {
+    Confirmed,
+    Finalized,
+}
+
+impl Commitment {
+    /// Name of the stream's cursor.
+    pub(crate) fn stream(self) -> &'static str {
+        match self {
+            Self::Confirmed => "confirmed",
+            Self::Finalized => "finalized",
+        }
+    }
+}
+
+/// Events of a transaction as seen at `commitment`.
+#[derive(Debug)]
+pub(crate) struct TxEvents<T> {
+    pub(crate) commitment: Commitment,
+    pub(crate) signature: String,
+    pub(crate) slot: u64,
+    pub(crate) events: Vec<T>,
+}
+
 pub(crate) struct EventProcessor<T: Event> {
+    commitment: Commitment,
     logs_receiver: UnboundedReceiver<Arc<Tx>>,
-    event_sender: UnboundedSender<(String, Vec<T>)>,
+    event_sender: UnboundedSender<TxEvents<T>>,
 }
 
 impl<T: Event> EventProcessor<T> {
     pub(crate) fn new(
+        commitment: Commitment,
         logs_receiver: UnboundedReceiver<Arc<Tx>>,
-        event_sender: UnboundedSender<(String, Vec<T>)>,
+        event_sender: UnboundedSender<TxEvents<T>>,
     ) -> Self {
         Self {
+            commitment,
             logs_receiver,
             event_sender,
         }
@@ -74,8 +116,14 @@ impl<T: Event> EventProcessor<T> {
 impl<T: Event> EventListener for EventProcessor<T> {
     type Event = T;
 
-    fn on_events(&self, events: Vec<Self::Event>, signature: &str, _slot: u64, _need_check: bool) {
-        if let Err(err) = self.event_sender.send((signature.into(), events)) {
+    fn on_events(&self, events: Vec<Self::Event>, signature: &str, slot: u64, _need_check: bool) {
+        let tx_events = TxEvents {
+            commitment: self.commitment,
+            signature: signature.into(),
+            slot,
+            events,
+        };
+        if let Err(err) = self.event_sender.send(tx_events) {
             error!("Failed to send event through the channel: {}", err);
         }
     }
@@ -2,12 +2,14 @@ use clap::Parser;
 use core::time::Duration;
 use db::DbManager;
 use description_manager::DescriptionManager;
-use indexer_event_processor::IndexerEvent;
+use indexer_event_processor::{Commitment, IndexerEvent};
+use msg_chain::MsgChainChecker;
 use solana_tools::solana_transactor::RpcPool;
 use sqlx::postgres::PgPoolOptions;
 use std::{env, path::PathBuf};
 use tokio::sync::mpsc::unbounded_channel;
 use tracing::error;
+use vote_retention::VoteRetainer;
 
 use crate::{
     census_manager::CensusManager, config::IndexerConfig, indexer_event_processor::EventProcessor,
@@ -20,10 +22,11 @@ mod db;
 mod description_manager;
 mod event_processor;
 mod indexer_event_processor;
-mod parse_logs;
+mod msg_chain;
 mod server;
 mod solana_reader;
 mod utils;
+mod vote_retention;
 
 #[derive(Parser)]
 #[clap(author, version, about, long_about = None)]
@@ -53,31 +56,45 @@ async fn main() {
         .connect(&url)
         .await
         .expect("Expected postgres to connect");
+    DbManager::revert_pending(&pg_pool).await.unwrap();
     CensusManager::enqueue_unfinished(&pg_pool, &census_sender)
         .await
         .unwrap();
     DescriptionManager::enqueue_unfinished(&pg_pool, &description_sender)
         .await
         .unwrap();
+    let mut confirmed_tx_sender = Broadcaster::new();
+    let mut finalized_tx_sender = Broadcaster::new();
+    let mut dropped_tx_sender = Broadcaster::new();
+
+    let confirmed_receiver = confirmed_tx_sender.subscribe();
+    let finalized_receiver = finalized_tx_sender.subscribe();
+    let dropped_receiver = dropped_tx_sender.subscribe();
+
     let mut db_manager = DbManager::new(
         pg_pool.clone(),
         event_receiver,
+        dropped_receiver,
         census_sender,
         description_sender,
     )
     .await
     .unwrap();
 
+    // pending transactions were reverted, so read them again
     let tx_read_from = db_manager
-        .get_cursor()
+        .get_cursor(Commitment::Finalized)
         .await
         .unwrap()
         .map(|s| s.parse().unwrap());
 
-    let mut confirmed_tx_sender = Broadcaster::new();
-    let finalized_tx_sender = Broadcaster::new();
-
-    let logs_receiver = confirmed_tx_sender.subscribe();
+    let msg_chain_checker =
+        MsgChainChecker::new(pg_pool.clone(), rpc_pool.clone(), Duration::from_secs(60));
+    let vote_retainer = VoteRetainer::new(
+        pg_pool.clone(),
+        config.vote_retention,
+        Duration::from_secs(3600),
+    );
 
     let solana_reader = SolanaReader::new(
         rpc_pool,
@@ -87,9 +104,19 @@ async fn main() {
         Duration::from_secs(3),
         confirmed_tx_sender,
         finalized_tx_sender,
+        dropped_tx_sender,
     );
 
-    let mut event_processor = EventProcessor::<IndexerEvent>::new(logs_receiver, event_sender);
+    let mut confirmed_processor = EventProcessor::<IndexerEvent>::new(
+        Commitment::Confirmed,
+        confirmed_receiver,
+        event_sender.clone(),
+    );
+    let mut finalized_processor = EventProcessor::<IndexerEvent>::new(
+        Commitment::Finalized,
+        finalized_receiver,
+        event_sender,
+    );
     let reqwest_client = reqwest::Client::new();
     let census_manager =
         CensusManager::new(pg_pool.clone(), reqwest_client.clone(), census_receiver, 3);
@@ -107,8 +134,12 @@ async fn main() {
         error!("Database manager finished with {res:?}");
     });
     tokio::spawn(async move {
-        event_processor.execute().await;
-        error!("Event processor finished");
+        confirmed_processor.execute().await;
+        error!("Confirmed event processor finished");
+    });
+    tokio::spawn(async move {
+        finalized_processor.execute().await;
+        error!("Finalized event processor finished");
     });
     tokio::spawn(async move {
         census_manager.execute().await;
@@ -118,6 +149,13 @@ async fn main() {
         description_manager.execute().await;
         error!("Description manager finished");
     });
+    tokio::spawn(async move {
+        msg_chain_checker.execute().await;
+        error!("Message chain checker finished");
+    });
+    tokio::spawn(async move {
+        vote_retainer.execute().await;
+    });
     let res = server
         .execute(
             &config.addrs,
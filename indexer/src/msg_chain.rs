@@ -0,0 +1,149 @@
+use anchor_lang::AccountDeserialize;
+use anon_vote::state::Poll;
+use ark_bn254::Fr;
+use core::time::Duration;
+use light_poseidon::{Poseidon, PoseidonBytesHasher};
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
+use solana_tools::solana_transactor::RpcPool;
+use sqlx::PgPool;
+use thiserror::Error;
+
+/// `Poll.running_msg_hash` after a message with `msg_hash` is added, `None`
+/// if the inputs aren't field elements, which the program would reject.
+pub fn next_running_msg_hash(running_msg_hash: &[u8; 32], msg_hash: &[u8; 32]) -> Option<[u8; 32]> {
+    Poseidon::<Fr>::new_circom(2)
+        .ok()?
+        .hash_bytes_be(&[running_msg_hash, msg_hash])
+        .ok()
+}
+
+#[derive(Debug, Error)]
+enum MsgChainError {
+    #[error("Sqlx error {0}")]
+    Sqlx(#[from] sqlx::Error),
+    #[error("Failed to deserialize poll account {0}")]
+    Anchor(#[from] anchor_lang::error::Error),
+}
+
+/// Periodically compares the indexed message chains with the
+/// `running_msg_hash` of the poll accounts.
+pub struct MsgChainChecker {
+    pg_pool: PgPool,
+    rpc_pool: RpcPool,
+    interval: Duration,
+}
+
+impl MsgChainChecker {
+    pub fn new(pg_pool: PgPool, rpc_pool: RpcPool, interval: Duration) -> Self {
+        Self {
+            pg_pool,
+            rpc_pool,
+            interval,
+        }
+    }
+
+    pub async fn execute(self) {
+        let mut interval = tokio::time::interval(self.interval);
+        loop {
+            interval.tick().await;
+            if let Err(err) = self.check_all().await {
+                tracing::error!("message chain check failed: {err}");
+            }
+        }
+    }
+
+    async fn check_all(&self) -> Result<(), MsgChainError> {
+        // polls still collecting votes or awaiting the tally
+        let poll_ids = sqlx::query_scalar!(
+            r#"
+            SELECT DISTINCT v.poll_id FROM votes v
+            JOIN polls p ON p.poll_id = v.poll_id AND p.votes_final_at IS NULL
+            WHERE v.running_msg_hash IS NOT NULL
+            "#
+        )
+        .fetch_all(&self.pg_pool)
+        .await?;
+        for poll_id in poll_ids {
+            self.check(poll_id).await?;
+        }
+        Ok(())
+    }
+
+    async fn check(&self, poll_id: i64) -> Result<(), MsgChainError> {
+        let address =
+            Pubkey::find_program_address(&[b"POLL", &poll_id.to_le_bytes()], &anon_vote::ID).0;
+        let account = self
+            .rpc_pool
+            .with_read_rpc_loop(
+                |rpc| async move {
+                    rpc.get_account_with_commitment(&address, CommitmentConfig::confirmed())
+                        .await
+                },
+                CommitmentConfig::confirmed(),
+            )
+            .await
+            .value;
+        let Some(account) = account else {
+            return Ok(());
+        };
+        let poll = Poll::try_deserialize(&mut &account.data[..])?;
+        if poll.n_messages == 0 {
+            return Ok(());
+        }
+
+        let Some(indexed) = sqlx::query_scalar!(
+            "SELECT running_msg_hash FROM votes WHERE poll_id = $1 AND msg_index = $2",
+            poll_id,
+            poll.n_messages as i64 - 1
+        )
+        .fetch_optional(&self.pg_pool)
+        .await?
+        .flatten() else {
+            // the indexer is behind
+            return Ok(());
+        };
+
+        if indexed == poll.running_msg_hash {
+            sqlx::query!(
+                r#"
+                UPDATE polls SET msg_chain_verified = $2, msg_chain_invalid_reason = NULL
+                WHERE poll_id = $1
+                "#,
+                poll_id,
+                poll.n_messages as i64
+            )
+            .execute(&self.pg_pool)
+            .await?;
+        } else {
+            let reason = format!(
+                "running_msg_hash after {} messages is {}, indexed {}",
+                poll.n_messages,
+                hex::encode(poll.running_msg_hash),
+                hex::encode(&indexed)
+            );
+            tracing::error!("message chain of poll {poll_id} diverged: {reason}");
+            sqlx::query!(
+                "UPDATE polls SET msg_chain_invalid_reason = $2 WHERE poll_id = $1",
+                poll_id,
+                reason
+            )
+            .execute(&self.pg_pool)
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_running_msg_hash() {
+        assert_eq!(
+            hex::encode(next_running_msg_hash(&[0; 32], &[0; 32]).unwrap()),
+            "2098f5fb9e239eab3ceac3f27b81e481dc3124d55ffed523a839ee8446b64864"
+        );
+        assert_eq!(next_running_msg_hash(&[0; 32], &[0xff; 32]), None);
+    }
+}
@@ -1,11 +1,20 @@
 use actix_cors::Cors;
-use actix_web::{get, web, App, HttpServer, Responder};
+use actix_web::{
+    get,
+    http::header::{ContentEncoding, CONTENT_ENCODING},
+    web, App, HttpResponse, HttpServer, Responder,
+};
+use bytes::Bytes;
+use futures_util::{stream, TryStreamExt};
 use openssl::ssl::{SslAcceptor, SslFiletype, SslMethod};
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, DisplayFromStr};
 use sqlx::PgPool;
+use tokio_util::io::ReaderStream;
 
-use crate::config::SslConfig;
+use smt_circom::census;
+
+use crate::{config::SslConfig, vote_retention};
 
 #[derive(Clone)]
 struct AppState {
@@ -40,6 +49,9 @@ impl Server {
                 .wrap(actix_web::middleware::Compress::default())
                 .service(get_poll)
                 .service(list_votes)
+                .service(export_votes)
+                .service(vote_receipt)
+                .service(census_proof)
                 .service(polls_by_voter)
                 .service(polls_by_coordinator)
         })
@@ -61,35 +73,96 @@ struct PollOut {
     coordinator_key: (String, String),
     voting_start_time: u64,
     voting_end_time: u64,
+    tally_deadline: u64,
+    /// No tally was published before `tally_deadline`.
+    failed: bool,
     #[serde_as(as = "DisplayFromStr")]
     fee: u64,
     #[serde_as(as = "DisplayFromStr")]
     platform_fee: u64,
+    /// Left in the poll to pay relayers, lamports or `fee_token` amount.
+    #[serde_as(as = "DisplayFromStr")]
+    relay_budget: u64,
+    /// Relayed messages the budget still pays for, none if relaying is free.
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    relay_messages_left: Option<u64>,
     fee_destination: String,
+    /// SPL token the fees are paid in, lamports if absent.
+    fee_token: Option<FeeTokenOut>,
     description_url: String,
     census_url: String,
     tally: Option<Vec<u64>>,
+    /// The `PollResult` account holding the outcome once the poll is closed.
+    result_account: Option<String>,
+    /// Leading messages whose `running_msg_hash` was seen to match the poll
+    /// account.
+    #[serde_as(as = "DisplayFromStr")]
+    msg_chain_verified: u64,
+    /// Why the indexed messages don't reproduce the poll's
+    /// `running_msg_hash`.
+    msg_chain_invalid_reason: Option<String>,
+}
+
+#[derive(Serialize)]
+struct FeeTokenOut {
+    mint: String,
+    /// Token account of the poll paying relayers.
+    vault: String,
+    token_program: String,
+}
+
+#[derive(Deserialize)]
+struct CommitmentQuery {
+    /// Leave out what transactions not finalized yet did.
+    #[serde(default)]
+    finalized: bool,
 }
 
 #[get("/polls/{poll_id}")]
 async fn get_poll(
     state: web::Data<AppState>,
     path: web::Path<i64>,
+    query: web::Query<CommitmentQuery>,
 ) -> actix_web::Result<impl Responder> {
     let poll_id = path.into_inner();
-    let rec = sqlx::query!(
+    let mut rec = sqlx::query!(
         r#"
         SELECT poll_id, title, choices, census_root, coord_x, coord_y,
-               voting_start_time, voting_end_time, fee, platform_fee,
-               fee_destination, description_url, census_url, tally
+               voting_start_time, voting_end_time, tally_deadline, failed, fee, platform_fee,
+               relay_fee, relay_budget, fee_mint, fee_vault, token_program,
+               fee_destination, description_url, census_url, tally, result_account,
+               msg_chain_verified, msg_chain_invalid_reason
         FROM polls WHERE poll_id = $1 AND title IS NOT NULL AND census_valid IS TRUE
+            AND (finalized OR NOT $2)
         "#,
-        poll_id
+        poll_id,
+        query.finalized
     )
     .fetch_optional(&state.pool)
     .await
     .map_err(|_| actix_web::error::ErrorInternalServerError("db"))?;
 
+    if let Some(p) = rec.as_mut().filter(|_| query.finalized) {
+        // the fields before the first change not finalized yet
+        let undo = sqlx::query!(
+            r#"
+            SELECT tally, failed, result_account, relay_budget
+            FROM poll_undo WHERE poll_id = $1
+            ORDER BY seq LIMIT 1
+            "#,
+            poll_id
+        )
+        .fetch_optional(&state.pool)
+        .await
+        .map_err(|_| actix_web::error::ErrorInternalServerError("db"))?;
+        if let Some(u) = undo {
+            p.tally = u.tally;
+            p.failed = u.failed;
+            p.result_account = u.result_account;
+            p.relay_budget = u.relay_budget;
+        }
+    }
+
     if let Some(p) = rec {
         let out = PollOut {
             poll_id: p.poll_id as u64,
@@ -103,12 +176,26 @@ async fn get_poll(
             coordinator_key: (hex::encode(&p.coord_x), hex::encode(&p.coord_y)),
             voting_start_time: p.voting_start_time as u64,
             voting_end_time: p.voting_end_time as u64,
+            tally_deadline: p.tally_deadline as u64,
+            failed: p.failed,
             fee: p.fee as u64,
             platform_fee: p.platform_fee as u64,
+            relay_budget: p.relay_budget as u64,
+            relay_messages_left: (p.relay_fee > 0).then(|| (p.relay_budget / p.relay_fee) as u64),
             fee_destination: p.fee_destination,
+            fee_token: p.fee_mint.zip(p.fee_vault).zip(p.token_program).map(
+                |((mint, vault), token_program)| FeeTokenOut {
+                    mint,
+                    vault,
+                    token_program,
+                },
+            ),
             description_url: p.description_url,
             census_url: p.census_url,
             tally: p.tally.map(bytemuck::cast_vec),
+            result_account: p.result_account,
+            msg_chain_verified: p.msg_chain_verified as u64,
+            msg_chain_invalid_reason: p.msg_chain_invalid_reason,
         };
         Ok(web::Json(out))
     } else {
@@ -126,12 +213,16 @@ struct VoteOut {
     #[serde_as(as = "DisplayFromStr")]
     nonce: u64,
     ciphertext: String,
+    msg_hash: Option<String>,
 }
 
 #[derive(Deserialize)]
 struct VotesQuery {
     limit: Option<i64>,
     after: Option<i64>,
+    /// Only votes of finalized transactions.
+    #[serde(default)]
+    finalized: bool,
 }
 
 #[get("/polls/{poll_id}/votes")]
@@ -145,8 +236,9 @@ async fn list_votes(
     let after_id = query.after.unwrap_or(0);
 
     let total = sqlx::query_scalar!(
-        r#"SELECT COUNT(*)::BIGINT FROM votes WHERE poll_id = $1"#,
-        poll_id
+        r#"SELECT COUNT(*)::BIGINT FROM votes WHERE poll_id = $1 AND (finalized OR NOT $2)"#,
+        poll_id,
+        query.finalized
     )
     .fetch_one(&state.pool)
     .await
@@ -155,15 +247,16 @@ async fn list_votes(
 
     let rows = sqlx::query!(
         r#"
-        SELECT id, eph_x, eph_y, nonce, ciphertext
+        SELECT id, eph_x, eph_y, nonce, ciphertext, msg_hash
         FROM votes
-        WHERE poll_id = $1 AND id > $2
+        WHERE poll_id = $1 AND id > $2 AND (finalized OR NOT $4)
         ORDER BY id ASC
         LIMIT $3
         "#,
         poll_id,
         after_id,
-        limit + 1
+        limit + 1,
+        query.finalized
     )
     .fetch_all(&state.pool)
     .await
@@ -184,6 +277,7 @@ async fn list_votes(
             eph_y: hex::encode(&r.eph_y),
             nonce: r.nonce as u64,
             ciphertext: hex::encode(&r.ciphertext),
+            msg_hash: r.msg_hash.map(hex::encode),
         })
         .collect();
 
@@ -201,6 +295,214 @@ async fn list_votes(
     }))
 }
 
+/// Every vote of the poll as binary records in on-chain order, see
+/// `vote_retention`. Archived votes are served from the gzipped archive as is.
+#[get("/polls/{poll_id}/votes/export")]
+async fn export_votes(
+    state: web::Data<AppState>,
+    path: web::Path<i64>,
+    query: web::Query<CommitmentQuery>,
+) -> actix_web::Result<HttpResponse> {
+    let poll_id = path.into_inner();
+    let archive = sqlx::query_scalar!(
+        "SELECT votes_archive FROM polls WHERE poll_id = $1",
+        poll_id
+    )
+    .fetch_optional(&state.pool)
+    .await
+    .map_err(|_| actix_web::error::ErrorInternalServerError("db"))?
+    .ok_or_else(|| actix_web::error::ErrorNotFound("poll not found"))?;
+
+    if let Some(archive) = archive {
+        let file = tokio::fs::File::open(archive)
+            .await
+            .map_err(|_| actix_web::error::ErrorInternalServerError("archive"))?;
+        return Ok(HttpResponse::Ok()
+            .content_type("application/octet-stream")
+            .insert_header((CONTENT_ENCODING, ContentEncoding::Gzip))
+            .streaming(ReaderStream::new(file)));
+    }
+
+    const PAGE: i64 = 1000;
+    let pool = state.pool.clone();
+    let finalized = query.finalized;
+    let pages = stream::try_unfold(Some(0), move |after_id| {
+        let pool = pool.clone();
+        async move {
+            let Some(after_id) = after_id else {
+                return Ok(None);
+            };
+            let rows = sqlx::query!(
+                r#"
+                SELECT id, eph_x, eph_y, nonce, ciphertext, msg_hash
+                FROM votes
+                WHERE poll_id = $1 AND id > $2 AND (finalized OR NOT $4)
+                ORDER BY id ASC
+                LIMIT $3
+                "#,
+                poll_id,
+                after_id,
+                PAGE,
+                finalized
+            )
+            .fetch_all(&pool)
+            .await?;
+            if rows.is_empty() {
+                return Ok(None);
+            }
+            let mut records = Vec::with_capacity(rows.len() * vote_retention::RECORD_LEN);
+            for r in &rows {
+                vote_retention::encode_record(
+                    &mut records,
+                    &r.eph_x,
+                    &r.eph_y,
+                    r.nonce,
+                    &r.ciphertext,
+                    r.msg_hash.as_deref(),
+                );
+            }
+            let next = (rows.len() as i64 == PAGE).then(|| rows[rows.len() - 1].id);
+            Ok(Some((Bytes::from(records), next)))
+        }
+    })
+    .map_err(|_: sqlx::Error| actix_web::error::ErrorInternalServerError("db"));
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/octet-stream")
+        .streaming(pages))
+}
+
+#[serde_as]
+#[derive(Serialize)]
+struct VoteReceiptOut {
+    msg_hash: String,
+    /// Position of the message in the poll's `running_msg_hash` chain.
+    #[serde_as(as = "DisplayFromStr")]
+    msg_index: u64,
+    /// The chain before and after the message, `running_msg_hash_after =
+    /// Poseidon(running_msg_hash_before, msg_hash)`.
+    running_msg_hash_before: String,
+    running_msg_hash_after: String,
+    /// The poll account's `running_msg_hash` was seen to include the message.
+    verified: bool,
+    /// The vote's transaction is finalized.
+    finalized: bool,
+    signature: Option<String>,
+}
+
+#[get("/polls/{poll_id}/votes/{msg_hash}")]
+async fn vote_receipt(
+    state: web::Data<AppState>,
+    path: web::Path<(i64, String)>,
+) -> actix_web::Result<impl Responder> {
+    let (poll_id, msg_hash) = path.into_inner();
+    let mut msg_hash_arr = [0u8; 32];
+    hex::decode_to_slice(&*msg_hash, &mut msg_hash_arr)
+        .map_err(|_| actix_web::error::ErrorBadRequest("bad hex"))?;
+
+    let r = sqlx::query!(
+        r#"
+        SELECT v.msg_index AS "msg_index!", v.running_msg_hash AS "running_msg_hash!",
+               v.finalized, v.signature, p.msg_chain_verified,
+               prev.running_msg_hash AS "prev_running_msg_hash?"
+        FROM votes v
+        JOIN polls p ON p.poll_id = v.poll_id
+        LEFT JOIN votes prev ON prev.poll_id = v.poll_id AND prev.msg_index = v.msg_index - 1
+        WHERE v.poll_id = $1 AND v.msg_hash = $2 AND v.running_msg_hash IS NOT NULL
+        ORDER BY v.msg_index
+        LIMIT 1
+        "#,
+        poll_id,
+        &msg_hash_arr
+    )
+    .fetch_optional(&state.pool)
+    .await
+    .map_err(|_| actix_web::error::ErrorInternalServerError("db"))?
+    .ok_or_else(|| actix_web::error::ErrorNotFound("vote not found"))?;
+
+    Ok(web::Json(VoteReceiptOut {
+        msg_hash: hex::encode(msg_hash_arr),
+        msg_index: r.msg_index as u64,
+        running_msg_hash_before: hex::encode(r.prev_running_msg_hash.unwrap_or(vec![0; 32])),
+        running_msg_hash_after: hex::encode(&r.running_msg_hash),
+        verified: r.msg_index < r.msg_chain_verified,
+        finalized: r.finalized,
+        signature: r.signature,
+    }))
+}
+
+#[serde_as]
+#[derive(Serialize)]
+struct CensusProofOut {
+    #[serde_as(as = "DisplayFromStr")]
+    leaf_index: u64,
+    /// Siblings from the leaf up as decimal field elements, the `Path` input
+    /// of the Vote circuits.
+    path: Vec<String>,
+    /// The `PathPos` input, 1 where the path goes through a right child.
+    path_pos: Vec<u8>,
+}
+
+#[get("/polls/{poll_id}/census/proof/{leaf}")]
+async fn census_proof(
+    state: web::Data<AppState>,
+    path: web::Path<(i64, String)>,
+) -> actix_web::Result<impl Responder> {
+    let (poll_id, leaf) = path.into_inner();
+    let mut leaf_arr = [0u8; 32];
+    hex::decode_to_slice(&*leaf, &mut leaf_arr)
+        .map_err(|_| actix_web::error::ErrorBadRequest("bad hex"))?;
+
+    let leaf_index = sqlx::query_scalar!(
+        r#"
+        SELECT vp.leaf_index
+        FROM voter_polls vp
+        JOIN polls p ON p.poll_id = vp.poll_id AND p.census_valid = TRUE
+        WHERE vp.poll_id = $1 AND vp.key_hash = $2
+        "#,
+        poll_id,
+        &leaf_arr
+    )
+    .fetch_optional(&state.pool)
+    .await
+    .map_err(|_| actix_web::error::ErrorInternalServerError("db"))?
+    .flatten()
+    .ok_or_else(|| actix_web::error::ErrorNotFound("leaf not in census"))?
+        as u64;
+
+    let (levels, indices): (Vec<i16>, Vec<i64>) = census::path_siblings(leaf_index)
+        .map(|(level, index)| (level as i16, index as i64))
+        .unzip();
+    let rows = sqlx::query!(
+        r#"
+        SELECT level, hash
+        FROM census_nodes
+        WHERE poll_id = $1
+            AND (level, idx) IN (SELECT * FROM UNNEST($2::SMALLINT[], $3::BIGINT[]))
+        "#,
+        poll_id,
+        &levels,
+        &indices
+    )
+    .fetch_all(&state.pool)
+    .await
+    .map_err(|_| actix_web::error::ErrorInternalServerError("db"))?;
+
+    let mut siblings: Vec<[u8; 32]> = (0..census::CENSUS_DEPTH).map(census::empty_node).collect();
+    for row in rows {
+        siblings[row.level as usize] = row
+            .hash
+            .try_into()
+            .map_err(|_| actix_web::error::ErrorInternalServerError("db"))?;
+    }
+
+    Ok(web::Json(CensusProofOut {
+        leaf_index,
+        path: siblings.iter().map(census::to_decimal).collect(),
+        path_pos: census::path_pos(leaf_index).to_vec(),
+    }))
+}
+
 #[serde_as]
 #[derive(Serialize)]
 struct PollItem {
@@ -222,6 +524,9 @@ struct PollPage {
 struct UserPollsQuery {
     limit: Option<i64>,
     after: Option<i64>,
+    /// Only polls created by finalized transactions.
+    #[serde(default)]
+    finalized: bool,
 }
 
 #[actix_web::get("/voters/{leaf}/polls")]
@@ -249,13 +554,14 @@ async fn polls_by_voter(
         FROM voter_polls vp
         JOIN polls p ON p.poll_id = vp.poll_id
             AND census_valid = TRUE AND title IS NOT NULL
-        WHERE vp.key_hash = $1 AND p.id <= $2
+        WHERE vp.key_hash = $1 AND p.id <= $2 AND (p.finalized OR NOT $4)
         ORDER BY p.id DESC
         LIMIT $3
         "#,
         &leaf_arr,
         total as i64 - after,
-        limit + 1
+        limit + 1,
+        q.finalized
     )
     .fetch_all(&state.pool)
     .await
@@ -304,14 +610,15 @@ async fn polls_by_coordinator(
                description_url, census_url
         FROM polls
         WHERE coord_x=$1 AND coord_y=$2 AND id <= $3
-            AND census_valid = TRUE AND title IS NOT NULL
+            AND census_valid = TRUE AND title IS NOT NULL AND (finalized OR NOT $5)
         ORDER BY id DESC
         LIMIT $4
         "#,
         &xy_arr[..32],
         &xy_arr[32..],
         total as i64 - after,
-        limit + 1
+        limit + 1,
+        q.finalized
     )
     .fetch_all(&state.pool)
     .await
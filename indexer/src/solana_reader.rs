@@ -19,8 +19,13 @@ use solana_transaction_status::{
     TransactionConfirmationStatus, TransactionDetails, UiTransactionEncoding,
     UiTransactionStatusMeta,
 };
-use std::{collections::HashMap, str::FromStr, sync::Arc, time::Duration};
-use tracing::debug;
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
+use tracing::{debug, warn};
 
 use super::utils::Broadcaster;
 
@@ -41,11 +46,14 @@ pub struct SolanaReader {
     polling_interval: Duration,
     confirmed_tx_sender: Broadcaster<Arc<Tx>>,
     finalized_tx_sender: Broadcaster<Arc<Tx>>,
+    /// Confirmed transactions that won't be finalized.
+    dropped_tx_sender: Broadcaster<Signature>,
     /// Transactions that were confirmed but not yet finalized.
     confirmed_txs: HashMap<Signature, Arc<Tx>>,
 }
 
 impl SolanaReader {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         rpc_pool: RpcPool,
         program: Pubkey,
@@ -54,6 +62,7 @@ impl SolanaReader {
         polling_interval: Duration,
         confirmed_tx_sender: Broadcaster<Arc<Tx>>,
         finalized_tx_sender: Broadcaster<Arc<Tx>>,
+        dropped_tx_sender: Broadcaster<Signature>,
     ) -> Self {
         Self {
             rpc_pool,
@@ -62,6 +71,7 @@ impl SolanaReader {
             tx_read_from,
             confirmed_tx_sender,
             finalized_tx_sender,
+            dropped_tx_sender,
             polling_interval,
             confirmed_txs: HashMap::new(),
         }
@@ -127,6 +137,8 @@ impl SolanaReader {
                 }
             }
 
+            self.drop_unlisted(&sig_chunks);
+
             let mut blocks_to_fetch =
                 Vec::<(Slot, Vec<(Signature, TransactionConfirmationStatus)>)>::new();
 
@@ -250,6 +262,40 @@ impl SolanaReader {
         }
     }
 
+    /// Drops confirmed transactions that are missing from the listing while
+    /// a later slot got finalized, so they can no longer be.
+    fn drop_unlisted(&mut self, sig_chunks: &[Vec<RpcConfirmedTransactionStatusWithSignature>]) {
+        let Some(finalized_slot) = sig_chunks
+            .iter()
+            .flatten()
+            .filter(|sig| sig.confirmation_status == Some(TransactionConfirmationStatus::Finalized))
+            .map(|sig| sig.slot)
+            .max()
+        else {
+            return;
+        };
+        let listed: HashSet<&str> = sig_chunks
+            .iter()
+            .flatten()
+            .map(|sig| sig.signature.as_str())
+            .collect();
+        let dropped: Vec<Signature> = self
+            .confirmed_txs
+            .iter()
+            .filter(|(sig, tx)| {
+                tx.slot < finalized_slot && !listed.contains(sig.to_string().as_str())
+            })
+            .map(|(sig, _)| *sig)
+            .collect();
+        for sig in dropped {
+            warn!("Confirmed tx {sig} was dropped");
+            self.confirmed_txs.remove(&sig);
+            self.dropped_tx_sender
+                .send(sig)
+                .expect("Failed to send signature over a channel");
+        }
+    }
+
     async fn fetch_tx(
         rpc_pool: RpcPool,
         signature: Signature,
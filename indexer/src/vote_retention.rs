@@ -0,0 +1,177 @@
+//! Votes are exported as `eph_x || eph_y || nonce || ciphertext || msg_hash`
+//! records in on-chain order, the nonce as 8 big-endian bytes and `msg_hash`
+//! zero for votes indexed before it was kept.
+
+use core::time::Duration;
+use flate2::{write::GzEncoder, Compression};
+use sqlx::PgPool;
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+use thiserror::Error;
+
+use crate::config::VoteRetention;
+
+pub const RECORD_LEN: usize = 32 + 32 + 8 + 7 * 32 + 32;
+
+pub fn encode_record(
+    out: &mut Vec<u8>,
+    eph_x: &[u8],
+    eph_y: &[u8],
+    nonce: i64,
+    ciphertext: &[u8],
+    msg_hash: Option<&[u8]>,
+) {
+    out.extend_from_slice(eph_x);
+    out.extend_from_slice(eph_y);
+    out.extend_from_slice(&(nonce as u64).to_be_bytes());
+    out.extend_from_slice(ciphertext);
+    out.extend_from_slice(msg_hash.unwrap_or(&[0; 32]));
+}
+
+#[derive(Debug, Error)]
+enum VoteRetentionError {
+    #[error("Sqlx error {0}")]
+    Sqlx(#[from] sqlx::Error),
+    #[error("IO error {0}")]
+    Io(#[from] io::Error),
+}
+
+/// Applies the retention policy to votes of polls whose tally or failure is
+/// finalized.
+pub struct VoteRetainer {
+    pg_pool: PgPool,
+    retention: VoteRetention,
+    interval: Duration,
+}
+
+impl VoteRetainer {
+    pub fn new(pg_pool: PgPool, retention: VoteRetention, interval: Duration) -> Self {
+        Self {
+            pg_pool,
+            retention,
+            interval,
+        }
+    }
+
+    pub async fn execute(self) {
+        let mut interval = tokio::time::interval(self.interval);
+        loop {
+            interval.tick().await;
+            let res = match &self.retention {
+                VoteRetention::KeepAll => return,
+                VoteRetention::KeepDays { days } => self.delete_older(*days).await,
+                VoteRetention::Archive { dir } => self.archive_all(dir).await,
+            };
+            if let Err(err) = res {
+                tracing::error!("vote retention failed: {err}");
+            }
+        }
+    }
+
+    async fn delete_older(&self, days: u32) -> Result<(), VoteRetentionError> {
+        let res = sqlx::query!(
+            r#"
+            DELETE FROM votes v USING polls p
+            WHERE v.poll_id = p.poll_id
+                AND p.votes_final_at < now() - make_interval(days => $1)
+            "#,
+            days as i32
+        )
+        .execute(&self.pg_pool)
+        .await?;
+        if res.rows_affected() > 0 {
+            tracing::info!(
+                "deleted {} votes older than {days} days",
+                res.rows_affected()
+            );
+        }
+        Ok(())
+    }
+
+    async fn archive_all(&self, dir: &Path) -> Result<(), VoteRetentionError> {
+        let poll_ids = sqlx::query_scalar!(
+            r#"
+            SELECT poll_id FROM polls p
+            WHERE votes_final_at IS NOT NULL AND votes_archive IS NULL
+                AND EXISTS (SELECT 1 FROM votes v WHERE v.poll_id = p.poll_id)
+            "#
+        )
+        .fetch_all(&self.pg_pool)
+        .await?;
+        for poll_id in poll_ids {
+            self.archive(dir, poll_id).await?;
+            tracing::info!("archived votes of poll {poll_id}");
+        }
+        Ok(())
+    }
+
+    async fn archive(&self, dir: &Path, poll_id: i64) -> Result<(), VoteRetentionError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT eph_x, eph_y, nonce, ciphertext, msg_hash
+            FROM votes WHERE poll_id = $1
+            ORDER BY id
+            "#,
+            poll_id
+        )
+        .fetch_all(&self.pg_pool)
+        .await?;
+        let mut records = Vec::with_capacity(rows.len() * RECORD_LEN);
+        for r in &rows {
+            encode_record(
+                &mut records,
+                &r.eph_x,
+                &r.eph_y,
+                r.nonce,
+                &r.ciphertext,
+                r.msg_hash.as_deref(),
+            );
+        }
+
+        let path = dir.join(format!("{poll_id}.votes.gz"));
+        let tmp_path = path.with_extension("gz.tmp");
+        let (dir, write_path) = (dir.to_owned(), path.clone());
+        tokio::task::spawn_blocking(move || -> io::Result<()> {
+            fs::create_dir_all(dir)?;
+            let mut encoder = GzEncoder::new(fs::File::create(&tmp_path)?, Compression::best());
+            encoder.write_all(&records)?;
+            encoder.finish()?.sync_all()?;
+            fs::rename(tmp_path, write_path)
+        })
+        .await
+        .map_err(io::Error::other)??;
+
+        let mut tx = self.pg_pool.begin().await?;
+        sqlx::query!(
+            "UPDATE polls SET votes_archive = $2 WHERE poll_id = $1",
+            poll_id,
+            &*path.to_string_lossy()
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!("DELETE FROM votes WHERE poll_id = $1", poll_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_record() {
+        let mut out = Vec::new();
+        encode_record(&mut out, &[1; 32], &[2; 32], 3, &[4; 224], None);
+        encode_record(&mut out, &[1; 32], &[2; 32], 3, &[4; 224], Some(&[5; 32]));
+        assert_eq!(out.len(), 2 * RECORD_LEN);
+        assert_eq!(out[64..72], 3u64.to_be_bytes());
+        assert_eq!(out[RECORD_LEN - 32..RECORD_LEN], [0; 32]);
+        assert_eq!(out[2 * RECORD_LEN - 32..], [5; 32]);
+    }
+}
@@ -70,7 +70,7 @@ impl<'a> Execution<'a> {
     }
 }
 
-pub(crate) fn parse_logs<T: Event>(
+pub fn parse_logs<T: Event>(
     logs: &[&str],
     program_id_str: &str,
 ) -> Result<Vec<T>, ParseLogsError> {
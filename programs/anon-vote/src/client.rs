@@ -0,0 +1,3 @@
+//! The vote checks off-chain clients, e.g. relayers, share with the program.
+
+pub use crate::utils::{msg_hash, vote_public_inputs, VotePublicInputs};
@@ -1,6 +1,9 @@
 use anchor_lang::prelude::*;
+use num_enum::TryFromPrimitive;
 
+/// `TryFrom<u32>` takes the code less `ERROR_CODE_OFFSET`.
 #[error_code]
+#[derive(TryFromPrimitive)]
 pub enum AnonVoteError {
     /// 6000 0x1770
     #[msg("Proof verification failed")]
@@ -23,4 +26,37 @@ pub enum AnonVoteError {
     /// 6006 0x1776
     #[msg("Poll duration is too long")]
     PollTooLong,
+    /// 6007 0x1777
+    #[msg("Relayer is not funded by the poll")]
+    RelayerNotFunded,
+    /// 6008 0x1778
+    #[msg("Too many choices")]
+    TooManyChoices,
+    /// 6009 0x1779
+    #[msg("Tally deadline must be after voting ends and within a month")]
+    InvalidTallyDeadline,
+    /// 6010 0x177a
+    #[msg("Poll is already tallied or failed")]
+    AlreadyTallied,
+    /// 6011 0x177b
+    #[msg("Poll is not tallied")]
+    NotTallied,
+    /// 6012 0x177c
+    #[msg("Relayer state must be closed first")]
+    RelayerStateNotClosed,
+    /// 6013 0x177d
+    #[msg("Fee account doesn't match the poll's fee token or destination")]
+    InvalidFeeAccount,
+    /// 6014 0x177e
+    #[msg("Poll id was already used by a closed poll")]
+    PollIdUsed,
+    /// 6015 0x177f
+    #[msg("No platform fee is set for the token")]
+    NoTokenFee,
+    /// 6016 0x1780
+    #[msg("Relay budget overflows")]
+    RelayBudgetOverflow,
+    /// 6017 0x1781
+    #[msg("Fee token isn't an SPL Token mint")]
+    UnsupportedToken,
 }
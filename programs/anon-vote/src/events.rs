@@ -10,6 +10,10 @@ pub struct VoteEvent {
     pub nonce: u64,
     pub ciphertext: [[u8; 32]; 7],
     pub msg_hash: [u8; 32],
+    /// Position of the message in the poll's `running_msg_hash` chain.
+    pub msg_index: u64,
+    /// Left in the poll to pay relayers, lamports or fee token amount.
+    pub relay_budget: u64,
 }
 
 #[event(discriminator = 1u8)]
@@ -21,9 +25,15 @@ pub struct CreatePollEvent {
     pub census_root: [u8; 32],
     pub voting_start_time: u64,
     pub voting_end_time: u64,
+    pub tally_deadline: u64,
     pub platform_fee: u64,
+    pub relay_fee: u64,
+    /// What the poll was funded with to pay relayers, lamports or fee token
+    /// amount.
+    pub relay_budget: u64,
     pub fee: u64,
     pub fee_destination: Pubkey,
+    pub fee_token: Option<FeeToken>,
     pub n_voters: u64,
     pub description_url: String,
     pub census_url: String,
@@ -35,3 +45,30 @@ pub struct FinishTallyEvent {
     pub poll_id: u64,
     pub tally: Vec<u64>,
 }
+
+#[event(discriminator = 3u8)]
+#[derive(Clone, Debug)]
+pub struct PollFailedEvent {
+    pub poll_id: u64,
+    /// Unspent deposit refunded to the poll's fee destination, lamports or
+    /// fee token amount.
+    pub refunded: u64,
+}
+
+#[event(discriminator = 4u8)]
+#[derive(Clone, Debug)]
+pub struct ClosePollEvent {
+    pub poll_id: u64,
+    /// The `PollResult` account now holding the outcome.
+    pub result: Pubkey,
+}
+
+#[event(discriminator = 5u8)]
+#[derive(Clone, Debug)]
+pub struct PollFundedEvent {
+    pub poll_id: u64,
+    pub funder: Pubkey,
+    pub amount: u64,
+    /// Left in the poll to pay relayers, lamports or fee token amount.
+    pub relay_budget: u64,
+}
@@ -0,0 +1,103 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    error::AnonVoteError,
+    events::*,
+    state::*,
+    utils::{
+        close_vault, fee_mint, fee_vault, spare_lamports, token_account, token_balance,
+        token_program, transfer,
+    },
+};
+
+#[derive(Accounts)]
+pub struct ClosePoll<'info> {
+    #[account(mut)]
+    creator: Signer<'info>,
+    /// CHECK: checked using has_one
+    #[account(mut)]
+    fee_destination: AccountInfo<'info>,
+    #[account(mut, close = creator, has_one = creator, has_one = fee_destination)]
+    poll: Account<'info, Poll>,
+    #[account(
+        init,
+        space = PollResult::DISCRIMINATOR.len() + PollResult::INIT_SPACE,
+        payer = creator,
+        seeds = [&b"RESULT"[..], &poll.id.to_le_bytes()],
+        bump,
+    )]
+    result: Account<'info, PollResult>,
+    /// CHECK: only checked to be closed, its rent would be stuck otherwise
+    #[account(
+        seeds = [&b"RELAYER_STATE"[..], &crate::ID.to_bytes(), &poll.id.to_le_bytes()],
+        seeds::program = zk_relayer::ID,
+        bump,
+        constraint = relayer_state.data_is_empty() @ AnonVoteError::RelayerStateNotClosed,
+    )]
+    relayer_state: AccountInfo<'info>,
+    system_program: Program<'info, System>,
+    /// CHECK: the poll's token vault in token polls
+    #[account(mut)]
+    fee_vault: Option<UncheckedAccount<'info>>,
+    /// CHECK: the fee destination's token account in token polls, checked
+    /// with `token_balance`
+    #[account(mut)]
+    fee_destination_tokens: Option<UncheckedAccount<'info>>,
+    /// CHECK: the poll's token program in token polls
+    token_program: Option<UncheckedAccount<'info>>,
+    /// CHECK: the fee token's mint in token polls
+    fee_mint: Option<UncheckedAccount<'info>>,
+}
+
+/// Moves the outcome of a tallied or failed poll into a `PollResult` and
+/// closes the poll and its token vault, sending what's left of the deposit
+/// to the poll fee destination and the rent to the creator.
+pub fn close_poll(ctx: Context<ClosePoll>) -> Result<()> {
+    let fee_destination = &ctx.accounts.fee_destination;
+    let poll = &ctx.accounts.poll;
+
+    require!(
+        !poll.tally.is_empty() || poll.failed,
+        AnonVoteError::NotTallied
+    );
+
+    let mut tally = [0; MAX_CHOICES];
+    tally[..poll.tally.len()].copy_from_slice(&poll.tally);
+    ctx.accounts.result.set_inner(PollResult {
+        poll_id: poll.id,
+        n_choices: poll.n_choices,
+        failed: poll.failed,
+        n_messages: poll.n_messages,
+        census_root: poll.census_root,
+        coordinator_key: poll.coordinator_key,
+        running_msg_hash: poll.running_msg_hash,
+        tally,
+    });
+
+    transfer(
+        poll.as_ref(),
+        fee_destination,
+        spare_lamports(poll.as_ref())?,
+    )?;
+
+    if let Some(fee_token) = &poll.fee_token {
+        token_program(fee_token, &ctx.accounts.token_program)?;
+        let to = token_account(&ctx.accounts.fee_destination_tokens)?;
+        token_balance(fee_token, to, &poll.fee_destination)?;
+        close_vault(
+            poll,
+            fee_token,
+            fee_vault(fee_token, &ctx.accounts.fee_vault)?.to_account_info(),
+            fee_mint(fee_token, &ctx.accounts.fee_mint)?.to_account_info(),
+            to.to_account_info(),
+            ctx.accounts.creator.to_account_info(),
+        )?;
+    }
+
+    emit!(ClosePollEvent {
+        poll_id: poll.id,
+        result: ctx.accounts.result.key(),
+    });
+
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use anchor_lang::prelude::*;
+use zk_relayer::{
+    cpi::{
+        accounts::CloseRelayerState as CloseRelayerStateCpi,
+        close_relayer_state as close_relayer_state_cpi,
+    },
+    program::ZkRelayer,
+};
+
+use crate::state::*;
+
+#[derive(Accounts)]
+pub struct CloseRelayerState<'info> {
+    /// CHECK: checked using has_one
+    #[account(mut)]
+    creator: AccountInfo<'info>,
+    #[account(has_one = creator)]
+    poll: Account<'info, Poll>,
+    /// CHECK: checked in CPI
+    #[account(mut)]
+    relayer_state: AccountInfo<'info>,
+    /// CHECK: it's an empty signer account
+    #[account(seeds = [b"ZK_RELAYER_SIGNER"], bump)]
+    program_signer: AccountInfo<'info>,
+    zk_relayer_program: Program<'info, ZkRelayer>,
+}
+
+/// Closes the poll's relayer state after voting ends, returning the rent
+/// to the poll creator who paid it.
+pub fn close_relayer_state(ctx: Context<CloseRelayerState>) -> Result<()> {
+    let poll = &ctx.accounts.poll;
+
+    close_relayer_state_cpi(
+        CpiContext::new_with_signer(
+            ctx.accounts.zk_relayer_program.to_account_info(),
+            CloseRelayerStateCpi {
+                destination: ctx.accounts.creator.to_account_info(),
+                relayer_state: ctx.accounts.relayer_state.to_account_info(),
+                program_signer: ctx.accounts.program_signer.to_account_info(),
+            },
+            &[&[b"ZK_RELAYER_SIGNER", &[ctx.bumps.program_signer]]],
+        ),
+        crate::ID,
+        poll.id,
+    )
+}
@@ -2,16 +2,24 @@ use anchor_lang::prelude::*;
 use zk_relayer::{
     cpi::{accounts::CreateRelayerState, create_relayer_state},
     program::ZkRelayer,
-    state::ZkRelayerConfig,
+    state::RelayerState,
 };
 
-use crate::{error::AnonVoteError, events::*, state::*, utils::system_transfer};
+use crate::{
+    error::AnonVoteError,
+    events::*,
+    state::*,
+    utils::{
+        fee_mint, system_transfer, token_account, token_transfer, TokenAccount, SPL_TOKEN_PROGRAM,
+    },
+};
 
 /// Maximum number of messages per voter per relayer.
 const MSG_LIMIT: u64 = 3;
 /// A year.
 const MAX_POLL_DURATION: u64 = 365 * 24 * 60 * 60;
-const N_RELAYERS: u64 = 1;
+/// A month.
+const MAX_TALLY_DURATION: u64 = 30 * 24 * 60 * 60;
 
 #[derive(Accounts)]
 #[instruction(poll_id: u64, n_choices: u8, description_url: String, census_url_len: u32)]
@@ -29,20 +37,42 @@ pub struct CreatePoll<'info> {
         bump,
     )]
     poll: Account<'info, Poll>,
-    relayer_config: Account<'info, ZkRelayerConfig>,
+    /// CHECK: only checked to be empty, the result of a closed poll keeps
+    /// its id from being reused
+    #[account(
+        seeds = [&b"RESULT"[..], &poll_id.to_le_bytes()],
+        bump,
+        constraint = result.data_is_empty() @ AnonVoteError::PollIdUsed,
+    )]
+    result: AccountInfo<'info>,
     /// CHECK: checked in CPI
     #[account(mut)]
     relayer_state: AccountInfo<'info>,
+    /// CHECK: checked in CPI
+    allowed_programs: AccountInfo<'info>,
     /// CHECK: it's an empty signer account
     #[account(seeds = [b"ZK_RELAYER_SIGNER"], bump)]
     program_signer: AccountInfo<'info>,
     zk_relayer_program: Program<'info, ZkRelayer>,
     system_program: Program<'info, System>,
+    /// CHECK: for polls paying fees in an SPL token, a token account owned
+    /// by the poll with neither a delegate nor a close authority
+    #[account(mut)]
+    fee_vault: Option<UncheckedAccount<'info>>,
+    /// CHECK: the payer's token account, checked by the token program
+    #[account(mut)]
+    payer_tokens: Option<UncheckedAccount<'info>>,
+    /// CHECK: `SPL_TOKEN_PROGRAM`
+    token_program: Option<UncheckedAccount<'info>>,
+    /// CHECK: the mint of `fee_vault`
+    fee_mint: Option<UncheckedAccount<'info>>,
+    /// The platform fee of token polls, for the mint of `fee_vault`
+    platform_token_fee: Option<Account<'info, PlatformTokenFee>>,
 }
 
 #[allow(clippy::too_many_arguments)]
-pub fn create_poll(
-    ctx: Context<CreatePoll>,
+pub fn create_poll<'info>(
+    ctx: Context<'_, '_, '_, 'info, CreatePoll<'info>>,
     id: u64,
     n_choices: u8,
     description_url: String,
@@ -51,63 +81,143 @@ pub fn create_poll(
     census_root: [u8; 32],
     voting_start_time: u64,
     voting_end_time: u64,
+    tally_deadline: u64,
     fee: u64,
     fee_destination: Pubkey,
     n_voters: u64,
+    relayers: Vec<Pubkey>,
 ) -> Result<()> {
     let payer = &ctx.accounts.payer;
     let platform_config = &ctx.accounts.platform_config;
     let poll = &mut ctx.accounts.poll;
-    let relayer_config = &ctx.accounts.relayer_config;
     let relayer_state = &ctx.accounts.relayer_state;
+    let allowed_programs = &ctx.accounts.allowed_programs;
     let program_signer = &ctx.accounts.program_signer;
     let system_program = &ctx.accounts.system_program;
     let zk_relayer_program = &ctx.accounts.zk_relayer_program;
 
     require_gt!(n_choices, 0);
+    require!(
+        n_choices as usize <= MAX_CHOICES,
+        AnonVoteError::TooManyChoices
+    );
 
     require!(
         voting_end_time > voting_start_time
             && voting_end_time - voting_start_time <= MAX_POLL_DURATION,
         AnonVoteError::PollTooLong
     );
+    require!(
+        tally_deadline > voting_end_time && tally_deadline - voting_end_time <= MAX_TALLY_DURATION,
+        AnonVoteError::InvalidTallyDeadline
+    );
 
     poll.id = id;
+    poll.bump = ctx.bumps.poll;
+    poll.creator = payer.key();
     poll.n_choices = n_choices;
     poll.coordinator_key = coordinator_key;
     poll.census_root = census_root;
     poll.voting_start_time = voting_start_time;
     poll.voting_end_time = voting_end_time;
-    poll.platform_fee = platform_config.fee;
+    poll.tally_deadline = tally_deadline;
     poll.fee = fee;
     poll.fee_destination = fee_destination;
+    poll.fee_token = match &ctx.accounts.fee_vault {
+        Some(vault) => {
+            let token_program = token_account(&ctx.accounts.token_program)?.key();
+            require_keys_eq!(
+                token_program,
+                SPL_TOKEN_PROGRAM,
+                AnonVoteError::UnsupportedToken
+            );
+            let vault_account = TokenAccount::unpack(vault, &token_program)?;
+            require!(
+                vault_account.owner == poll.key() && vault_account.exclusive,
+                AnonVoteError::InvalidFeeAccount
+            );
+            Some(FeeToken {
+                mint: vault_account.mint,
+                vault: vault.key(),
+                token_program,
+            })
+        }
+        None => None,
+    };
+    poll.platform_fee = match &poll.fee_token {
+        Some(fee_token) => {
+            let token_fee = ctx
+                .accounts
+                .platform_token_fee
+                .as_ref()
+                .ok_or(AnonVoteError::NoTokenFee)?;
+            require_keys_eq!(token_fee.mint, fee_token.mint, AnonVoteError::NoTokenFee);
+            token_fee.fee
+        }
+        None => platform_config.fee,
+    };
     poll.description_url = description_url.clone();
     poll.census_url = census_url.clone();
 
-    system_transfer(
-        payer.to_account_info(),
-        poll.to_account_info(),
-        (relayer_config.fee + platform_config.fee) * MSG_LIMIT * N_RELAYERS * n_voters,
-    )?;
-
     create_relayer_state(
         CpiContext::new_with_signer(
             zk_relayer_program.to_account_info(),
             CreateRelayerState {
                 payer: payer.to_account_info(),
-                relayer_config: relayer_config.to_account_info(),
                 relayer_state: relayer_state.to_account_info(),
+                allowed_programs: allowed_programs.to_account_info(),
                 program_signer: program_signer.to_account_info(),
                 system_program: system_program.to_account_info(),
             },
             &[&[b"ZK_RELAYER_SIGNER", &[ctx.bumps.program_signer]]],
-        ),
+        )
+        .with_remaining_accounts(ctx.remaining_accounts.to_vec()),
         crate::ID,
         id,
         MSG_LIMIT,
         voting_end_time,
+        relayers,
+        poll.fee_token.as_ref().map(|fee_token| fee_token.mint),
     )?;
 
+    // relayer fees are fixed in the relayer state, fund every relayer's quota
+    let quotas =
+        RelayerState::try_deserialize(&mut &relayer_state.try_borrow_data()?[..])?.relayers;
+    let platform_fee = poll.platform_fee;
+    let relayer_fees = quotas
+        .iter()
+        .try_fold(0u64, |sum, q| {
+            sum.checked_add(q.fee)?.checked_add(platform_fee)
+        })
+        .ok_or(AnonVoteError::RelayBudgetOverflow)?;
+    poll.relay_fee = quotas
+        .iter()
+        .map(|q| q.fee)
+        .max()
+        .unwrap_or(0)
+        .checked_add(platform_fee)
+        .ok_or(AnonVoteError::RelayBudgetOverflow)?;
+    let relay_budget = relayer_fees
+        .checked_mul(MSG_LIMIT)
+        .and_then(|fees| fees.checked_mul(n_voters))
+        .ok_or(AnonVoteError::RelayBudgetOverflow)?;
+    match &poll.fee_token {
+        Some(fee_token) => token_transfer(
+            &fee_token.token_program,
+            token_account(&ctx.accounts.payer_tokens)?.to_account_info(),
+            fee_mint(fee_token, &ctx.accounts.fee_mint)?.to_account_info(),
+            token_account(&ctx.accounts.fee_vault)?.to_account_info(),
+            payer.to_account_info(),
+            relay_budget,
+            &[],
+        )?,
+        None => system_transfer(
+            payer.to_account_info(),
+            poll.to_account_info(),
+            relay_budget,
+        )?,
+    }
+
     emit!(CreatePollEvent {
         poll_id: poll.id,
         n_choices,
@@ -115,9 +225,13 @@ pub fn create_poll(
         census_root,
         voting_start_time,
         voting_end_time,
-        platform_fee: platform_config.fee,
+        tally_deadline,
+        platform_fee: poll.platform_fee,
+        relay_fee: poll.relay_fee,
+        relay_budget,
         fee,
         fee_destination,
+        fee_token: poll.fee_token.clone(),
         n_voters,
         description_url,
         census_url,
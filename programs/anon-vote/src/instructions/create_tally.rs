@@ -14,6 +14,8 @@ pub struct CreateTally<'info> {
         bump,
     )]
     tally: Account<'info, Tally>,
+    #[account(seeds = [&b"POLL"[..], &poll_id.to_le_bytes()], bump)]
+    poll: Account<'info, Poll>,
     system_program: Program<'info, System>,
 }
 
@@ -22,7 +24,10 @@ pub fn create_tally(
     _poll_id: u64,
     initial_tally_hash: [u8; 32],
 ) -> Result<()> {
+    let poll = &ctx.accounts.poll;
     ctx.accounts.tally.set_inner(Tally {
+        poll_id: poll.id,
+        coordinator_key: poll.coordinator_key,
         tally_hash: initial_tally_hash,
         running_msg_hash: [0; 32],
         root: [0; 32],
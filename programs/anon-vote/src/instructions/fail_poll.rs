@@ -0,0 +1,74 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    error::AnonVoteError,
+    events::*,
+    state::*,
+    utils::{
+        fee_mint, fee_vault, spare_lamports, token_account, token_balance, token_program, transfer,
+        vault_transfer,
+    },
+};
+
+#[derive(Accounts)]
+pub struct FailPoll<'info> {
+    /// CHECK: checked using has_one
+    #[account(mut)]
+    fee_destination: AccountInfo<'info>,
+    #[account(mut, has_one = fee_destination)]
+    poll: Account<'info, Poll>,
+    /// CHECK: the poll's token vault in token polls
+    #[account(mut)]
+    fee_vault: Option<UncheckedAccount<'info>>,
+    /// CHECK: the poll fee destination's token account in token polls,
+    /// checked with `token_balance`
+    #[account(mut)]
+    fee_destination_tokens: Option<UncheckedAccount<'info>>,
+    /// CHECK: the poll's token program in token polls
+    token_program: Option<UncheckedAccount<'info>>,
+    /// CHECK: the fee token's mint in token polls
+    fee_mint: Option<UncheckedAccount<'info>>,
+}
+
+/// Marks a poll that wasn't tallied before its deadline as failed, anyone
+/// can call it. The unspent deposit is refunded to the poll's fee
+/// destination, the platform fees of the relayed votes being paid already.
+pub fn fail_poll(ctx: Context<FailPoll>) -> Result<()> {
+    let fee_destination = &ctx.accounts.fee_destination;
+    let poll = &mut ctx.accounts.poll;
+
+    let now = Clock::get()?.unix_timestamp as u64;
+    require!(now >= poll.tally_deadline, AnonVoteError::BadTime);
+    require!(
+        poll.tally.is_empty() && !poll.failed,
+        AnonVoteError::AlreadyTallied
+    );
+
+    poll.failed = true;
+
+    let mut refunded = spare_lamports(poll.as_ref())?;
+    transfer(poll.as_ref(), fee_destination, refunded)?;
+
+    if let Some(fee_token) = &poll.fee_token {
+        token_program(fee_token, &ctx.accounts.token_program)?;
+        let vault = fee_vault(fee_token, &ctx.accounts.fee_vault)?;
+        let to = token_account(&ctx.accounts.fee_destination_tokens)?;
+        token_balance(fee_token, to, fee_destination.key)?;
+        refunded = token_balance(fee_token, vault, &poll.key())?;
+        vault_transfer(
+            poll,
+            fee_token,
+            vault.to_account_info(),
+            fee_mint(fee_token, &ctx.accounts.fee_mint)?.to_account_info(),
+            to.to_account_info(),
+            refunded,
+        )?;
+    }
+
+    emit!(PollFailedEvent {
+        poll_id: poll.id,
+        refunded,
+    });
+
+    Ok(())
+}
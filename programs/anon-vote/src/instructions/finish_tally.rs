@@ -8,8 +8,6 @@ use crate::{
     utils::{poseidon, u64_to_u256_be},
 };
 
-const MAX_CHOICES: usize = 8;
-
 #[derive(Accounts)]
 pub struct FinishTally<'info> {
     /// CHECK: it only receives funds
@@ -25,8 +23,7 @@ pub fn finish_tally(ctx: Context<FinishTally>, tally: Vec<u64>, tally_salt: u64)
     let tally_acc = &ctx.accounts.tally;
     let poll = &mut ctx.accounts.poll;
 
-    let now = Clock::get()?.unix_timestamp as u64;
-    require!(now > poll.voting_end_time, AnonVoteError::BadTime);
+    check_finishable(tally_acc, poll)?;
 
     require!(
         tally.len() == poll.n_choices as usize,
@@ -34,18 +31,7 @@ pub fn finish_tally(ctx: Context<FinishTally>, tally: Vec<u64>, tally_salt: u64)
     );
 
     require!(
-        tally_acc.running_msg_hash == poll.running_msg_hash,
-        AnonVoteError::IncorrectTally
-    );
-
-    let mut preimage = Vec::with_capacity(tally.len() + 1);
-    let tally_salt = u64_to_u256_be(tally_salt);
-    preimage.push(&tally_salt[..]);
-    let raw_tally_u128: Vec<_> = tally.iter().copied().map(u64_to_u256_be).collect();
-    preimage.extend(raw_tally_u128.iter().map(|x| x.as_slice()));
-    preimage.extend(repeat(&[0; 32][..]).take(MAX_CHOICES - poll.n_choices as usize));
-    require!(
-        poseidon(&preimage).map_err(|_| AnonVoteError::Poseidon)? == tally_acc.tally_hash,
+        hash_tally(tally_salt, &tally)? == tally_acc.tally_hash,
         AnonVoteError::IncorrectTally
     );
 
@@ -58,3 +44,31 @@ pub fn finish_tally(ctx: Context<FinishTally>, tally: Vec<u64>, tally_salt: u64)
 
     Ok(())
 }
+
+fn check_finishable(tally_acc: &Tally, poll: &Poll) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp as u64;
+    require!(
+        now > poll.voting_end_time && now < poll.tally_deadline,
+        AnonVoteError::BadTime
+    );
+
+    require!(
+        tally_acc.poll_id == poll.id
+            && tally_acc.coordinator_key == poll.coordinator_key
+            && tally_acc.running_msg_hash == poll.running_msg_hash,
+        AnonVoteError::IncorrectTally
+    );
+
+    Ok(())
+}
+
+/// `Poseidon(salt, tally...)` with the tally padded to `MAX_CHOICES`.
+fn hash_tally(tally_salt: u64, tally: &[u64]) -> Result<[u8; 32]> {
+    let mut preimage = Vec::with_capacity(MAX_CHOICES + 1);
+    let tally_salt = u64_to_u256_be(tally_salt);
+    preimage.push(&tally_salt[..]);
+    let raw_tally_u128: Vec<_> = tally.iter().copied().map(u64_to_u256_be).collect();
+    preimage.extend(raw_tally_u128.iter().map(|x| x.as_slice()));
+    preimage.extend(repeat(&[0; 32][..]).take(MAX_CHOICES - tally.len()));
+    Ok(poseidon(&preimage).map_err(|_| AnonVoteError::Poseidon)?)
+}
@@ -0,0 +1,65 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    error::AnonVoteError,
+    events::*,
+    state::*,
+    utils::{
+        fee_mint, fee_vault, relay_budget, system_transfer, token_account, token_program,
+        token_transfer,
+    },
+};
+
+#[derive(Accounts)]
+pub struct FundPoll<'info> {
+    #[account(mut)]
+    funder: Signer<'info>,
+    #[account(mut)]
+    poll: Account<'info, Poll>,
+    system_program: Program<'info, System>,
+    /// CHECK: the funder's token account, checked by the token program
+    #[account(mut)]
+    funder_tokens: Option<UncheckedAccount<'info>>,
+    /// CHECK: the poll's token vault in token polls
+    #[account(mut)]
+    fee_vault: Option<UncheckedAccount<'info>>,
+    /// CHECK: the poll's token program in token polls
+    token_program: Option<UncheckedAccount<'info>>,
+    /// CHECK: the fee token's mint in token polls
+    fee_mint: Option<UncheckedAccount<'info>>,
+}
+
+/// Adds lamports, or the fee token, to pay the poll's relayers, anyone can
+/// sponsor a poll until voting ends.
+pub fn fund_poll(ctx: Context<FundPoll>, amount: u64) -> Result<()> {
+    let funder = &ctx.accounts.funder;
+    let poll = &ctx.accounts.poll;
+    let vault = &ctx.accounts.fee_vault;
+
+    let now = Clock::get()?.unix_timestamp as u64;
+    require!(now <= poll.voting_end_time, AnonVoteError::BadTime);
+
+    match &poll.fee_token {
+        Some(fee_token) => {
+            token_transfer(
+                token_program(fee_token, &ctx.accounts.token_program)?.key,
+                token_account(&ctx.accounts.funder_tokens)?.to_account_info(),
+                fee_mint(fee_token, &ctx.accounts.fee_mint)?.to_account_info(),
+                fee_vault(fee_token, vault)?.to_account_info(),
+                funder.to_account_info(),
+                amount,
+                &[],
+            )?;
+        }
+        None => system_transfer(funder.to_account_info(), poll.to_account_info(), amount)?,
+    }
+
+    emit!(PollFundedEvent {
+        poll_id: poll.id,
+        funder: funder.key(),
+        amount,
+        relay_budget: relay_budget(poll, vault)?,
+    });
+
+    Ok(())
+}
@@ -1,13 +1,19 @@
 pub use self::{
-    close_tally::*, create_poll::*, create_tally::*, finish_tally::*, initialize::*,
-    tally_batch::*, update_config::*, vote::*, withdraw_poll::*,
+    close_poll::*, close_relayer_state::*, close_tally::*, create_poll::*, create_tally::*,
+    fail_poll::*, finish_tally::*, fund_poll::*, initialize::*, set_token_fee::*, tally_batch::*,
+    update_config::*, vote::*, withdraw_poll::*,
 };
 
+mod close_poll;
+mod close_relayer_state;
 mod close_tally;
 mod create_poll;
 mod create_tally;
+mod fail_poll;
 mod finish_tally;
+mod fund_poll;
 mod initialize;
+mod set_token_fee;
 mod tally_batch;
 mod update_config;
 mod vote;
@@ -0,0 +1,31 @@
+use crate::state::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct SetTokenFee<'info> {
+    #[account(mut)]
+    admin: Signer<'info>,
+    #[account(has_one = admin)]
+    platform_config: Account<'info, PlatformConfig>,
+    #[account(
+        init_if_needed,
+        space = PlatformTokenFee::DISCRIMINATOR.len() + PlatformTokenFee::INIT_SPACE,
+        payer = admin,
+        seeds = [&b"TOKEN_FEE"[..], &mint.to_bytes()],
+        bump,
+    )]
+    token_fee: Account<'info, PlatformTokenFee>,
+    system_program: Program<'info, System>,
+}
+
+/// Sets the platform fee of polls paying in `mint`, in its base units. It
+/// applies to polls created afterwards.
+pub fn set_token_fee(ctx: Context<SetTokenFee>, mint: Pubkey, fee: u64) -> Result<()> {
+    let token_fee = &mut ctx.accounts.token_fee;
+
+    token_fee.mint = mint;
+    token_fee.fee = fee;
+
+    Ok(())
+}
@@ -1,6 +1,5 @@
-use crate::{error::AnonVoteError, state::*, vk::VK_TALLY};
+use crate::{error::AnonVoteError, state::*, utils::verify_proof, vk::VK_TALLY};
 use anchor_lang::prelude::*;
-use groth16_solana::groth16::Groth16Verifier;
 
 #[derive(Accounts)]
 pub struct TallyBatch<'info> {
@@ -28,9 +27,7 @@ pub fn tally_batch(
         tally.running_msg_hash,
         tally.tally_hash,
     ];
-    let mut v = Groth16Verifier::<6>::new(&proof.a, &proof.b, &proof.c, &public_inputs, &VK_TALLY)
-        .map_err(|_| AnonVoteError::InvalidProof)?;
-    v.verify().map_err(|_| AnonVoteError::InvalidProof)?;
+    verify_proof(&proof, &public_inputs, &VK_TALLY)?;
 
     tally.root = root_after;
     tally.running_msg_hash = running_msg_hash_after;
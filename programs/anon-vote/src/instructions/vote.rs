@@ -1,8 +1,7 @@
 use anchor_lang::prelude::*;
-use groth16_solana::groth16::Groth16Verifier;
 use zk_relayer::state::RelayerState;
 
-use crate::{error::AnonVoteError, events::VoteEvent, state::*, utils::*, vk::VK_VOTE};
+use crate::{error::AnonVoteError, events::VoteEvent, state::*, utils::*};
 
 const RELAYER_PROGRAM: Pubkey = pubkey!("Re1aDNwUYroFdKmsPDKnZaQPxxKtDBvwNctH1w9ow6j");
 
@@ -10,11 +9,14 @@ const RELAYER_PROGRAM: Pubkey = pubkey!("Re1aDNwUYroFdKmsPDKnZaQPxxKtDBvwNctH1w9
 pub struct Vote<'info> {
     #[account(mut)]
     payer: Signer<'info>,
-    /// CHECK: the address is checked
-    #[account(mut, address = vote_common.poll.fee_destination)]
+    /// CHECK: checked with `check_fee_recipient`
+    #[account(mut)]
     poll_fee_destination: AccountInfo<'info>,
     vote_common: VoteCommon<'info>,
     system_program: Program<'info, System>,
+    /// CHECK: the payer's token account, checked by the token program
+    #[account(mut)]
+    payer_tokens: Option<UncheckedAccount<'info>>,
 }
 
 #[derive(Accounts)]
@@ -29,6 +31,9 @@ pub struct VoteWithRelayer<'info> {
     )]
     relayer_state: Account<'info, RelayerState>,
     vote_common: VoteCommon<'info>,
+    /// CHECK: the relayer's token account, checked with `token_balance`
+    #[account(mut)]
+    relayer_tokens: Option<UncheckedAccount<'info>>,
 }
 
 pub fn vote(
@@ -41,20 +46,54 @@ pub fn vote(
     let common = &mut ctx.accounts.vote_common;
     let payer = &ctx.accounts.payer;
     let poll_fee_destination = &ctx.accounts.poll_fee_destination;
-    let poll = &mut common.poll;
+    let poll = &common.poll;
     let platform_fee_destination = &common.platform_fee_destination;
 
-    system_transfer(
-        payer.to_account_info(),
-        poll_fee_destination.to_account_info(),
-        poll.fee,
-    )?;
-    system_transfer(
-        payer.to_account_info(),
-        platform_fee_destination.to_account_info(),
-        poll.platform_fee,
+    check_fee_recipient(poll, poll_fee_destination, &poll.fee_destination)?;
+    check_fee_recipient(
+        poll,
+        platform_fee_destination,
+        &common.platform_config.fee_destination,
     )?;
 
+    match &poll.fee_token {
+        Some(fee_token) => {
+            let token_program = token_program(fee_token, &common.token_program)?.key();
+            let mint = fee_mint(fee_token, &common.fee_mint)?;
+            let payer_tokens = token_account(&ctx.accounts.payer_tokens)?;
+            token_transfer(
+                &token_program,
+                payer_tokens.to_account_info(),
+                mint.to_account_info(),
+                poll_fee_destination.to_account_info(),
+                payer.to_account_info(),
+                poll.fee,
+                &[],
+            )?;
+            token_transfer(
+                &token_program,
+                payer_tokens.to_account_info(),
+                mint.to_account_info(),
+                platform_fee_destination.to_account_info(),
+                payer.to_account_info(),
+                poll.platform_fee,
+                &[],
+            )?;
+        }
+        None => {
+            system_transfer(
+                payer.to_account_info(),
+                poll_fee_destination.to_account_info(),
+                poll.fee,
+            )?;
+            system_transfer(
+                payer.to_account_info(),
+                platform_fee_destination.to_account_info(),
+                poll.platform_fee,
+            )?;
+        }
+    }
+
     let relayer_nu_hash = [0; 32];
     let relayer_id = [0; 32];
 
@@ -86,13 +125,48 @@ pub fn vote_with_relayer(
 
     let common = &mut ctx.accounts.vote_common;
 
-    transfer(common.poll.as_ref(), relayer, relayer_state.fee)?;
-    transfer(
-        common.poll.as_ref(),
-        &common.platform_fee_destination,
-        common.platform_config.fee,
+    let quota = relayer_state
+        .quota(relayer.key)
+        .ok_or(AnonVoteError::RelayerNotFunded)?;
+    let poll = &common.poll;
+    let platform_fee_destination = &common.platform_fee_destination;
+
+    check_fee_recipient(
+        poll,
+        platform_fee_destination,
+        &common.platform_config.fee_destination,
     )?;
 
+    match &poll.fee_token {
+        Some(fee_token) => {
+            token_program(fee_token, &common.token_program)?;
+            let vault = fee_vault(fee_token, &common.fee_vault)?;
+            let mint = fee_mint(fee_token, &common.fee_mint)?;
+            let relayer_tokens = token_account(&ctx.accounts.relayer_tokens)?;
+            token_balance(fee_token, relayer_tokens, relayer.key)?;
+            vault_transfer(
+                poll,
+                fee_token,
+                vault.to_account_info(),
+                mint.to_account_info(),
+                relayer_tokens.to_account_info(),
+                quota.fee,
+            )?;
+            vault_transfer(
+                poll,
+                fee_token,
+                vault.to_account_info(),
+                mint.to_account_info(),
+                platform_fee_destination.to_account_info(),
+                poll.platform_fee,
+            )?;
+        }
+        None => {
+            transfer(poll.as_ref(), relayer, quota.fee)?;
+            transfer(poll.as_ref(), platform_fee_destination, poll.platform_fee)?;
+        }
+    }
+
     vote_common(
         eph_key,
         nonce,
@@ -124,15 +198,7 @@ fn vote_common(
         AnonVoteError::BadTime
     );
 
-    let mut preimage = [&eph_key.x[..]; 10];
-    preimage[0] = &eph_key.x;
-    preimage[1] = &eph_key.y;
-    let nonce_u128 = u64_to_u256_be(nonce);
-    preimage[2] = &nonce_u128;
-    for (i, c) in ciphertext.iter().enumerate() {
-        preimage[3 + i] = c;
-    }
-    let msg_hash = poseidon(&preimage).map_err(|_| AnonVoteError::Poseidon)?;
+    let msg_hash = msg_hash(&eph_key, nonce, &ciphertext).map_err(|_| AnonVoteError::Poseidon)?;
 
     if let Some(msg_hash_from_relayer) = msg_hash_from_relayer {
         require!(
@@ -144,22 +210,11 @@ fn vote_common(
     let proof = proof
         .decompress()
         .map_err(|_| AnonVoteError::ProofDecompressionError)?;
-    let public_inputs = [
-        msg_hash,
-        relayer_nu_hash,
-        poll.census_root,
-        u64_to_u256_be(poll.id),
-        u8_to_u256_be(poll.n_choices),
-        poll.coordinator_key.x,
-        poll.coordinator_key.y,
-        relayer_id,
-    ];
-    let mut v = Groth16Verifier::<8>::new(&proof.a, &proof.b, &proof.c, &public_inputs, &VK_VOTE)
-        .map_err(|_| AnonVoteError::InvalidProof)?;
-    v.verify().map_err(|_| AnonVoteError::InvalidProof)?;
+    vote_public_inputs(poll, msg_hash, relayer_nu_hash, relayer_id).verify(&proof)?;
 
     poll.running_msg_hash =
         poseidon(&[&poll.running_msg_hash, &msg_hash]).map_err(|_| AnonVoteError::Poseidon)?;
+    poll.n_messages += 1;
 
     emit!(VoteEvent {
         poll_id: poll.id,
@@ -167,6 +222,8 @@ fn vote_common(
         nonce,
         ciphertext,
         msg_hash,
+        msg_index: poll.n_messages - 1,
+        relay_budget: relay_budget(poll, &common.fee_vault)?,
     });
 
     Ok(())
@@ -177,7 +234,14 @@ pub struct VoteCommon<'info> {
     platform_config: Account<'info, PlatformConfig>,
     #[account(mut)]
     poll: Account<'info, Poll>,
-    /// CHECK: the address is checked
-    #[account(mut, address = platform_config.fee_destination)]
+    /// CHECK: checked with `check_fee_recipient`
+    #[account(mut)]
     platform_fee_destination: AccountInfo<'info>,
+    /// CHECK: the poll's token vault in token polls
+    #[account(mut)]
+    fee_vault: Option<UncheckedAccount<'info>>,
+    /// CHECK: the poll's token program in token polls
+    token_program: Option<UncheckedAccount<'info>>,
+    /// CHECK: the fee token's mint in token polls
+    fee_mint: Option<UncheckedAccount<'info>>,
 }
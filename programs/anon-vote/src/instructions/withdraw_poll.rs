@@ -1,6 +1,13 @@
 use anchor_lang::prelude::*;
 
-use crate::{error::AnonVoteError, state::*, utils::transfer};
+use crate::{
+    error::AnonVoteError,
+    state::*,
+    utils::{
+        fee_mint, fee_vault, spare_lamports, token_account, token_balance, token_program, transfer,
+        vault_transfer,
+    },
+};
 
 #[derive(Accounts)]
 pub struct WithdrawPoll<'info> {
@@ -9,17 +16,50 @@ pub struct WithdrawPoll<'info> {
     fee_destination: AccountInfo<'info>,
     #[account(mut, has_one = fee_destination)]
     poll: Account<'info, Poll>,
+    /// CHECK: the poll's token vault in token polls
+    #[account(mut)]
+    fee_vault: Option<UncheckedAccount<'info>>,
+    /// CHECK: the fee destination's token account in token polls, checked
+    /// with `token_balance`
+    #[account(mut)]
+    fee_destination_tokens: Option<UncheckedAccount<'info>>,
+    /// CHECK: the poll's token program in token polls
+    token_program: Option<UncheckedAccount<'info>>,
+    /// CHECK: the fee token's mint in token polls
+    fee_mint: Option<UncheckedAccount<'info>>,
 }
 
 pub fn withdraw_poll(ctx: Context<WithdrawPoll>) -> Result<()> {
     let fee_destination = &ctx.accounts.fee_destination;
     let poll = &ctx.accounts.poll;
 
-    let now = Clock::get()?.unix_timestamp as u64;
-    require!(now >= poll.voting_end_time, AnonVoteError::BadTime);
+    // the deposit backs the tally until it's published, past the deadline
+    // `fail_poll` refunds it
+    require!(
+        !poll.tally.is_empty() || poll.failed,
+        AnonVoteError::NotTallied
+    );
+
+    transfer(
+        poll.as_ref(),
+        fee_destination,
+        spare_lamports(poll.as_ref())?,
+    )?;
 
-    let rent = Rent::get()?.minimum_balance(AsRef::<AccountInfo>::as_ref(poll).data_len());
-    transfer(poll.as_ref(), fee_destination, poll.get_lamports() - rent)?;
+    if let Some(fee_token) = &poll.fee_token {
+        token_program(fee_token, &ctx.accounts.token_program)?;
+        let vault = fee_vault(fee_token, &ctx.accounts.fee_vault)?;
+        let to = token_account(&ctx.accounts.fee_destination_tokens)?;
+        token_balance(fee_token, to, &poll.fee_destination)?;
+        vault_transfer(
+            poll,
+            fee_token,
+            vault.to_account_info(),
+            fee_mint(fee_token, &ctx.accounts.fee_mint)?.to_account_info(),
+            to.to_account_info(),
+            token_balance(fee_token, vault, &poll.key())?,
+        )?;
+    }
 
     Ok(())
 }
@@ -7,6 +7,7 @@ use anchor_lang::prelude::*;
 
 use crate::{instructions::*, state::*};
 
+pub mod client;
 pub mod error;
 pub mod events;
 mod instructions;
@@ -42,8 +43,8 @@ pub mod anon_vote {
 
     #[instruction(discriminator = 2u8)]
     #[allow(clippy::too_many_arguments)]
-    pub fn create_poll(
-        ctx: Context<CreatePoll>,
+    pub fn create_poll<'info>(
+        ctx: Context<'_, '_, '_, 'info, CreatePoll<'info>>,
         id: u64,
         n_choices: u8,
         description_url: String,
@@ -52,9 +53,11 @@ pub mod anon_vote {
         census_root: [u8; 32],
         voting_start_time: u64,
         voting_end_time: u64,
+        tally_deadline: u64,
         fee: u64,
         fee_destination: Pubkey,
         n_voters: u64,
+        relayers: Vec<Pubkey>,
     ) -> Result<()> {
         instructions::create_poll(
             ctx,
@@ -66,9 +69,11 @@ pub mod anon_vote {
             census_root,
             voting_start_time,
             voting_end_time,
+            tally_deadline,
             fee,
             fee_destination,
             n_voters,
+            relayers,
         )
     }
 
@@ -147,4 +152,29 @@ pub mod anon_vote {
     pub fn withdraw_poll(ctx: Context<WithdrawPoll>) -> Result<()> {
         instructions::withdraw_poll(ctx)
     }
+
+    #[instruction(discriminator = 10u8)]
+    pub fn close_relayer_state(ctx: Context<CloseRelayerState>) -> Result<()> {
+        instructions::close_relayer_state(ctx)
+    }
+
+    #[instruction(discriminator = 11u8)]
+    pub fn fail_poll(ctx: Context<FailPoll>) -> Result<()> {
+        instructions::fail_poll(ctx)
+    }
+
+    #[instruction(discriminator = 12u8)]
+    pub fn close_poll(ctx: Context<ClosePoll>) -> Result<()> {
+        instructions::close_poll(ctx)
+    }
+
+    #[instruction(discriminator = 13u8)]
+    pub fn fund_poll(ctx: Context<FundPoll>, amount: u64) -> Result<()> {
+        instructions::fund_poll(ctx, amount)
+    }
+
+    #[instruction(discriminator = 14u8)]
+    pub fn set_token_fee(ctx: Context<SetTokenFee>, mint: Pubkey, fee: u64) -> Result<()> {
+        instructions::set_token_fee(ctx, mint, fee)
+    }
 }
@@ -1,12 +1,27 @@
 use anchor_lang::prelude::*;
 use solana_bn254::compression::prelude::*;
 
+/// Maximum number of poll options, the number of tally slots of the Tally
+/// circuits.
+pub const MAX_CHOICES: usize = 8;
+
 /// Global fee collection config.
 #[account(discriminator = 1u8)]
 #[derive(Debug, InitSpace)]
 pub struct PlatformConfig {
     pub admin: Pubkey,
     pub fee_destination: Pubkey,
+    /// Fee per message in lamports, polls paying in an SPL token use the
+    /// `PlatformTokenFee` of their mint instead.
+    pub fee: u64,
+}
+
+/// Platform fee per message of polls paying in `mint`, in base units of the
+/// mint.
+#[account(discriminator = 5u8)]
+#[derive(Debug, InitSpace)]
+pub struct PlatformTokenFee {
+    pub mint: Pubkey,
     pub fee: u64,
 }
 
@@ -15,15 +30,33 @@ pub struct PlatformConfig {
 #[derive(Debug, InitSpace)]
 pub struct Poll {
     pub id: u64,
+    /// Bump of the poll PDA, which owns the fee token vault.
+    pub bump: u8,
+    /// Receives the rent back in `close_poll`.
+    pub creator: Pubkey,
     pub n_choices: u8,
     pub coordinator_key: Point,
     pub census_root: [u8; 32],
     pub running_msg_hash: [u8; 32],
+    /// Number of messages hashed into `running_msg_hash`.
+    pub n_messages: u64,
     pub voting_start_time: u64,
     pub voting_end_time: u64,
+    /// The tally has to be finished before this time, after it the poll may
+    /// be marked failed and the unspent deposit refunded.
+    pub tally_deadline: u64,
+    /// No tally was published before the deadline.
+    pub failed: bool,
+    /// The platform fee per message when the poll was created, in the units
+    /// of `fee_token`: base units of the mint, or lamports if absent.
     pub platform_fee: u64,
+    /// The most a relayed message costs the poll, the relayer fee plus the
+    /// platform fee.
+    pub relay_fee: u64,
     pub fee: u64,
     pub fee_destination: Pubkey,
+    /// Token the fees are paid in, lamports if absent.
+    pub fee_token: Option<FeeToken>,
     /// URL containing name, description and names of options.
     #[max_len(0)]
     pub description_url: String,
@@ -44,6 +77,19 @@ impl Poll {
     }
 }
 
+/// SPL token of a poll. All its fees are then amounts of the mint instead of
+/// lamports: the platform fee comes from the mint's `PlatformTokenFee` and
+/// the relayer fees from the relayers' `RelayerTokenFee`.
+#[derive(Clone, Debug, AnchorSerialize, AnchorDeserialize, InitSpace)]
+pub struct FeeToken {
+    pub mint: Pubkey,
+    /// Token account owned by the poll, holding the deposit that pays
+    /// relayers.
+    pub vault: Pubkey,
+    /// `SPL_TOKEN_PROGRAM`, Token-2022 mints aren't taken.
+    pub token_program: Pubkey,
+}
+
 #[derive(Debug, AnchorSerialize, AnchorDeserialize)]
 pub struct Proof {
     pub a: [u8; 64],
@@ -68,7 +114,9 @@ impl CompressedProof {
     }
 }
 
-#[derive(Clone, Copy, Debug, Default, AnchorSerialize, AnchorDeserialize, InitSpace)]
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, AnchorSerialize, AnchorDeserialize, InitSpace,
+)]
 pub struct Point {
     pub x: [u8; 32],
     pub y: [u8; 32],
@@ -77,7 +125,26 @@ pub struct Point {
 #[account(discriminator = 3u8)]
 #[derive(Debug, InitSpace)]
 pub struct Tally {
+    /// The poll and its coordinator key, checked against the poll on finish.
+    pub poll_id: u64,
+    pub coordinator_key: Point,
     pub tally_hash: [u8; 32],
     pub running_msg_hash: [u8; 32],
     pub root: [u8; 32],
 }
+
+/// The outcome of a closed poll, fixed-size for other programs to read.
+#[account(discriminator = 4u8)]
+#[derive(Debug, InitSpace)]
+pub struct PollResult {
+    pub poll_id: u64,
+    pub n_choices: u8,
+    /// No tally was published before the deadline, `tally` is then zero.
+    pub failed: bool,
+    pub n_messages: u64,
+    pub census_root: [u8; 32],
+    pub coordinator_key: Point,
+    pub running_msg_hash: [u8; 32],
+    /// The poll tally, zero past `n_choices`.
+    pub tally: [u64; MAX_CHOICES],
+}
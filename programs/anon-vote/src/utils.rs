@@ -1,6 +1,22 @@
-use anchor_lang::prelude::*;
-use solana_invoke::invoke;
+use anchor_lang::{prelude::*, solana_program::instruction::Instruction};
+use groth16_solana::groth16::{Groth16Verifier, Groth16Verifyingkey};
+use solana_invoke::{invoke, invoke_signed};
 use solana_poseidon::PoseidonSyscallError;
+use zk_relayer::token::is_token_account_data;
+
+use crate::{
+    error::AnonVoteError,
+    state::{FeeToken, Point, Poll, Proof},
+    vk::VK_VOTE,
+};
+
+/// The SPL Token program, the only one fee tokens may use: Token-2022
+/// extensions such as transfer fees or hooks aren't handled.
+pub const SPL_TOKEN_PROGRAM: Pubkey = pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+/// Token account state of initialized, not frozen accounts.
+const ACCOUNT_STATE_INITIALIZED: u8 = 1;
+/// Offset of `decimals` in an SPL mint.
+const MINT_DECIMALS: usize = 44;
 
 pub fn system_transfer<'info>(
     from: AccountInfo<'info>,
@@ -22,6 +38,204 @@ pub fn transfer(from: &AccountInfo, to: &AccountInfo, lamports: u64) -> Result<(
     Ok(())
 }
 
+/// Base fields of an SPL token account.
+pub struct TokenAccount {
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    /// Neither a delegate nor a close authority is set.
+    pub exclusive: bool,
+}
+
+impl TokenAccount {
+    pub fn unpack(account: &AccountInfo, token_program: &Pubkey) -> Result<Self> {
+        require_keys_eq!(
+            *account.owner,
+            *token_program,
+            AnonVoteError::InvalidFeeAccount
+        );
+        let data = account.try_borrow_data()?;
+        require!(
+            is_token_account_data(&data) && data[108] == ACCOUNT_STATE_INITIALIZED,
+            AnonVoteError::InvalidFeeAccount
+        );
+        Ok(Self {
+            mint: Pubkey::new_from_array(data[..32].try_into().unwrap()),
+            owner: Pubkey::new_from_array(data[32..64].try_into().unwrap()),
+            amount: u64::from_le_bytes(data[64..72].try_into().unwrap()),
+            exclusive: data[72..76] == [0; 4] && data[129..133] == [0; 4],
+        })
+    }
+}
+
+/// An optional account that token polls require.
+pub fn token_account<'a, 'info>(
+    account: &'a Option<UncheckedAccount<'info>>,
+) -> Result<&'a AccountInfo<'info>> {
+    Ok(account.as_deref().ok_or(AnonVoteError::InvalidFeeAccount)?)
+}
+
+/// The token program of a token poll.
+pub fn token_program<'a, 'info>(
+    fee_token: &FeeToken,
+    account: &'a Option<UncheckedAccount<'info>>,
+) -> Result<&'a AccountInfo<'info>> {
+    let account = token_account(account)?;
+    require_keys_eq!(
+        account.key(),
+        fee_token.token_program,
+        AnonVoteError::InvalidFeeAccount
+    );
+    Ok(account)
+}
+
+/// The vault of a token poll.
+pub fn fee_vault<'a, 'info>(
+    fee_token: &FeeToken,
+    account: &'a Option<UncheckedAccount<'info>>,
+) -> Result<&'a AccountInfo<'info>> {
+    let account = token_account(account)?;
+    require_keys_eq!(
+        account.key(),
+        fee_token.vault,
+        AnonVoteError::InvalidFeeAccount
+    );
+    Ok(account)
+}
+
+/// The mint of a token poll.
+pub fn fee_mint<'a, 'info>(
+    fee_token: &FeeToken,
+    account: &'a Option<UncheckedAccount<'info>>,
+) -> Result<&'a AccountInfo<'info>> {
+    let account = token_account(account)?;
+    require_keys_eq!(
+        account.key(),
+        fee_token.mint,
+        AnonVoteError::InvalidFeeAccount
+    );
+    Ok(account)
+}
+
+/// Balance of `owner`'s token account of the fee token.
+pub fn token_balance(fee_token: &FeeToken, account: &AccountInfo, owner: &Pubkey) -> Result<u64> {
+    let token_account = TokenAccount::unpack(account, &fee_token.token_program)?;
+    require!(
+        token_account.mint == fee_token.mint && token_account.owner == *owner,
+        AnonVoteError::InvalidFeeAccount
+    );
+    Ok(token_account.amount)
+}
+
+/// Checks where a fee goes: to `wallet` itself in lamport polls, to its
+/// token account in token polls.
+pub fn check_fee_recipient(poll: &Poll, account: &AccountInfo, wallet: &Pubkey) -> Result<()> {
+    match &poll.fee_token {
+        Some(fee_token) => token_balance(fee_token, account, wallet).map(|_| ()),
+        None => {
+            require_keys_eq!(account.key(), *wallet, AnonVoteError::InvalidFeeAccount);
+            Ok(())
+        }
+    }
+}
+
+/// What's left to pay relayers: the vault balance in token polls, spare
+/// lamports otherwise.
+pub fn relay_budget(poll: &Account<Poll>, vault: &Option<UncheckedAccount>) -> Result<u64> {
+    match &poll.fee_token {
+        Some(fee_token) => token_balance(fee_token, fee_vault(fee_token, vault)?, &poll.key()),
+        None => spare_lamports(poll.as_ref()),
+    }
+}
+
+/// SPL `TransferChecked`, the same in Token and Token-2022, the token
+/// program checking `mint` and its decimals against the accounts.
+pub fn token_transfer<'info>(
+    token_program: &Pubkey,
+    from: AccountInfo<'info>,
+    mint: AccountInfo<'info>,
+    to: AccountInfo<'info>,
+    authority: AccountInfo<'info>,
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let decimals = *mint
+        .try_borrow_data()?
+        .get(MINT_DECIMALS)
+        .ok_or(AnonVoteError::InvalidFeeAccount)?;
+    let mut data = [0; 10];
+    data[0] = 12;
+    data[1..9].copy_from_slice(&amount.to_le_bytes());
+    data[9] = decimals;
+    let ix = Instruction::new_with_bytes(
+        *token_program,
+        &data,
+        vec![
+            AccountMeta::new(from.key(), false),
+            AccountMeta::new_readonly(mint.key(), false),
+            AccountMeta::new(to.key(), false),
+            AccountMeta::new_readonly(authority.key(), true),
+        ],
+    );
+    invoke_signed(&ix, &[from, mint, to, authority], signer_seeds)?;
+    Ok(())
+}
+
+/// Transfers tokens out of the poll's vault.
+pub fn vault_transfer<'info>(
+    poll: &Account<'info, Poll>,
+    fee_token: &FeeToken,
+    vault: AccountInfo<'info>,
+    mint: AccountInfo<'info>,
+    to: AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    token_transfer(
+        &fee_token.token_program,
+        vault,
+        mint,
+        to,
+        poll.to_account_info(),
+        amount,
+        &[&[&b"POLL"[..], &poll.id.to_le_bytes(), &[poll.bump]]],
+    )
+}
+
+/// Empties and closes the poll's vault, its rent going to `rent_destination`.
+pub fn close_vault<'info>(
+    poll: &Account<'info, Poll>,
+    fee_token: &FeeToken,
+    vault: AccountInfo<'info>,
+    mint: AccountInfo<'info>,
+    to: AccountInfo<'info>,
+    rent_destination: AccountInfo<'info>,
+) -> Result<()> {
+    let amount = token_balance(fee_token, &vault, &poll.key())?;
+    vault_transfer(poll, fee_token, vault.clone(), mint, to, amount)?;
+
+    let ix = Instruction::new_with_bytes(
+        fee_token.token_program,
+        &[9],
+        vec![
+            AccountMeta::new(vault.key(), false),
+            AccountMeta::new(rent_destination.key(), false),
+            AccountMeta::new_readonly(poll.key(), true),
+        ],
+    );
+    invoke_signed(
+        &ix,
+        &[vault, rent_destination, poll.to_account_info()],
+        &[&[&b"POLL"[..], &poll.id.to_le_bytes(), &[poll.bump]]],
+    )?;
+    Ok(())
+}
+
+/// Lamports above the account's rent exemption.
+pub fn spare_lamports(account: &AccountInfo) -> Result<u64> {
+    let rent = Rent::get()?.minimum_balance(account.data_len());
+    Ok(account.lamports().saturating_sub(rent))
+}
+
 pub fn u8_to_u256_be(x: u8) -> [u8; 32] {
     let mut res = [0; 32];
     res[31] = x;
@@ -43,3 +257,61 @@ pub fn poseidon(inputs: &[&[u8]]) -> core::result::Result<[u8; 32], PoseidonSysc
     )
     .map(|x| x.0)
 }
+
+/// `Poseidon(eph_key.x, eph_key.y, nonce, ...ciphertext)`, the message hash
+/// a vote proof commits to.
+pub fn msg_hash(
+    eph_key: &Point,
+    nonce: u64,
+    ciphertext: &[[u8; 32]; 7],
+) -> core::result::Result<[u8; 32], PoseidonSyscallError> {
+    let nonce = u64_to_u256_be(nonce);
+    let mut preimage = [&eph_key.x[..]; 10];
+    preimage[1] = &eph_key.y;
+    preimage[2] = &nonce;
+    for (i, c) in ciphertext.iter().enumerate() {
+        preimage[3 + i] = c;
+    }
+    poseidon(&preimage)
+}
+
+pub fn verify_proof<const N: usize>(
+    proof: &Proof,
+    public_inputs: &[[u8; 32]; N],
+    vk: &Groth16Verifyingkey,
+) -> Result<()> {
+    let mut v = Groth16Verifier::<N>::new(&proof.a, &proof.b, &proof.c, public_inputs, vk)
+        .map_err(|_| AnonVoteError::InvalidProof)?;
+    v.verify().map_err(|_| AnonVoteError::InvalidProof)?;
+    Ok(())
+}
+
+/// Public inputs of a vote proof.
+pub struct VotePublicInputs(pub [[u8; 32]; 8]);
+
+impl VotePublicInputs {
+    /// Verifies the proof with `VK_VOTE`.
+    pub fn verify(&self, proof: &Proof) -> Result<()> {
+        verify_proof(proof, &self.0, &VK_VOTE)
+    }
+}
+
+/// The public inputs a vote proof is checked against, the same for votes
+/// sent directly and through a relayer.
+pub fn vote_public_inputs(
+    poll: &Poll,
+    msg_hash: [u8; 32],
+    relayer_nu_hash: [u8; 32],
+    relayer_id: [u8; 32],
+) -> VotePublicInputs {
+    VotePublicInputs([
+        msg_hash,
+        relayer_nu_hash,
+        poll.census_root,
+        u64_to_u256_be(poll.id),
+        u8_to_u256_be(poll.n_choices),
+        poll.coordinator_key.x,
+        poll.coordinator_key.y,
+        relayer_id,
+    ])
+}
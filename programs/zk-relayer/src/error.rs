@@ -1,6 +1,9 @@
 use anchor_lang::prelude::*;
+use num_enum::TryFromPrimitive;
 
+/// `TryFrom<u32>` takes the code less `ERROR_CODE_OFFSET`.
 #[error_code]
+#[derive(TryFromPrimitive)]
 pub enum ZkRelayerError {
     /// 6000 0x1770
     #[msg("Proof verification failed")]
@@ -20,4 +23,31 @@ pub enum ZkRelayerError {
     /// 6005 0x1775
     #[msg("Target program is not allowed")]
     ProgramNotAllowed,
+    /// 6006 0x1776
+    #[msg("Operation not allowed during that time")]
+    BadTime,
+    /// 6007 0x1777
+    #[msg("Bond is lower than required")]
+    BondTooLow,
+    /// 6008 0x1778
+    #[msg("Relayers are empty, duplicated, unregistered or too many")]
+    InvalidRelayers,
+    /// 6009 0x1779
+    #[msg("Target program is already allowed")]
+    ProgramAlreadyAllowed,
+    /// 6010 0x177a
+    #[msg("Relayer token account is invalid")]
+    InvalidRelayerTokens,
+    /// 6011 0x177b
+    #[msg("Relayer is unbonding")]
+    RelayerUnbonding,
+    /// 6012 0x177c
+    #[msg("Slashed amount exceeds the bond")]
+    SlashExceedsBond,
+    /// 6013 0x177d
+    #[msg("Relayer has no fee for the token")]
+    NoTokenFee,
+    /// 6014 0x177e
+    #[msg("Config already has the current layout")]
+    ConfigAlreadyMigrated,
 }
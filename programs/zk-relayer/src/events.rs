@@ -0,0 +1,12 @@
+use anchor_lang::prelude::*;
+
+#[event(discriminator = 0u8)]
+#[derive(Clone, Debug)]
+pub struct RelayerSlashedEvent {
+    pub operator: Pubkey,
+    pub amount: u64,
+    /// Bond left after the slash.
+    pub bond: u64,
+    /// Why the admin slashed the relayer, such as the offending transaction.
+    pub reason: String,
+}
@@ -0,0 +1,43 @@
+use crate::{error::*, state::*};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct AddAllowedProgram<'info> {
+    #[account(mut)]
+    payer: Signer<'info>,
+    admin: Signer<'info>,
+    #[account(has_one = admin)]
+    relayer_config: Account<'info, ZkRelayerConfig>,
+    #[account(
+        mut,
+        seeds = [&b"ALLOWED_PROGRAMS"[..]],
+        bump,
+        realloc = AllowedPrograms::DISCRIMINATOR.len()
+            + AllowedPrograms::INIT_SPACE
+            + AllowedPrograms::added_space(allowed_programs.programs.len() + 1),
+        realloc::payer = payer,
+        realloc::zero = false,
+    )]
+    allowed_programs: Account<'info, AllowedPrograms>,
+    system_program: Program<'info, System>,
+}
+
+pub fn add_allowed_program(
+    ctx: Context<AddAllowedProgram>,
+    program: Pubkey,
+    fee_multiplier_bps: Option<u32>,
+) -> Result<()> {
+    let allowed_programs = &mut ctx.accounts.allowed_programs;
+
+    require!(
+        allowed_programs.get(&program).is_none(),
+        ZkRelayerError::ProgramAlreadyAllowed
+    );
+
+    allowed_programs.programs.push(AllowedProgram {
+        program,
+        fee_multiplier_bps,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::*, state::*};
+
+#[derive(Accounts)]
+#[instruction(target_program: Pubkey, state_id: u64)]
+pub struct CloseRelayerState<'info> {
+    /// CHECK: it only receives the rent, chosen by the target program
+    #[account(mut)]
+    destination: AccountInfo<'info>,
+    #[account(
+        mut,
+        close = destination,
+        seeds = [&b"RELAYER_STATE"[..], &target_program.to_bytes(), &state_id.to_le_bytes()],
+        bump,
+    )]
+    relayer_state: Account<'info, RelayerState>,
+    #[account(
+        seeds = [b"ZK_RELAYER_SIGNER"],
+        seeds::program = target_program,
+        bump,
+    )]
+    program_signer: Signer<'info>,
+}
+
+pub fn close_relayer_state(
+    ctx: Context<CloseRelayerState>,
+    _target_program: Pubkey,
+    _state_id: u64,
+) -> Result<()> {
+    let relayer_state = &ctx.accounts.relayer_state;
+
+    let now = Clock::get()?.unix_timestamp as u64;
+    require!(now > relayer_state.end_time, ZkRelayerError::BadTime);
+
+    Ok(())
+}
@@ -1,52 +1,118 @@
 use anchor_lang::prelude::*;
 
-use crate::{error::*, state::*, ALLOWED_PROGRAMS};
+use crate::{error::*, state::*};
 
 const HASH_0_0: [u8; 32] = [
     32, 152, 245, 251, 158, 35, 158, 171, 60, 234, 195, 242, 123, 129, 228, 129, 220, 49, 36, 213,
     95, 254, 213, 35, 168, 57, 238, 132, 70, 182, 72, 100,
 ];
 
+/// Limits the relayer state size and the accounts passed to create it.
+const MAX_RELAYERS: usize = 16;
+
 #[derive(Accounts)]
-#[instruction(target_program: Pubkey, state_id: u64)]
+#[instruction(
+    target_program: Pubkey,
+    state_id: u64,
+    msg_limit: u64,
+    end_time: u64,
+    relayers: Vec<Pubkey>,
+    fee_mint: Option<Pubkey>,
+)]
 pub struct CreateRelayerState<'info> {
     #[account(mut)]
     payer: Signer<'info>,
-    relayer_config: Account<'info, ZkRelayerConfig>,
     #[account(
         init,
         space = RelayerState::DISCRIMINATOR.len()
-            + RelayerState::INIT_SPACE,
+            + RelayerState::INIT_SPACE
+            + RelayerState::added_space(relayers.len()),
         payer = payer,
         seeds = [&b"RELAYER_STATE"[..], &target_program.to_bytes(), &state_id.to_le_bytes()],
         bump,
     )]
     relayer_state: Account<'info, RelayerState>,
+    #[account(
+        seeds = [&b"ALLOWED_PROGRAMS"[..]],
+        bump,
+        constraint = allowed_programs.get(&target_program).is_some()
+            @ ZkRelayerError::ProgramNotAllowed,
+    )]
+    allowed_programs: Account<'info, AllowedPrograms>,
     #[account(
         seeds = [b"ZK_RELAYER_SIGNER"],
         seeds::program = target_program,
         bump,
-        constraint = ALLOWED_PROGRAMS.contains(&target_program)
-            @ ZkRelayerError::ProgramNotAllowed,
     )]
     program_signer: Signer<'info>,
     system_program: Program<'info, System>,
 }
 
-pub fn create_relayer_state(
-    ctx: Context<CreateRelayerState>,
-    _target_program: Pubkey,
+/// `remaining_accounts` are the `RegisteredRelayer` accounts of `relayers`,
+/// in the same order. With a `fee_mint` they are followed by the relayers'
+/// `RelayerTokenFee` accounts of that mint, in the same order too.
+pub fn create_relayer_state<'info>(
+    ctx: Context<'_, '_, 'info, 'info, CreateRelayerState<'info>>,
+    target_program: Pubkey,
     _state_id: u64,
     msg_limit: u64,
     end_time: u64,
+    relayers: Vec<Pubkey>,
+    fee_mint: Option<Pubkey>,
 ) -> Result<()> {
-    let relayer_config = &ctx.accounts.relayer_config;
+    let allowed_program = ctx
+        .accounts
+        .allowed_programs
+        .get(&target_program)
+        .ok_or(ZkRelayerError::ProgramNotAllowed)?
+        .clone();
     let relayer_state = &mut ctx.accounts.relayer_state;
 
-    relayer_state.root_state = HASH_0_0;
+    let n_accounts = match fee_mint {
+        Some(_) => 2 * relayers.len(),
+        None => relayers.len(),
+    };
+    require!(
+        !relayers.is_empty()
+            && relayers.len() <= MAX_RELAYERS
+            && ctx.remaining_accounts.len() == n_accounts,
+        ZkRelayerError::InvalidRelayers
+    );
+    let (registered_relayers, token_fees) = ctx.remaining_accounts.split_at(relayers.len());
+
+    let mut quotas = Vec::with_capacity(relayers.len());
+    for (i, (relayer, account)) in relayers.iter().zip(registered_relayers).enumerate() {
+        let registered_relayer = Account::<RegisteredRelayer>::try_from(account)?;
+        require!(
+            registered_relayer.fee_key == *relayer && !relayers[..i].contains(relayer),
+            ZkRelayerError::InvalidRelayers
+        );
+        require!(
+            registered_relayer.unbonding_end == 0,
+            ZkRelayerError::RelayerUnbonding
+        );
+        let fee = match fee_mint {
+            Some(mint) => {
+                let token_fee = Account::<RelayerTokenFee>::try_from(&token_fees[i])?;
+                require!(
+                    token_fee.fee_key == *relayer && token_fee.mint == mint,
+                    ZkRelayerError::NoTokenFee
+                );
+                token_fee.fee
+            }
+            None => registered_relayer.fee,
+        };
+        quotas.push(RelayerQuota {
+            relayer: *relayer,
+            fee: allowed_program.apply_fee_multiplier(fee),
+            root_state: HASH_0_0,
+        });
+    }
+
     relayer_state.msg_limit = msg_limit;
     relayer_state.end_time = end_time;
-    relayer_state.fee = relayer_config.fee;
+    relayer_state.fee_mint = fee_mint;
+    relayer_state.relayers = quotas;
 
     Ok(())
 }
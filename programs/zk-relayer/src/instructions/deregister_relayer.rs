@@ -0,0 +1,30 @@
+use crate::{error::*, state::*};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct DeregisterRelayer<'info> {
+    #[account(mut)]
+    operator: Signer<'info>,
+    /// Closing returns both the rent and what is left of the bond.
+    #[account(
+        mut,
+        close = operator,
+        seeds = [&b"RELAYER"[..], &operator.key().to_bytes()],
+        bump,
+    )]
+    registered_relayer: Account<'info, RegisteredRelayer>,
+}
+
+/// Requires the unbonding period started by `request_deregistration` to be
+/// over.
+pub fn deregister_relayer(ctx: Context<DeregisterRelayer>) -> Result<()> {
+    let registered_relayer = &ctx.accounts.registered_relayer;
+
+    let now = Clock::get()?.unix_timestamp as u64;
+    require!(
+        registered_relayer.unbonding_end != 0 && now >= registered_relayer.unbonding_end,
+        ZkRelayerError::BadTime
+    );
+
+    Ok(())
+}
@@ -0,0 +1,26 @@
+use crate::state::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct InitAllowedPrograms<'info> {
+    #[account(mut)]
+    payer: Signer<'info>,
+    admin: Signer<'info>,
+    #[account(has_one = admin)]
+    relayer_config: Account<'info, ZkRelayerConfig>,
+    #[account(
+        init,
+        space = AllowedPrograms::DISCRIMINATOR.len() + AllowedPrograms::INIT_SPACE,
+        payer = payer,
+        seeds = [&b"ALLOWED_PROGRAMS"[..]],
+        bump,
+    )]
+    allowed_programs: Account<'info, AllowedPrograms>,
+    system_program: Program<'info, System>,
+}
+
+/// Creates the empty list of allowed programs, for new deployments as well
+/// as the ones upgraded from the compile-time list.
+pub fn init_allowed_programs(_ctx: Context<InitAllowedPrograms>) -> Result<()> {
+    Ok(())
+}
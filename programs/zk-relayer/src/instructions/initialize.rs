@@ -2,15 +2,12 @@ use crate::state::*;
 use anchor_lang::prelude::*;
 
 #[derive(Accounts)]
-#[instruction(relayer_endpoint_len: u32)]
 pub struct Initialize<'info> {
     #[account(mut)]
     payer: Signer<'info>,
     #[account(
         init,
-        space = ZkRelayerConfig::DISCRIMINATOR.len()
-            + ZkRelayerConfig::INIT_SPACE
-            + ZkRelayerConfig::added_space(relayer_endpoint_len as usize),
+        space = ZkRelayerConfig::DISCRIMINATOR.len() + ZkRelayerConfig::INIT_SPACE,
         payer = payer,
         seeds = [&b"RELAYER_CONFIG"[..]],
         bump,
@@ -21,15 +18,15 @@ pub struct Initialize<'info> {
 
 pub fn initialize(
     ctx: Context<Initialize>,
-    relayer: Relayer,
     admin: Pubkey,
-    fee: u64,
+    min_bond: u64,
+    unbonding_period: u64,
 ) -> Result<()> {
     let relayer_config = &mut ctx.accounts.relayer_config;
 
     relayer_config.admin = admin;
-    relayer_config.fee = fee;
-    relayer_config.relayer = relayer;
+    relayer_config.min_bond = min_bond;
+    relayer_config.unbonding_period = unbonding_period;
 
     Ok(())
 }
@@ -0,0 +1,70 @@
+use crate::{error::*, state::*, utils::transfer};
+use anchor_lang::{error::ErrorCode, prelude::*};
+
+/// `ZkRelayerConfig` as it was with a single hard-wired relayer.
+#[derive(AnchorDeserialize)]
+struct LegacyZkRelayerConfig {
+    admin: Pubkey,
+    _fee: u64,
+    _relayer_endpoint: String,
+    _relayer_fee_key: Pubkey,
+}
+
+#[derive(Accounts)]
+pub struct MigrateConfig<'info> {
+    /// Receives the rent freed by the smaller layout.
+    #[account(mut)]
+    admin: Signer<'info>,
+    /// CHECK: deserialized as `LegacyZkRelayerConfig` and checked in the
+    /// instruction
+    #[account(
+        mut,
+        owner = crate::ID,
+        seeds = [&b"RELAYER_CONFIG"[..]],
+        bump,
+    )]
+    relayer_config: UncheckedAccount<'info>,
+}
+
+/// Rewrites the config of a deployment upgraded from the single relayer
+/// layout, the relayer registering itself afterwards.
+pub fn migrate_config(
+    ctx: Context<MigrateConfig>,
+    min_bond: u64,
+    unbonding_period: u64,
+) -> Result<()> {
+    let admin = &ctx.accounts.admin;
+    let account = ctx.accounts.relayer_config.to_account_info();
+
+    let space = ZkRelayerConfig::DISCRIMINATOR.len() + ZkRelayerConfig::INIT_SPACE;
+    require!(
+        account.data_len() != space,
+        ZkRelayerError::ConfigAlreadyMigrated
+    );
+    let legacy = {
+        let data = account.try_borrow_data()?;
+        let data = data
+            .strip_prefix(ZkRelayerConfig::DISCRIMINATOR)
+            .ok_or(ErrorCode::AccountDiscriminatorMismatch)?;
+        LegacyZkRelayerConfig::deserialize(&mut &data[..])
+            .map_err(|_| ErrorCode::AccountDidNotDeserialize)?
+    };
+    require_keys_eq!(legacy.admin, admin.key(), ErrorCode::ConstraintHasOne);
+
+    let rent = Rent::get()?.minimum_balance(space);
+    transfer(
+        &account,
+        &admin.to_account_info(),
+        account.lamports().saturating_sub(rent),
+    )?;
+    account.realloc(space, false)?;
+
+    let config = ZkRelayerConfig {
+        admin: legacy.admin,
+        min_bond,
+        unbonding_period,
+    };
+    config.try_serialize(&mut &mut account.try_borrow_mut_data()?[..])?;
+
+    Ok(())
+}
@@ -1,6 +1,21 @@
-pub use self::{create_relayer_state::*, initialize::*, relay::*, update_config::*};
-
+pub use self::{
+    add_allowed_program::*, close_relayer_state::*, create_relayer_state::*, deregister_relayer::*,
+    init_allowed_programs::*, initialize::*, migrate_config::*, register_relayer::*, relay::*,
+    remove_allowed_program::*, request_deregistration::*, set_token_fee::*, slash_relayer::*,
+    update_config::*, update_relayer::*,
+};
+mod add_allowed_program;
+mod close_relayer_state;
 mod create_relayer_state;
+mod deregister_relayer;
+mod init_allowed_programs;
 mod initialize;
+mod migrate_config;
+mod register_relayer;
 mod relay;
+mod remove_allowed_program;
+mod request_deregistration;
+mod set_token_fee;
+mod slash_relayer;
 mod update_config;
+mod update_relayer;
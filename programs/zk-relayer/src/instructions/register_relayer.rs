@@ -0,0 +1,48 @@
+use crate::{error::*, state::*, utils::system_transfer};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+#[instruction(endpoint_len: u32)]
+pub struct RegisterRelayer<'info> {
+    #[account(mut)]
+    operator: Signer<'info>,
+    #[account(seeds = [&b"RELAYER_CONFIG"[..]], bump)]
+    relayer_config: Account<'info, ZkRelayerConfig>,
+    #[account(
+        init,
+        space = RegisteredRelayer::DISCRIMINATOR.len()
+            + RegisteredRelayer::INIT_SPACE
+            + RegisteredRelayer::added_space(endpoint_len as usize),
+        payer = operator,
+        seeds = [&b"RELAYER"[..], &operator.key().to_bytes()],
+        bump,
+    )]
+    registered_relayer: Account<'info, RegisteredRelayer>,
+    system_program: Program<'info, System>,
+}
+
+pub fn register_relayer(
+    ctx: Context<RegisterRelayer>,
+    endpoint: String,
+    fee: u64,
+    bond: u64,
+) -> Result<()> {
+    let operator = &ctx.accounts.operator;
+    let relayer_config = &ctx.accounts.relayer_config;
+    let registered_relayer = &mut ctx.accounts.registered_relayer;
+
+    require!(bond >= relayer_config.min_bond, ZkRelayerError::BondTooLow);
+
+    system_transfer(
+        operator.to_account_info(),
+        registered_relayer.to_account_info(),
+        bond,
+    )?;
+
+    registered_relayer.fee_key = operator.key();
+    registered_relayer.fee = fee;
+    registered_relayer.bond = bond;
+    registered_relayer.endpoint = endpoint;
+
+    Ok(())
+}
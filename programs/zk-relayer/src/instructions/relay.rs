@@ -2,27 +2,37 @@ use anchor_lang::{prelude::*, solana_program::instruction::Instruction};
 use groth16_solana::groth16::Groth16Verifier;
 use solana_invoke::invoke_signed;
 
-use crate::{error::*, state::*, utils::u64_to_u128_be, vk::VK_RELAY, ALLOWED_PROGRAMS};
+use crate::{
+    error::*,
+    state::*,
+    utils::{token_balance, u64_to_u128_be},
+    vk::VK_RELAY,
+};
 
 #[derive(Accounts)]
 #[instruction(state_id: u64)]
 pub struct Relay<'info> {
-    #[account(mut, address = relayer_config.relayer.fee_key)]
+    #[account(mut)]
     relayer: Signer<'info>,
-    relayer_config: Account<'info, ZkRelayerConfig>,
     #[account(
         mut,
         seeds = [&b"RELAYER_STATE"[..], &target_program.key.to_bytes(), &state_id.to_le_bytes()],
         bump,
     )]
     relayer_state: Account<'info, RelayerState>,
-    /// CHECK: it's one of the allowed programs
     #[account(
-        constraint = ALLOWED_PROGRAMS.contains(target_program.key)
-            @ ZkRelayerError::ProgramNotAllowed
+        seeds = [&b"ALLOWED_PROGRAMS"[..]],
+        bump,
+        constraint = allowed_programs.get(target_program.key).is_some()
+            @ ZkRelayerError::ProgramNotAllowed,
     )]
+    allowed_programs: Account<'info, AllowedPrograms>,
+    /// CHECK: it's one of the allowed programs
     target_program: AccountInfo<'info>,
     system_program: Program<'info, System>,
+    /// CHECK: token account of the relayer, for relayer states with a
+    /// `fee_mint`, checked with `token_balance`
+    relayer_tokens: Option<UncheckedAccount<'info>>,
 }
 
 pub fn relay<'info>(
@@ -39,11 +49,20 @@ pub fn relay<'info>(
     let relayer_state = &mut ctx.accounts.relayer_state;
     let target_program = &ctx.accounts.target_program;
 
+    let quota_index = relayer_state
+        .relayers
+        .iter()
+        .position(|q| q.relayer == relayer.key())
+        .ok_or(ZkRelayerError::RelayerNotAllowed)?;
+    let quota = &relayer_state.relayers[quota_index];
+    let fee = quota.fee;
+    let fee_mint = relayer_state.fee_mint;
+
     let proof = proof
         .decompress()
         .map_err(|_| ZkRelayerError::ProofDecompressionError)?;
     let public_inputs = [
-        relayer_state.root_state,
+        quota.root_state,
         root_state_after,
         nu_hash,
         msg_hash,
@@ -53,7 +72,7 @@ pub fn relay<'info>(
         .map_err(|_| ZkRelayerError::InvalidProof)?;
     v.verify().map_err(|_| ZkRelayerError::InvalidProof)?;
 
-    relayer_state.root_state = root_state_after;
+    relayer_state.relayers[quota_index].root_state = root_state_after;
 
     let mut relayer_id = relayer.key().to_bytes();
     relayer_id[0] &= (1 << 5) - 1;
@@ -89,7 +108,14 @@ pub fn relay<'info>(
     accounts.push(relayer_state.to_account_info());
     accounts.extend(ctx.remaining_accounts.iter().cloned());
 
-    let balance_before = relayer.get_lamports();
+    let relayer_tokens = ctx.accounts.relayer_tokens.as_deref();
+    let balance = || match (fee_mint, relayer_tokens) {
+        (Some(mint), Some(account)) => token_balance(account, relayer.key, &mint),
+        (None, None) => Ok(relayer.get_lamports()),
+        _ => err!(ZkRelayerError::InvalidRelayerTokens),
+    };
+
+    let balance_before = balance()?;
 
     let ix = Instruction::new_with_bytes(ctx.accounts.target_program.key(), &full_data, metas);
     invoke_signed(
@@ -103,10 +129,10 @@ pub fn relay<'info>(
         ]],
     )?;
 
-    let balance_after = relayer.get_lamports();
+    let balance_after = balance()?;
 
     require!(
-        balance_after > balance_before && balance_after - balance_before >= relayer_state.fee,
+        balance_after > balance_before && balance_after - balance_before >= fee,
         ZkRelayerError::RelayerNotFunded
     );
 
@@ -0,0 +1,36 @@
+use crate::{error::*, state::*};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct RemoveAllowedProgram<'info> {
+    #[account(mut)]
+    payer: Signer<'info>,
+    admin: Signer<'info>,
+    #[account(has_one = admin)]
+    relayer_config: Account<'info, ZkRelayerConfig>,
+    #[account(
+        mut,
+        seeds = [&b"ALLOWED_PROGRAMS"[..]],
+        bump,
+        realloc = AllowedPrograms::DISCRIMINATOR.len()
+            + AllowedPrograms::INIT_SPACE
+            + AllowedPrograms::added_space(allowed_programs.programs.len().saturating_sub(1)),
+        realloc::payer = payer,
+        realloc::zero = false,
+    )]
+    allowed_programs: Account<'info, AllowedPrograms>,
+    system_program: Program<'info, System>,
+}
+
+pub fn remove_allowed_program(ctx: Context<RemoveAllowedProgram>, program: Pubkey) -> Result<()> {
+    let allowed_programs = &mut ctx.accounts.allowed_programs;
+
+    let index = allowed_programs
+        .programs
+        .iter()
+        .position(|p| p.program == program)
+        .ok_or(ZkRelayerError::ProgramNotAllowed)?;
+    allowed_programs.programs.remove(index);
+
+    Ok(())
+}
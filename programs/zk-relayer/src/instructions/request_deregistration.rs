@@ -0,0 +1,31 @@
+use crate::{error::*, state::*};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct RequestDeregistration<'info> {
+    operator: Signer<'info>,
+    #[account(seeds = [&b"RELAYER_CONFIG"[..]], bump)]
+    relayer_config: Account<'info, ZkRelayerConfig>,
+    #[account(
+        mut,
+        seeds = [&b"RELAYER"[..], &operator.key().to_bytes()],
+        bump,
+    )]
+    registered_relayer: Account<'info, RegisteredRelayer>,
+}
+
+/// Starts the unbonding period, after which the relayer can deregister.
+pub fn request_deregistration(ctx: Context<RequestDeregistration>) -> Result<()> {
+    let relayer_config = &ctx.accounts.relayer_config;
+    let registered_relayer = &mut ctx.accounts.registered_relayer;
+
+    require!(
+        registered_relayer.unbonding_end == 0,
+        ZkRelayerError::RelayerUnbonding
+    );
+
+    let now = Clock::get()?.unix_timestamp as u64;
+    registered_relayer.unbonding_end = now.saturating_add(relayer_config.unbonding_period);
+
+    Ok(())
+}
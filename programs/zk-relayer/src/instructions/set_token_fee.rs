@@ -0,0 +1,32 @@
+use crate::state::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct SetTokenFee<'info> {
+    #[account(mut)]
+    operator: Signer<'info>,
+    #[account(seeds = [&b"RELAYER"[..], &operator.key().to_bytes()], bump)]
+    registered_relayer: Account<'info, RegisteredRelayer>,
+    #[account(
+        init_if_needed,
+        space = RelayerTokenFee::DISCRIMINATOR.len() + RelayerTokenFee::INIT_SPACE,
+        payer = operator,
+        seeds = [&b"RELAYER_TOKEN_FEE"[..], &operator.key().to_bytes(), &mint.to_bytes()],
+        bump,
+    )]
+    token_fee: Account<'info, RelayerTokenFee>,
+    system_program: Program<'info, System>,
+}
+
+/// Sets the relayer's fee for target programs paying in `mint`, in its base
+/// units. It applies to relayer states created afterwards.
+pub fn set_token_fee(ctx: Context<SetTokenFee>, mint: Pubkey, fee: u64) -> Result<()> {
+    let token_fee = &mut ctx.accounts.token_fee;
+
+    token_fee.fee_key = ctx.accounts.operator.key();
+    token_fee.mint = mint;
+    token_fee.fee = fee;
+
+    Ok(())
+}
@@ -0,0 +1,50 @@
+use crate::{error::*, events::RelayerSlashedEvent, state::*, utils::transfer};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+#[instruction(operator: Pubkey)]
+pub struct SlashRelayer<'info> {
+    admin: Signer<'info>,
+    #[account(has_one = admin)]
+    relayer_config: Account<'info, ZkRelayerConfig>,
+    #[account(
+        mut,
+        seeds = [&b"RELAYER"[..], &operator.to_bytes()],
+        bump,
+    )]
+    registered_relayer: Account<'info, RegisteredRelayer>,
+    /// CHECK: receives the slashed lamports
+    #[account(mut)]
+    destination: UncheckedAccount<'info>,
+}
+
+/// Moves `amount` of the bond of a misbehaving relayer to `destination`, at
+/// most the whole bond, and records `reason` in a `RelayerSlashedEvent`.
+pub fn slash_relayer(
+    ctx: Context<SlashRelayer>,
+    operator: Pubkey,
+    amount: u64,
+    reason: String,
+) -> Result<()> {
+    let registered_relayer = &mut ctx.accounts.registered_relayer;
+
+    require!(
+        amount <= registered_relayer.bond,
+        ZkRelayerError::SlashExceedsBond
+    );
+    registered_relayer.bond -= amount;
+    transfer(
+        &registered_relayer.to_account_info(),
+        &ctx.accounts.destination,
+        amount,
+    )?;
+
+    emit!(RelayerSlashedEvent {
+        operator,
+        amount,
+        bond: registered_relayer.bond,
+        reason,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,50 @@
+use crate::{
+    state::*,
+    utils::{system_transfer, transfer},
+};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct UpdateRelayer<'info> {
+    #[account(mut)]
+    operator: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [&b"RELAYER"[..], &operator.key().to_bytes()],
+        bump,
+    )]
+    registered_relayer: Account<'info, RegisteredRelayer>,
+    system_program: Program<'info, System>,
+}
+
+/// Changes the relayer's endpoint and lamport fee without touching its bond.
+/// Relayer states created before keep the fees fixed in their quotas.
+pub fn update_relayer(ctx: Context<UpdateRelayer>, endpoint: String, fee: u64) -> Result<()> {
+    let operator = &ctx.accounts.operator;
+    let registered_relayer = &mut ctx.accounts.registered_relayer;
+
+    // the bond sits on top of rent, so the rent difference is settled with
+    // the operator here rather than with `realloc`, which would touch it
+    let space = RegisteredRelayer::DISCRIMINATOR.len()
+        + RegisteredRelayer::INIT_SPACE
+        + RegisteredRelayer::added_space(endpoint.len());
+    let account = registered_relayer.to_account_info();
+    let rent = Rent::get()?;
+    let old_rent = rent.minimum_balance(account.data_len());
+    let new_rent = rent.minimum_balance(space);
+    if new_rent > old_rent {
+        system_transfer(
+            operator.to_account_info(),
+            account.clone(),
+            new_rent - old_rent,
+        )?;
+    } else {
+        transfer(&account, &operator.to_account_info(), old_rent - new_rent)?;
+    }
+    account.realloc(space, false)?;
+
+    registered_relayer.fee = fee;
+    registered_relayer.endpoint = endpoint;
+
+    Ok(())
+}
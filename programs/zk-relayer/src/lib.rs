@@ -8,18 +8,15 @@ use anchor_lang::prelude::*;
 use crate::{instructions::*, state::*};
 
 pub mod error;
+pub mod events;
 mod instructions;
 pub mod state;
+pub mod token;
 mod utils;
 pub mod vk;
 
 declare_id!("Re1aDNwUYroFdKmsPDKnZaQPxxKtDBvwNctH1w9ow6j");
 
-/// The list of allowed programs. Relayer has to trust the program, otherwise
-/// a transaction could fail and waste network fee. Transaction simulation
-/// wouldn't help with that.
-const ALLOWED_PROGRAMS: &[Pubkey] = &[pubkey!("MootG8ueTooVorJJq3kxdXLLg59ZW6phaHpoWeSySqB")];
-
 #[program]
 pub mod zk_relayer {
     use super::*;
@@ -27,32 +24,42 @@ pub mod zk_relayer {
     #[instruction(discriminator = 0u8)]
     pub fn initialize(
         ctx: Context<Initialize>,
-        relayer: Relayer,
         admin: Pubkey,
-        fee: u64,
+        min_bond: u64,
+        unbonding_period: u64,
     ) -> Result<()> {
-        instructions::initialize(ctx, relayer, admin, fee)
+        instructions::initialize(ctx, admin, min_bond, unbonding_period)
     }
 
     #[instruction(discriminator = 1u8)]
     pub fn update_config(
         ctx: Context<UpdateConfig>,
-        relayer: Relayer,
         admin: Pubkey,
-        fee: u64,
+        min_bond: u64,
+        unbonding_period: u64,
     ) -> Result<()> {
-        instructions::update_config(ctx, relayer, admin, fee)
+        instructions::update_config(ctx, admin, min_bond, unbonding_period)
     }
 
     #[instruction(discriminator = 2u8)]
-    pub fn create_relayer_state(
-        ctx: Context<CreateRelayerState>,
+    pub fn create_relayer_state<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CreateRelayerState<'info>>,
         target_program: Pubkey,
         state_id: u64,
         msg_limit: u64,
         end_time: u64,
+        relayers: Vec<Pubkey>,
+        fee_mint: Option<Pubkey>,
     ) -> Result<()> {
-        instructions::create_relayer_state(ctx, target_program, state_id, msg_limit, end_time)
+        instructions::create_relayer_state(
+            ctx,
+            target_program,
+            state_id,
+            msg_limit,
+            end_time,
+            relayers,
+            fee_mint,
+        )
     }
 
     #[instruction(discriminator = 3u8)]
@@ -78,5 +85,83 @@ pub mod zk_relayer {
         )
     }
 
-    // TODO close_relayer_state
+    #[instruction(discriminator = 4u8)]
+    pub fn close_relayer_state(
+        ctx: Context<CloseRelayerState>,
+        target_program: Pubkey,
+        state_id: u64,
+    ) -> Result<()> {
+        instructions::close_relayer_state(ctx, target_program, state_id)
+    }
+
+    #[instruction(discriminator = 5u8)]
+    pub fn register_relayer(
+        ctx: Context<RegisterRelayer>,
+        endpoint: String,
+        fee: u64,
+        bond: u64,
+    ) -> Result<()> {
+        instructions::register_relayer(ctx, endpoint, fee, bond)
+    }
+
+    #[instruction(discriminator = 6u8)]
+    pub fn deregister_relayer(ctx: Context<DeregisterRelayer>) -> Result<()> {
+        instructions::deregister_relayer(ctx)
+    }
+
+    #[instruction(discriminator = 7u8)]
+    pub fn add_allowed_program(
+        ctx: Context<AddAllowedProgram>,
+        program: Pubkey,
+        fee_multiplier_bps: Option<u32>,
+    ) -> Result<()> {
+        instructions::add_allowed_program(ctx, program, fee_multiplier_bps)
+    }
+
+    #[instruction(discriminator = 8u8)]
+    pub fn remove_allowed_program(
+        ctx: Context<RemoveAllowedProgram>,
+        program: Pubkey,
+    ) -> Result<()> {
+        instructions::remove_allowed_program(ctx, program)
+    }
+
+    #[instruction(discriminator = 9u8)]
+    pub fn request_deregistration(ctx: Context<RequestDeregistration>) -> Result<()> {
+        instructions::request_deregistration(ctx)
+    }
+
+    #[instruction(discriminator = 10u8)]
+    pub fn slash_relayer(
+        ctx: Context<SlashRelayer>,
+        operator: Pubkey,
+        amount: u64,
+        reason: String,
+    ) -> Result<()> {
+        instructions::slash_relayer(ctx, operator, amount, reason)
+    }
+
+    #[instruction(discriminator = 11u8)]
+    pub fn set_token_fee(ctx: Context<SetTokenFee>, mint: Pubkey, fee: u64) -> Result<()> {
+        instructions::set_token_fee(ctx, mint, fee)
+    }
+
+    #[instruction(discriminator = 12u8)]
+    pub fn update_relayer(ctx: Context<UpdateRelayer>, endpoint: String, fee: u64) -> Result<()> {
+        instructions::update_relayer(ctx, endpoint, fee)
+    }
+
+    #[instruction(discriminator = 13u8)]
+    pub fn init_allowed_programs(ctx: Context<InitAllowedPrograms>) -> Result<()> {
+        instructions::init_allowed_programs(ctx)
+    }
+
+    #[instruction(discriminator = 14u8)]
+    pub fn migrate_config(
+        ctx: Context<MigrateConfig>,
+        min_bond: u64,
+        unbonding_period: u64,
+    ) -> Result<()> {
+        instructions::migrate_config(ctx, min_bond, unbonding_period)
+    }
 }
@@ -5,21 +5,85 @@ use solana_bn254::compression::prelude::*;
 #[derive(Debug, InitSpace)]
 pub struct ZkRelayerConfig {
     pub admin: Pubkey,
+    /// Minimum bond a relayer has to lock to register.
+    pub min_bond: u64,
+    /// Seconds between a deregistration request and the bond being returned.
+    pub unbonding_period: u64,
+}
+
+/// Relayer registered by its operator. The operator's key is the one that
+/// signs relayed transactions and receives the fees.
+#[account(discriminator = 252u8)]
+#[derive(Debug, InitSpace)]
+pub struct RegisteredRelayer {
+    pub fee_key: Pubkey,
+    /// Fee per relayed message in lamports. Target programs paying in an SPL
+    /// token use the relayer's `RelayerTokenFee` for its mint instead.
     pub fee: u64,
-    pub relayer: Relayer,
+    /// Lamports locked on top of rent, returned on deregistration after the
+    /// unbonding period and slashable by the admin until then.
+    pub bond: u64,
+    /// When the relayer can deregister, 0 if it hasn't requested to.
+    pub unbonding_end: u64,
+    #[max_len(0)]
+    pub endpoint: String,
 }
 
-impl ZkRelayerConfig {
-    pub fn added_space(relayer_endpoint_len: usize) -> usize {
-        Relayer::INIT_SPACE + relayer_endpoint_len
+impl RegisteredRelayer {
+    pub fn added_space(endpoint_len: usize) -> usize {
+        endpoint_len
     }
 }
 
-#[derive(Clone, Debug, AnchorSerialize, AnchorDeserialize, InitSpace)]
-pub struct Relayer {
-    #[max_len(0)]
-    pub endpoint: String,
+/// Fee per relayed message of a relayer for target programs paying in
+/// `mint`, in base units of the mint.
+#[account(discriminator = 249u8)]
+#[derive(Debug, InitSpace)]
+pub struct RelayerTokenFee {
     pub fee_key: Pubkey,
+    pub mint: Pubkey,
+    pub fee: u64,
+}
+
+/// The list of allowed programs. Relayer has to trust the program, otherwise
+/// a transaction could fail and waste network fee. Transaction simulation
+/// wouldn't help with that.
+#[account(discriminator = 253u8)]
+#[derive(Debug, InitSpace)]
+pub struct AllowedPrograms {
+    #[max_len(0)]
+    pub programs: Vec<AllowedProgram>,
+}
+
+impl AllowedPrograms {
+    pub fn added_space(n_programs: usize) -> usize {
+        n_programs * AllowedProgram::INIT_SPACE
+    }
+
+    pub fn get(&self, program: &Pubkey) -> Option<&AllowedProgram> {
+        self.programs.iter().find(|p| p.program == *program)
+    }
+}
+
+#[derive(Clone, Debug, AnchorSerialize, AnchorDeserialize, InitSpace)]
+pub struct AllowedProgram {
+    pub program: Pubkey,
+    /// Multiplier of the relayer fees for this program in basis points,
+    /// the fees are used as is if absent.
+    pub fee_multiplier_bps: Option<u32>,
+}
+
+impl AllowedProgram {
+    pub const BPS: u64 = 10_000;
+
+    pub fn apply_fee_multiplier(&self, fee: u64) -> u64 {
+        match self.fee_multiplier_bps {
+            Some(bps) => {
+                u64::try_from(fee as u128 * bps as u128 / Self::BPS as u128).unwrap_or(u64::MAX)
+            }
+            None => fee,
+        }
+    }
 }
 
 #[derive(Debug, AnchorSerialize, AnchorDeserialize)]
@@ -52,14 +116,35 @@ pub struct Point {
     pub y: [u8; 32],
 }
 
+/// Quota of a single relayer within a `RelayerState`.
+#[derive(Clone, Debug, AnchorSerialize, AnchorDeserialize, InitSpace)]
+pub struct RelayerQuota {
+    pub relayer: Pubkey,
+    /// Fee fixed at the time of creation, in the units of the state's
+    /// `fee_mint`.
+    pub fee: u64,
+    pub root_state: [u8; 32],
+}
+
 /// State per target program event, such as a poll.
 #[account(discriminator = 251u8)]
 #[derive(Debug, InitSpace)]
 pub struct RelayerState {
-    pub root_state: [u8; 32],
-    /// Fee fixed at the time of creation.
-    pub fee: u64,
-    /// Message limit per user.
+    /// Message limit per user per relayer.
     pub msg_limit: u64,
     pub end_time: u64,
+    /// Mint the fees are paid in, lamports if absent.
+    pub fee_mint: Option<Pubkey>,
+    #[max_len(0)]
+    pub relayers: Vec<RelayerQuota>,
+}
+
+impl RelayerState {
+    pub fn added_space(n_relayers: usize) -> usize {
+        n_relayers * RelayerQuota::INIT_SPACE
+    }
+
+    pub fn quota(&self, relayer: &Pubkey) -> Option<&RelayerQuota> {
+        self.relayers.iter().find(|q| q.relayer == *relayer)
+    }
 }
@@ -0,0 +1,17 @@
+use anchor_lang::prelude::*;
+
+/// SPL Token and Token-2022.
+pub const TOKEN_PROGRAMS: [Pubkey; 2] = [
+    pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+    pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb"),
+];
+/// Length of an SPL token account, Token-2022 extensions come after it.
+pub const TOKEN_ACCOUNT_LEN: usize = 165;
+/// Token-2022 account type of token accounts, mints have 1.
+pub const ACCOUNT_TYPE_ACCOUNT: u8 = 2;
+
+/// Whether `data` is laid out as a token account of either token program.
+pub fn is_token_account_data(data: &[u8]) -> bool {
+    data.len() >= TOKEN_ACCOUNT_LEN
+        && (data.len() == TOKEN_ACCOUNT_LEN || data[TOKEN_ACCOUNT_LEN] == ACCOUNT_TYPE_ACCOUNT)
+}
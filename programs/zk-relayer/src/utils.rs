@@ -1,6 +1,11 @@
 use anchor_lang::prelude::*;
 use solana_invoke::invoke;
 
+use crate::{
+    error::ZkRelayerError,
+    token::{is_token_account_data, TOKEN_PROGRAMS},
+};
+
 pub fn system_transfer<'info>(
     from: AccountInfo<'info>,
     to: AccountInfo<'info>,
@@ -21,6 +26,19 @@ pub fn transfer(from: &AccountInfo, to: &AccountInfo, lamports: u64) -> Result<(
     Ok(())
 }
 
+/// Balance of a token account of `mint` owned by `owner`.
+pub fn token_balance(account: &AccountInfo, owner: &Pubkey, mint: &Pubkey) -> Result<u64> {
+    let data = account.try_borrow_data()?;
+    require!(
+        TOKEN_PROGRAMS.contains(account.owner)
+            && is_token_account_data(&data)
+            && data[..32] == mint.to_bytes()
+            && data[32..64] == owner.to_bytes(),
+        ZkRelayerError::InvalidRelayerTokens
+    );
+    Ok(u64::from_le_bytes(data[64..72].try_into().unwrap()))
+}
+
 pub fn u64_to_u128_be(x: u64) -> [u8; 32] {
     let mut res = [0; 32];
     res[32 - 8..].copy_from_slice(&x.to_be_bytes());
@@ -0,0 +1,215 @@
+use anchor_lang::{AccountDeserialize, AnchorDeserialize, Discriminator};
+use anon_vote::{
+    client::{msg_hash, vote_public_inputs},
+    instruction::VoteWithRelayer,
+    state::{FeeToken, PlatformConfig, Poll},
+};
+use solana_sdk::{account::Account, pubkey::Pubkey};
+use solana_tools::solana_transactor::RpcPool;
+use std::{future::Future, pin::Pin};
+use zk_relayer::token::is_token_account_data;
+
+use super::{check_account, AdapterError, RelayCall, TargetAdapter};
+use crate::{
+    server::{fetch_account, find_associated_token_account},
+    utils::now,
+};
+
+/// Accounts of `vote_with_relayer` after the relayer and its `RelayerState`:
+/// the `VoteCommon` accounts and the relayer's token account.
+const VOTE_ACCOUNTS: usize = 7;
+
+/// Checks `vote_with_relayer` the way `vote_common` does.
+pub struct AnonVoteAdapter;
+
+impl TargetAdapter for AnonVoteAdapter {
+    fn program(&self) -> Pubkey {
+        anon_vote::ID
+    }
+
+    fn validate<'a>(
+        &'a self,
+        rpc_pool: &'a RpcPool,
+        call: &'a RelayCall<'a>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), AdapterError>> + 'a>> {
+        Box::pin(validate(rpc_pool, call))
+    }
+}
+
+async fn validate(rpc_pool: &RpcPool, call: &RelayCall<'_>) -> Result<(), AdapterError> {
+    if [call.discriminator] != VoteWithRelayer::DISCRIMINATOR {
+        return Err(AdapterError::InstructionNotRelayable(call.discriminator));
+    }
+    let args = VoteWithRelayer::deserialize(&mut &call.args()[..])
+        .map_err(|_| AdapterError::MalformedData)?;
+    let msg_hash = msg_hash(&args.eph_key, args.nonce, &args.ciphertext)
+        .map_err(|_| AdapterError::MalformedData)?;
+    if msg_hash != call.msg_hash {
+        return Err(AdapterError::MsgHashMismatch);
+    }
+    let proof = args
+        .proof
+        .decompress()
+        .map_err(|_| AdapterError::InvalidProof)?;
+
+    let poll = fetch_account(rpc_pool, find_poll(call.state_id))
+        .await
+        .and_then(|account| Poll::try_deserialize(&mut &account.data[..]).ok())
+        .ok_or(AdapterError::StateUnavailable)?;
+    if !(poll.voting_start_time..=poll.voting_end_time).contains(&now()) {
+        return Err(AdapterError::BadTime);
+    }
+    check_accounts(rpc_pool, call, &poll).await?;
+
+    vote_public_inputs(&poll, msg_hash, args.relayer_nu_hash, args.relayer_id)
+        .verify(&proof)
+        .map_err(|_| AdapterError::InvalidProof)
+}
+
+/// Checks the accounts to be exactly the ones of the poll, absent optional
+/// accounts being passed as the program id.
+async fn check_accounts(
+    rpc_pool: &RpcPool,
+    call: &RelayCall<'_>,
+    poll: &Poll,
+) -> Result<(), AdapterError> {
+    let accounts = call.accounts;
+    if accounts.len() != VOTE_ACCOUNTS {
+        return Err(AdapterError::AccountCount {
+            expected: VOTE_ACCOUNTS,
+            got: accounts.len(),
+        });
+    }
+    let platform_config = fetch_account(rpc_pool, find_platform_config())
+        .await
+        .and_then(|account| PlatformConfig::try_deserialize(&mut &account.data[..]).ok())
+        .ok_or(AdapterError::StateUnavailable)?;
+
+    check_account(accounts, 0, find_platform_config(), false)?;
+    check_account(accounts, 1, find_poll(call.state_id), true)?;
+    let Some(fee_token) = &poll.fee_token else {
+        if call.fee_vault.is_some() {
+            return Err(AdapterError::FeeVaultMismatch);
+        }
+        check_account(accounts, 2, platform_config.fee_destination, true)?;
+        for index in 3..VOTE_ACCOUNTS {
+            check_account(accounts, index, anon_vote::ID, false)?;
+        }
+        return Ok(());
+    };
+
+    if call.fee_vault != Some(fee_token.vault) {
+        return Err(AdapterError::FeeVaultMismatch);
+    }
+    // any token account of the fee destination, as `check_fee_recipient`
+    // takes
+    if !accounts[2].is_writable {
+        return Err(AdapterError::AccountWritability {
+            index: 2,
+            writable: true,
+        });
+    }
+    fetch_account(rpc_pool, accounts[2].pubkey)
+        .await
+        .filter(|account| is_token_account(account, fee_token, &platform_config.fee_destination))
+        .ok_or(AdapterError::FeeAccountInvalid(2))?;
+    check_account(accounts, 3, fee_token.vault, true)?;
+    check_account(accounts, 4, fee_token.token_program, false)?;
+    check_account(accounts, 5, fee_token.mint, false)?;
+    let relayer_tokens =
+        find_associated_token_account(&call.relayer, &fee_token.token_program, &fee_token.mint);
+    check_account(accounts, 6, relayer_tokens, true)
+}
+
+/// An initialized token account of the fee token owned by `owner`, as
+/// `TokenAccount::unpack` takes.
+fn is_token_account(account: &Account, fee_token: &FeeToken, owner: &Pubkey) -> bool {
+    let data = &account.data;
+    account.owner == fee_token.token_program
+        && is_token_account_data(data)
+        && data[108] == 1
+        && data[..32] == fee_token.mint.to_bytes()
+        && data[32..64] == owner.to_bytes()
+}
+
+fn find_poll(poll_id: u64) -> Pubkey {
+    Pubkey::find_program_address(&[b"POLL", &poll_id.to_le_bytes()], &anon_vote::ID).0
+}
+
+fn find_platform_config() -> Pubkey {
+    Pubkey::find_program_address(&[b"PLATFORM_CONFIG"], &anon_vote::ID).0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::{prelude::borsh, AnchorSerialize};
+    use anon_vote::state::{CompressedProof, Point};
+    use zk_relayer::token::TOKEN_ACCOUNT_LEN;
+
+    #[test]
+    fn test_decode_relay_call() {
+        let eph_key = Point {
+            x: [1; 32],
+            y: [2; 32],
+        };
+        let proof = CompressedProof {
+            a: [4; 32],
+            b: [5; 64],
+            c: [6; 32],
+        };
+        let mut data = borsh::to_vec(&eph_key).unwrap();
+        data.extend(7u64.to_le_bytes());
+        data.extend([[3u8; 32]; 7].concat());
+        proof.serialize(&mut data).unwrap();
+        let relayer = Pubkey::new_from_array([0xff; 32]);
+        let call = RelayCall {
+            state_id: 0,
+            relayer,
+            discriminator: VoteWithRelayer::DISCRIMINATOR[0],
+            nu_hash: [8; 32],
+            msg_hash: [9; 32],
+            data: &data,
+            accounts: &[],
+            fee_vault: None,
+        };
+
+        let args = VoteWithRelayer::deserialize(&mut &call.args()[..]).unwrap();
+        assert_eq!(args.relayer_nu_hash, [8; 32]);
+        assert_eq!(args.msg_hash, [9; 32]);
+        let mut relayer_id = [0xff; 32];
+        relayer_id[0] = 0x1f;
+        assert_eq!(args.relayer_id, relayer_id);
+        assert_eq!(args.eph_key, eph_key);
+        assert_eq!(args.nonce, 7);
+        assert_eq!(args.ciphertext, [[3; 32]; 7]);
+        assert_eq!(args.proof, proof);
+        assert!(msg_hash(&args.eph_key, args.nonce, &args.ciphertext).is_ok());
+    }
+
+    #[test]
+    fn test_is_token_account() {
+        let fee_token = FeeToken {
+            mint: Pubkey::new_unique(),
+            vault: Pubkey::new_unique(),
+            token_program: Pubkey::new_unique(),
+        };
+        let owner = Pubkey::new_unique();
+        let mut data = vec![0; TOKEN_ACCOUNT_LEN];
+        data[..32].copy_from_slice(&fee_token.mint.to_bytes());
+        data[32..64].copy_from_slice(&owner.to_bytes());
+        data[108] = 1;
+        let mut account = Account {
+            owner: fee_token.token_program,
+            data,
+            ..Account::default()
+        };
+        assert!(is_token_account(&account, &fee_token, &owner));
+        assert!(!is_token_account(&account, &fee_token, &fee_token.vault));
+
+        account.data.extend([1, 0]);
+        assert!(!is_token_account(&account, &fee_token, &owner));
+        account.data[TOKEN_ACCOUNT_LEN] = 2;
+        assert!(is_token_account(&account, &fee_token, &owner));
+    }
+}
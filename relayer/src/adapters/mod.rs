@@ -0,0 +1,114 @@
+use solana_sdk::{instruction::AccountMeta, pubkey::Pubkey};
+use solana_tools::solana_transactor::RpcPool;
+use std::{collections::HashMap, future::Future, pin::Pin};
+use thiserror::Error;
+
+pub mod anon_vote;
+
+/// The instruction `zk_relayer` invokes on the target program, as it will be
+/// assembled on chain.
+pub struct RelayCall<'a> {
+    pub state_id: u64,
+    pub relayer: Pubkey,
+    pub discriminator: u8,
+    pub nu_hash: [u8; 32],
+    pub msg_hash: [u8; 32],
+    pub data: &'a [u8],
+    /// Accounts after the relayer and its `RelayerState`.
+    pub accounts: &'a [AccountMeta],
+    pub fee_vault: Option<Pubkey>,
+}
+
+impl RelayCall<'_> {
+    /// Instruction data after the discriminator.
+    pub fn args(&self) -> Vec<u8> {
+        let mut args = Vec::with_capacity(3 * 32 + self.data.len());
+        args.extend(&self.nu_hash);
+        args.extend(&self.msg_hash);
+        args.extend(&relayer_id(&self.relayer));
+        args.extend(self.data);
+        args
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum AdapterError {
+    #[error("Instruction {0} can't be relayed")]
+    InstructionNotRelayable(u8),
+    #[error("Instruction data is malformed")]
+    MalformedData,
+    #[error("Message hash doesn't match the message")]
+    MsgHashMismatch,
+    #[error("Target state account is missing or malformed")]
+    StateUnavailable,
+    #[error("Target state doesn't accept messages at this time")]
+    BadTime,
+    #[error("Message proof is invalid")]
+    InvalidProof,
+    #[error("Expected {expected} accounts, got {got}")]
+    AccountCount { expected: usize, got: usize },
+    #[error("Account {index} should be {expected}, got {got}")]
+    AccountMismatch {
+        index: usize,
+        expected: Pubkey,
+        got: Pubkey,
+    },
+    #[error("Account {index} should have writable set to {writable}")]
+    AccountWritability { index: usize, writable: bool },
+    #[error("Account {0} isn't a token account of the fee token")]
+    FeeAccountInvalid(usize),
+    #[error("Fee vault doesn't match the target state")]
+    FeeVaultMismatch,
+}
+
+/// Checks relayed instructions of a target program and their accounts off
+/// chain, so that requests the program would reject are turned down before
+/// proving the relay.
+pub trait TargetAdapter: Send + Sync {
+    fn program(&self) -> Pubkey;
+
+    fn validate<'a>(
+        &'a self,
+        rpc_pool: &'a RpcPool,
+        call: &'a RelayCall<'a>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), AdapterError>> + 'a>>;
+}
+
+/// Adapters by target program. Instructions of allowed programs without one
+/// are relayed unchecked.
+pub type Adapters = HashMap<Pubkey, Box<dyn TargetAdapter>>;
+
+pub fn adapters() -> Adapters {
+    let adapters: [Box<dyn TargetAdapter>; 1] = [Box::new(anon_vote::AnonVoteAdapter)];
+    adapters.into_iter().map(|a| (a.program(), a)).collect()
+}
+
+/// Checks that the account at `index` is `expected` with the given
+/// writability.
+pub fn check_account(
+    accounts: &[AccountMeta],
+    index: usize,
+    expected: Pubkey,
+    writable: bool,
+) -> Result<(), AdapterError> {
+    let account = &accounts[index];
+    if account.pubkey != expected {
+        return Err(AdapterError::AccountMismatch {
+            index,
+            expected,
+            got: account.pubkey,
+        });
+    }
+    if account.is_writable != writable {
+        return Err(AdapterError::AccountWritability { index, writable });
+    }
+    Ok(())
+}
+
+/// The relayer's key with the top bits cleared to fit the field, as
+/// `zk_relayer` passes it.
+pub fn relayer_id(relayer: &Pubkey) -> [u8; 32] {
+    let mut relayer_id = relayer.to_bytes();
+    relayer_id[0] &= (1 << 5) - 1;
+    relayer_id
+}
@@ -1,17 +1,26 @@
 use config::{Config, File};
 use serde::Deserialize;
-use solana_sdk::signature::Keypair;
+use serde_with::{serde_as, DisplayFromStr};
+use solana_sdk::{pubkey::Pubkey, signature::Keypair};
 use solana_tools::solana_transactor::RpcEntry;
 use solana_tools::utils::deserialize_keypair;
 use std::path::PathBuf;
 use tracing::debug;
 
+#[serde_as]
 #[derive(Debug, Deserialize)]
 pub(crate) struct RelayerConfig {
     pub addrs: String,
     pub solana: SolanaConfig,
     pub ssl: SslConfig,
     pub rocksdb_path: String,
+    /// Mints the relayer takes fees in besides lamports.
+    #[serde_as(as = "Vec<DisplayFromStr>")]
+    #[serde(default)]
+    pub accepted_mints: Vec<Pubkey>,
+    /// Bearer token of the admin endpoints, which are disabled without it.
+    #[serde(default)]
+    pub admin_token: Option<String>,
 }
 
 impl RelayerConfig {
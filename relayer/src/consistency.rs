@@ -0,0 +1,176 @@
+use anchor_lang::AccountDeserialize;
+use dashmap::DashMap;
+use rocksdb::WriteBatch;
+use serde::Serialize;
+use serde_with::{hex::Hex, serde_as, DisplayFromStr};
+use solana_sdk::pubkey::Pubkey;
+use solana_tools::solana_transactor::RpcPool;
+use std::{cell::RefCell, sync::Arc};
+use thiserror::Error;
+use tokio::sync::Mutex;
+use tracing::{error, info};
+use zk_relayer::state::{AllowedPrograms, RelayerState};
+
+use crate::{
+    rocks::{StateKey, StateStore},
+    server::{fetch_account, find_relayer_state, ALLOWED_PROGRAMS},
+};
+
+/// States whose trees don't reproduce the on-chain root, with the reason.
+/// Relays for them are rejected, as their proofs would fail.
+pub type Quarantine = DashMap<StateKey, String>;
+
+/// Per state locks, held while a state's tree is read or updated.
+pub type StateLocks = DashMap<StateKey, Arc<Mutex<()>>>;
+
+#[derive(Debug, Error)]
+pub enum ConsistencyError {
+    #[error("Allowed programs account is missing or malformed")]
+    AllowedProgramsUnavailable,
+    #[error("RocksDB error: {0}")]
+    RocksDb(#[from] rocksdb::Error),
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StateStatus {
+    Consistent,
+    Diverged,
+    /// The `RelayerState` is gone or doesn't fund this relayer.
+    Missing,
+    /// A journalled relay may still land.
+    Pending,
+}
+
+#[serde_as]
+#[derive(Debug, Serialize)]
+pub struct StateCheck {
+    #[serde_as(as = "DisplayFromStr")]
+    pub target_program: Pubkey,
+    #[serde_as(as = "DisplayFromStr")]
+    pub state_id: u64,
+    pub status: StateStatus,
+    #[serde_as(as = "Hex")]
+    pub local_root: [u8; 32],
+    #[serde_as(as = "Option<Hex>")]
+    pub onchain_root: Option<[u8; 32]>,
+}
+
+/// Compares the trees of every state of an allowed program with the
+/// relayer's `root_state` on chain, quarantining the divergent states and
+/// releasing the ones back in sync. Each state is locked while checked, so
+/// a relay can't move one of the roots in between.
+pub async fn check_states(
+    store: &StateStore,
+    rpc_pool: &RpcPool,
+    relayer: &Pubkey,
+    locks: &StateLocks,
+    quarantine: &Quarantine,
+) -> Result<Vec<StateCheck>, ConsistencyError> {
+    let allowed_programs = fetch_account(rpc_pool, ALLOWED_PROGRAMS)
+        .await
+        .and_then(|account| AllowedPrograms::try_deserialize(&mut &account.data[..]).ok())
+        .ok_or(ConsistencyError::AllowedProgramsUnavailable)?;
+
+    let mut checks = Vec::new();
+    for key in store.state_keys()? {
+        if allowed_programs.get(&key.program).is_none() {
+            continue;
+        }
+        let lock = locks
+            .entry(key.clone())
+            .or_insert_with(|| Arc::new(Mutex::new(())))
+            .clone();
+        let _guard = lock.lock().await;
+
+        let local_root = {
+            let batch = RefCell::new(WriteBatch::new());
+            store.load_state(&key, &batch)?.root_state()?
+        };
+        let onchain_root = fetch_account(rpc_pool, find_relayer_state(key.program, key.state_id))
+            .await
+            .and_then(|account| RelayerState::try_deserialize(&mut &account.data[..]).ok())
+            .and_then(|state| state.quota(relayer).map(|q| q.root_state));
+
+        let journalled = store.journal_entry(&key)?.is_some();
+        let status = state_status(&key, local_root, onchain_root, journalled, quarantine);
+        checks.push(StateCheck {
+            target_program: key.program,
+            state_id: key.state_id,
+            status,
+            local_root,
+            onchain_root,
+        });
+    }
+    Ok(checks)
+}
+
+/// Compares the roots of a state, quarantining it if they differ and
+/// releasing it if they match.
+pub(crate) fn state_status(
+    key: &StateKey,
+    local_root: [u8; 32],
+    onchain_root: Option<[u8; 32]>,
+    journalled: bool,
+    quarantine: &Quarantine,
+) -> StateStatus {
+    if journalled {
+        return StateStatus::Pending;
+    }
+    match onchain_root {
+        None => StateStatus::Missing,
+        Some(root) if root == local_root => {
+            if quarantine.remove(key).is_some() {
+                info!(
+                    "relayer state {} of {} is back in sync",
+                    key.state_id, key.program
+                );
+            }
+            StateStatus::Consistent
+        }
+        Some(root) => {
+            let reason = format!(
+                "local root {} differs from on-chain root {}",
+                hex::encode(local_root),
+                hex::encode(root)
+            );
+            error!(
+                "quarantining relayer state {} of {}: {reason}",
+                key.state_id, key.program
+            );
+            quarantine.insert(key.clone(), reason);
+            StateStatus::Diverged
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_status() {
+        let key = StateKey {
+            program: Pubkey::new_unique(),
+            state_id: 0,
+        };
+        let quarantine = Quarantine::new();
+
+        let status = state_status(&key, [1; 32], Some([2; 32]), true, &quarantine);
+        assert_eq!(status, StateStatus::Pending);
+        let status = state_status(&key, [1; 32], None, false, &quarantine);
+        assert_eq!(status, StateStatus::Missing);
+        assert!(quarantine.is_empty());
+
+        let status = state_status(&key, [1; 32], Some([2; 32]), false, &quarantine);
+        assert_eq!(status, StateStatus::Diverged);
+        assert!(quarantine.contains_key(&key));
+        // a pending relay doesn't release it
+        state_status(&key, [2; 32], Some([2; 32]), true, &quarantine);
+        assert!(quarantine.contains_key(&key));
+
+        let status = state_status(&key, [2; 32], Some([2; 32]), false, &quarantine);
+        assert_eq!(status, StateStatus::Consistent);
+        assert!(quarantine.is_empty());
+    }
+}
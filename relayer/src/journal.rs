@@ -0,0 +1,346 @@
+use anchor_lang::{prelude::borsh, AccountDeserialize, AnchorDeserialize, AnchorSerialize};
+use core::{future::Future, time::Duration};
+use rocksdb::WriteBatch;
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
+use solana_tools::solana_transactor::RpcPool;
+use std::cell::RefCell;
+use thiserror::Error;
+use tracing::{info, warn};
+use zk_relayer::state::RelayerState;
+
+use crate::{
+    rocks::{LoadedState, StateKey, StateStore},
+    server::{fetch_account, find_relayer_state, one_be, u64_to_u256_be},
+    utils::now,
+};
+
+/// Seconds after which a transaction that didn't land no longer can, its
+/// blockhash having expired.
+const TX_EXPIRY_SECS: u64 = 150;
+
+/// A relay written down before its transaction is sent, so that the trees
+/// can be caught up with the chain if it lands but the relayer exits before
+/// writing them.
+#[derive(Clone, Debug, AnchorSerialize, AnchorDeserialize)]
+pub struct JournalEntry {
+    pub root_state_before: [u8; 32],
+    /// The pending root the relay sets the relayer's `root_state` to.
+    pub root_state_after: [u8; 32],
+    /// Roots of the local trees the relay applies to.
+    pub root_quota_before: [u8; 32],
+    pub root_uniq_before: [u8; 32],
+    pub msg_hash: [u8; 32],
+    /// Key of the quota tree, the nullifier cut to the tree depth.
+    pub nu_index: [u8; 32],
+    pub prev_count: u64,
+    /// Known once the transactor returns.
+    pub signature: Option<[u8; 64]>,
+    /// Unix time of the submission.
+    pub submitted_at: u64,
+}
+
+impl JournalEntry {
+    /// Updates the trees the way the relay circuit does.
+    pub fn apply(&self, state: &mut LoadedState) -> Result<(), smt_circom::Error<rocksdb::Error>> {
+        let new_count = u64_to_u256_be(self.prev_count + 1);
+        if self.prev_count == 0 {
+            state.quota_tree.add(self.nu_index, new_count)?;
+        } else {
+            state.quota_tree.update(self.nu_index, new_count)?;
+        }
+        state.uniq_tree.add(self.msg_hash, one_be())
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum JournalError {
+    #[error("Merkle tree error: {0}")]
+    Smt(#[from] smt_circom::Error<rocksdb::Error>),
+    #[error("RocksDB error: {0}")]
+    RocksDb(#[from] rocksdb::Error),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Landed,
+    Dropped,
+    /// The transaction may still land.
+    Pending,
+}
+
+/// Tells from the on-chain root and the signature status whether the relay
+/// took effect.
+pub async fn resolve(
+    rpc_pool: &RpcPool,
+    relayer: &Pubkey,
+    key: &StateKey,
+    entry: &JournalEntry,
+) -> Outcome {
+    let root_state = fetch_account(rpc_pool, find_relayer_state(key.program, key.state_id))
+        .await
+        .and_then(|account| RelayerState::try_deserialize(&mut &account.data[..]).ok())
+        .and_then(|state| state.quota(relayer).map(|q| q.root_state));
+
+    let mut tx_failed = false;
+    if root_state == Some(entry.root_state_before) {
+        if let Some(signature) = entry.signature.map(Signature::from) {
+            let status = rpc_pool
+                .with_read_rpc_loop(
+                    |rpc| async move { rpc.get_signature_statuses(&[signature]).await },
+                    CommitmentConfig::confirmed(),
+                )
+                .await
+                .value
+                .pop()
+                .flatten();
+            tx_failed = status.is_some_and(|status| status.err.is_some());
+        }
+    }
+    outcome(key, entry, root_state, tx_failed, now())
+}
+
+/// The outcome of the relay given the relayer's `root_state` on chain, if
+/// its quota is still there, and whether the transaction failed.
+fn outcome(
+    key: &StateKey,
+    entry: &JournalEntry,
+    root_state: Option<[u8; 32]>,
+    tx_failed: bool,
+    now: u64,
+) -> Outcome {
+    let Some(root_state) = root_state else {
+        // nothing left to catch up with
+        return Outcome::Dropped;
+    };
+
+    if root_state == entry.root_state_after {
+        return Outcome::Landed;
+    }
+    if root_state != entry.root_state_before {
+        warn!(
+            "relayer state {} of {} is at neither root of the journalled relay",
+            key.state_id, key.program
+        );
+        return Outcome::Dropped;
+    }
+
+    if tx_failed || now > entry.submitted_at + TX_EXPIRY_SECS {
+        Outcome::Dropped
+    } else {
+        Outcome::Pending
+    }
+}
+
+/// Applies a landed relay to the trees and removes the entry.
+pub fn settle(
+    store: &StateStore,
+    key: &StateKey,
+    entry: &JournalEntry,
+    outcome: Outcome,
+) -> Result<(), JournalError> {
+    let batch = RefCell::new(WriteBatch::new());
+    if outcome == Outcome::Landed {
+        let mut state = store.load_state(key, &batch)?;
+        if state.quota_tree.root()? == entry.root_quota_before
+            && state.uniq_tree.root()? == entry.root_uniq_before
+        {
+            entry.apply(&mut state)?;
+        } else {
+            warn!(
+                "trees of relayer state {} of {} moved past the journalled relay",
+                key.state_id, key.program
+            );
+        }
+    }
+    store.delete_journal(key, &batch);
+    store.db.write(batch.take())?;
+    Ok(())
+}
+
+/// Settles the relays journalled by a previous run, waiting for the ones
+/// that may still land.
+pub async fn reconcile(
+    store: &StateStore,
+    rpc_pool: &RpcPool,
+    relayer: &Pubkey,
+) -> Result<(), JournalError> {
+    reconcile_with(store, |key, entry| async move {
+        resolve(rpc_pool, relayer, &key, &entry).await
+    })
+    .await
+}
+
+async fn reconcile_with<F, Fut>(store: &StateStore, mut resolve: F) -> Result<(), JournalError>
+where
+    F: FnMut(StateKey, JournalEntry) -> Fut,
+    Fut: Future<Output = Outcome>,
+{
+    loop {
+        let mut pending = 0;
+        for (key, entry) in store.journal_entries()? {
+            let outcome = resolve(key.clone(), entry.clone()).await;
+            if outcome == Outcome::Pending {
+                pending += 1;
+                continue;
+            }
+            info!(
+                "journalled relay of message {} {outcome:?}",
+                hex::encode(entry.msg_hash)
+            );
+            settle(store, &key, &entry, outcome)?;
+        }
+        if pending == 0 {
+            return Ok(());
+        }
+        info!("waiting for {pending} journalled relays to land or expire");
+        tokio::time::sleep(Duration::from_secs(5)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Removed on drop, after the store opened in it if declared before.
+    struct TempDir(std::path::PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("journal-{name}-{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&path);
+            Self(path)
+        }
+
+        fn open(&self) -> StateStore {
+            StateStore::open(self.0.to_str().unwrap()).unwrap()
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn roots(store: &StateStore, key: &StateKey) -> ([u8; 32], [u8; 32]) {
+        let batch = RefCell::new(WriteBatch::new());
+        let state = store.load_state(key, &batch).unwrap();
+        (
+            state.quota_tree.root().unwrap(),
+            state.uniq_tree.root().unwrap(),
+        )
+    }
+
+    fn entry(store: &StateStore, key: &StateKey, msg: u64) -> JournalEntry {
+        let (root_quota_before, root_uniq_before) = roots(store, key);
+        JournalEntry {
+            root_state_before: [1; 32],
+            root_state_after: [2; 32],
+            root_quota_before,
+            root_uniq_before,
+            msg_hash: u64_to_u256_be(msg),
+            nu_index: u64_to_u256_be(7),
+            prev_count: 0,
+            signature: None,
+            submitted_at: 1000,
+        }
+    }
+
+    #[test]
+    fn test_outcome() {
+        let key = StateKey {
+            program: Pubkey::new_unique(),
+            state_id: 0,
+        };
+        let dir = TempDir::new("outcome");
+        let store = dir.open();
+        let entry = entry(&store, &key, 1);
+        let expiry = entry.submitted_at + TX_EXPIRY_SECS;
+
+        let outcome =
+            |root_state, tx_failed, now| outcome(&key, &entry, root_state, tx_failed, now);
+        assert_eq!(outcome(Some([2; 32]), false, expiry + 1), Outcome::Landed);
+        assert_eq!(outcome(Some([1; 32]), false, expiry), Outcome::Pending);
+        assert_eq!(outcome(Some([1; 32]), false, expiry + 1), Outcome::Dropped);
+        assert_eq!(outcome(Some([1; 32]), true, expiry), Outcome::Dropped);
+        assert_eq!(outcome(Some([3; 32]), false, expiry), Outcome::Dropped);
+        assert_eq!(outcome(None, false, expiry), Outcome::Dropped);
+    }
+
+    #[test]
+    fn test_settle() {
+        let key = StateKey {
+            program: Pubkey::new_unique(),
+            state_id: 0,
+        };
+        let dir = TempDir::new("settle");
+        let store = dir.open();
+        let empty = roots(&store, &key);
+
+        let dropped = entry(&store, &key, 1);
+        store.put_journal(&key, &dropped).unwrap();
+        settle(&store, &key, &dropped, Outcome::Dropped).unwrap();
+        assert!(store.journal_entry(&key).unwrap().is_none());
+        assert_eq!(roots(&store, &key), empty);
+
+        let landed = entry(&store, &key, 2);
+        store.put_journal(&key, &landed).unwrap();
+        settle(&store, &key, &landed, Outcome::Landed).unwrap();
+        assert!(store.journal_entry(&key).unwrap().is_none());
+        let applied = roots(&store, &key);
+        assert_ne!(applied.0, empty.0);
+        assert_ne!(applied.1, empty.1);
+
+        // settling again, as after a crash before the entry was deleted
+        store.put_journal(&key, &landed).unwrap();
+        settle(&store, &key, &landed, Outcome::Landed).unwrap();
+        assert!(store.journal_entry(&key).unwrap().is_none());
+        assert_eq!(roots(&store, &key), applied);
+    }
+
+    #[actix_web::test]
+    async fn test_reconcile() {
+        let program = Pubkey::new_unique();
+        let landed_key = StateKey {
+            program,
+            state_id: 0,
+        };
+        let dropped_key = StateKey {
+            program,
+            state_id: 1,
+        };
+        let dir = TempDir::new("reconcile");
+        let store = dir.open();
+        let empty = roots(&store, &landed_key);
+        store
+            .put_journal(&landed_key, &entry(&store, &landed_key, 1))
+            .unwrap();
+        store
+            .put_journal(&dropped_key, &entry(&store, &dropped_key, 1))
+            .unwrap();
+
+        reconcile_with(&store, |key, _| async move {
+            if key.state_id == 0 {
+                Outcome::Landed
+            } else {
+                Outcome::Dropped
+            }
+        })
+        .await
+        .unwrap();
+        assert!(store.journal_entries().unwrap().is_empty());
+        let applied = roots(&store, &landed_key);
+        assert_ne!(applied, empty);
+        assert_eq!(roots(&store, &dropped_key), empty);
+
+        let mut resolved = 0;
+        reconcile_with(&store, |_, _| {
+            resolved += 1;
+            async { Outcome::Dropped }
+        })
+        .await
+        .unwrap();
+        assert_eq!(resolved, 0);
+        assert_eq!(roots(&store, &landed_key), applied);
+    }
+}
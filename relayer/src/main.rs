@@ -2,11 +2,15 @@ use clap::Parser;
 use solana_sdk::signer::Signer;
 use solana_tools::solana_transactor::{RpcPool, SolanaTransactor};
 use std::{env, path::PathBuf};
-use tracing::{debug, error};
+use tracing::{debug, error, info};
 
 use crate::{config::RelayerConfig, server::Server};
 
+mod adapters;
 mod config;
+mod consistency;
+mod journal;
+mod preflight;
 mod prover;
 mod rocks;
 mod server;
@@ -31,10 +35,27 @@ async fn main() {
     let rpc_pool = RpcPool::new(&config.solana.read_rpcs, &config.solana.write_rpcs)
         .expect("RPC pool failed to initialize");
 
-    let transactor = SolanaTransactor::start(rpc_pool)
+    let transactor = SolanaTransactor::start(rpc_pool.clone())
         .await
         .expect("Failed to start solana transactor");
-    let server = Server::new(config.solana.keypair, transactor, &config.rocksdb_path);
+    let server = Server::new(
+        config.solana.keypair,
+        rpc_pool,
+        transactor,
+        &config.rocksdb_path,
+        config.accepted_mints,
+        config.admin_token,
+    );
+
+    server
+        .reconcile()
+        .await
+        .expect("Failed to reconcile the relay journal");
+    let checks = server
+        .check_states()
+        .await
+        .expect("Failed to check the relayer states");
+    info!("Checked {} relayer states", checks.len());
 
     let res = server
         .execute(
@@ -0,0 +1,320 @@
+use anchor_lang::error::ERROR_CODE_OFFSET;
+use anon_vote::error::AnonVoteError;
+use serde::Serialize;
+use serde_with::{serde_as, DisplayFromStr};
+use solana_client::rpc_config::RpcSimulateTransactionConfig;
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    compute_budget::ComputeBudgetInstruction,
+    instruction::{Instruction, InstructionError},
+    message::Message,
+    pubkey::Pubkey,
+    transaction::{Transaction, TransactionError},
+};
+use solana_tools::solana_transactor::RpcPool;
+use std::fmt;
+use zk_relayer::error::ZkRelayerError;
+
+/// Why a simulated transaction failed, decoded from the error code of the
+/// failing program, or from the Anchor error it logged if it is neither
+/// `anon_vote` nor `zk_relayer`.
+#[serde_as]
+#[derive(Debug, Serialize)]
+pub struct SimulationError {
+    /// Program the error comes from.
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub program: Option<Pubkey>,
+    /// `anon_vote` or `zk_relayer`, none for other programs.
+    pub program_name: Option<&'static str>,
+    /// Anchor error number, such as 6000 for the first program error.
+    pub code: Option<u32>,
+    /// Name of the error variant, such as `VotingEnded`.
+    pub name: Option<String>,
+    pub message: String,
+    pub logs: Vec<String>,
+}
+
+impl fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.program_name, &self.name) {
+            (Some(program), Some(name)) => write!(f, "{program} {name}: {}", self.message),
+            _ => f.write_str(&self.message),
+        }
+    }
+}
+
+/// Simulates the instruction in a transaction paid by `payer`, without
+/// checking signatures.
+pub async fn simulate(
+    rpc_pool: &RpcPool,
+    payer: Pubkey,
+    instruction: &Instruction,
+    cu_limit: u32,
+) -> Result<(), SimulationError> {
+    let message = Message::new(
+        &[
+            ComputeBudgetInstruction::set_compute_unit_limit(cu_limit),
+            instruction.clone(),
+        ],
+        Some(&payer),
+    );
+    let tx = Transaction::new_unsigned(message);
+    let result = rpc_pool
+        .with_read_rpc_loop(
+            |rpc| {
+                let tx = tx.clone();
+                async move {
+                    rpc.simulate_transaction_with_config(
+                        &tx,
+                        RpcSimulateTransactionConfig {
+                            sig_verify: false,
+                            replace_recent_blockhash: true,
+                            commitment: Some(CommitmentConfig::confirmed()),
+                            ..Default::default()
+                        },
+                    )
+                    .await
+                }
+            },
+            CommitmentConfig::confirmed(),
+        )
+        .await
+        .value;
+    match result.err {
+        None => Ok(()),
+        Some(err) => Err(decode(err, result.logs.unwrap_or_default())),
+    }
+}
+
+fn decode(err: TransactionError, logs: Vec<String>) -> SimulationError {
+    let failed = failed_program(&logs);
+    let code = match err {
+        TransactionError::InstructionError(_, InstructionError::Custom(code)) => Some(code),
+        _ => None,
+    };
+    if let Some((program, code)) = failed.zip(code) {
+        if let Some((name, message)) = program_error(program, code) {
+            return SimulationError {
+                program: Some(program),
+                program_name: program_name(program),
+                code: Some(code),
+                name: Some(name),
+                message,
+                logs,
+            };
+        }
+    }
+    // errors of other programs, as far as they're logged
+    if let Some((program, name, code, message)) = logged_anchor_error(&logs) {
+        return SimulationError {
+            program,
+            program_name: program.and_then(program_name),
+            code: Some(code),
+            name: Some(name),
+            message,
+            logs,
+        };
+    }
+    SimulationError {
+        program: failed,
+        program_name: failed.and_then(program_name),
+        code,
+        name: None,
+        message: err.to_string(),
+        logs,
+    }
+}
+
+/// Name and message of a program error of `anon_vote` or `zk_relayer`.
+fn program_error(program: Pubkey, code: u32) -> Option<(String, String)> {
+    let index = code.checked_sub(ERROR_CODE_OFFSET)?;
+    if program == anon_vote::ID {
+        AnonVoteError::try_from(index)
+            .ok()
+            .map(|err| (err.name(), err.to_string()))
+    } else if program == zk_relayer::ID {
+        ZkRelayerError::try_from(index)
+            .ok()
+            .map(|err| (err.name(), err.to_string()))
+    } else {
+        None
+    }
+}
+
+/// The innermost program that failed, the first one logged as failing.
+fn failed_program(logs: &[String]) -> Option<Pubkey> {
+    logs.iter().find_map(|log| {
+        let mut words = log.strip_prefix("Program ")?.split(' ');
+        let program = words.next()?.parse().ok()?;
+        (words.next()? == "failed:").then_some(program)
+    })
+}
+
+/// The Anchor error logged first, with the program that was running.
+fn logged_anchor_error(logs: &[String]) -> Option<(Option<Pubkey>, String, u32, String)> {
+    let mut invoked = Vec::new();
+    for log in logs {
+        if let Some(rest) = log.strip_prefix("Program log: AnchorError ") {
+            if let Some((name, code, message)) = parse_anchor_error(rest) {
+                return Some((invoked.last().copied(), name, code, message));
+            }
+        }
+        let Some(rest) = log.strip_prefix("Program ") else {
+            continue;
+        };
+        let mut words = rest.split(' ');
+        let (Some(program), Some(event)) = (
+            words.next().and_then(|p| p.parse::<Pubkey>().ok()),
+            words.next(),
+        ) else {
+            continue;
+        };
+        match event {
+            "invoke" => invoked.push(program),
+            "success" => {
+                invoked.pop();
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Parses `... Error Code: {name}. Error Number: {code}. Error Message:
+/// {message}.`
+fn parse_anchor_error(log: &str) -> Option<(String, u32, String)> {
+    let (_, rest) = log.split_once("Error Code: ")?;
+    let (name, rest) = rest.split_once(". Error Number: ")?;
+    let (code, rest) = rest.split_once(". Error Message: ")?;
+    let message = rest.strip_suffix('.').unwrap_or(rest);
+    Some((name.to_owned(), code.parse().ok()?, message.to_owned()))
+}
+
+fn program_name(program: Pubkey) -> Option<&'static str> {
+    if program == anon_vote::ID {
+        Some("anon_vote")
+    } else if program == zk_relayer::ID {
+        Some("zk_relayer")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_anchor_error() {
+        let logs = [
+            format!("Program {} invoke [1]", zk_relayer::ID),
+            "Program log: Instruction: Relay".to_owned(),
+            format!("Program {} invoke [2]", anon_vote::ID),
+            "Program log: AnchorError thrown in programs/anon-vote/src/instructions/vote.rs:80. \
+             Error Code: BadTime. Error Number: 6003. Error Message: Operation not allowed \
+             during that time."
+                .to_owned(),
+            format!(
+                "Program {} failed: custom program error: 0x1773",
+                anon_vote::ID
+            ),
+            format!(
+                "Program {} failed: custom program error: 0x1773",
+                zk_relayer::ID
+            ),
+        ];
+        let err = decode(
+            TransactionError::InstructionError(1, InstructionError::Custom(6003)),
+            logs.to_vec(),
+        );
+        assert_eq!(err.program, Some(anon_vote::ID));
+        assert_eq!(err.program_name, Some("anon_vote"));
+        assert_eq!(err.code, Some(6003));
+        assert_eq!(err.name.as_deref(), Some("BadTime"));
+        assert_eq!(err.message, "Operation not allowed during that time");
+    }
+
+    #[test]
+    fn test_error_codes() {
+        for code in ERROR_CODE_OFFSET..ERROR_CODE_OFFSET + 18 {
+            let err = AnonVoteError::try_from(code - ERROR_CODE_OFFSET).unwrap();
+            assert_eq!(u32::from(err), code);
+        }
+        for code in ERROR_CODE_OFFSET..ERROR_CODE_OFFSET + 15 {
+            let err = ZkRelayerError::try_from(code - ERROR_CODE_OFFSET).unwrap();
+            assert_eq!(u32::from(err), code);
+        }
+        assert_eq!(
+            program_error(anon_vote::ID, 6013),
+            Some((
+                "InvalidFeeAccount".to_owned(),
+                "Fee account doesn't match the poll's fee token or destination".to_owned()
+            ))
+        );
+        assert_eq!(
+            program_error(zk_relayer::ID, 6011),
+            Some((
+                "RelayerUnbonding".to_owned(),
+                "Relayer is unbonding".to_owned()
+            ))
+        );
+        assert_eq!(program_error(anon_vote::ID, 6018), None);
+        assert_eq!(program_error(zk_relayer::ID, 6015), None);
+        assert_eq!(program_error(zk_relayer::ID, 5999), None);
+        assert_eq!(program_error(Pubkey::new_unique(), 6000), None);
+    }
+
+    #[test]
+    fn test_decode_without_anchor_error() {
+        let logs = [
+            format!("Program {} invoke [1]", zk_relayer::ID),
+            format!(
+                "Program {} failed: custom program error: 0x1770",
+                zk_relayer::ID
+            ),
+        ];
+        let err = decode(
+            TransactionError::InstructionError(1, InstructionError::Custom(6000)),
+            logs.to_vec(),
+        );
+        assert_eq!(err.program_name, Some("zk_relayer"));
+        assert_eq!(err.code, Some(6000));
+        assert_eq!(err.name.as_deref(), Some("InvalidProof"));
+        assert_eq!(err.message, "Proof verification failed");
+    }
+
+    #[test]
+    fn test_decode_other_program() {
+        let program = Pubkey::new_unique();
+        let logs = [
+            format!("Program {} invoke [1]", zk_relayer::ID),
+            format!("Program {program} invoke [2]"),
+            "Program log: AnchorError occurred. Error Code: AccountNotInitialized. \
+             Error Number: 3012. Error Message: The program expected this account to be \
+             already initialized."
+                .to_owned(),
+            format!("Program {program} failed: custom program error: 0xbc4"),
+            format!(
+                "Program {} failed: custom program error: 0xbc4",
+                zk_relayer::ID
+            ),
+        ];
+        let err = decode(
+            TransactionError::InstructionError(1, InstructionError::Custom(3012)),
+            logs.to_vec(),
+        );
+        assert_eq!(err.program, Some(program));
+        assert_eq!(err.program_name, None);
+        assert_eq!(err.code, Some(3012));
+        assert_eq!(err.name.as_deref(), Some("AccountNotInitialized"));
+
+        // nothing to go by without logs
+        let err = decode(
+            TransactionError::InstructionError(1, InstructionError::Custom(6003)),
+            Vec::new(),
+        );
+        assert_eq!(err.program, None);
+        assert_eq!(err.code, Some(6003));
+        assert_eq!(err.name, None);
+    }
+}
@@ -1,3 +1,4 @@
+use ark_bn254::Fr;
 use ark_serialize::CanonicalSerialize;
 use circom_prover::{
     prover::{circom::Proof, CircomProof, ProofLib, PublicInputs},
@@ -5,6 +6,7 @@ use circom_prover::{
     CircomProver,
 };
 use core::ops::Neg;
+use light_poseidon::{Poseidon, PoseidonBytesHasher};
 use serde::Serialize;
 use zk_relayer::state::CompressedProof;
 
@@ -109,6 +111,15 @@ impl From<&PublicInputs> for RelayPublicInputs {
     }
 }
 
+/// `NuHash` output of the relay circuit, `None` if `nu` isn't a field
+/// element.
+pub fn nu_hash(nu: &[u8; 32], msg_hash: &[u8; 32]) -> Option<[u8; 32]> {
+    Poseidon::<Fr>::new_circom(2)
+        .ok()?
+        .hash_bytes_be(&[nu, msg_hash])
+        .ok()
+}
+
 pub fn prove_relay(inputs: &RelayInputs) -> anyhow::Result<CircomProof> {
     prove(
         "build/Relay/groth16_pkey.zkey".into(),
@@ -190,6 +201,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_nu_hash() {
+        // inputs and output of `test_prover`
+        let nu = [
+            30, 255, 43, 163, 141, 32, 191, 2, 16, 205, 0, 68, 82, 181, 155, 219, 55, 13, 165, 208,
+            243, 143, 154, 168, 47, 2, 143, 105, 145, 196, 169, 89,
+        ];
+        let msg_hash = [
+            4, 80, 77, 215, 150, 85, 113, 116, 109, 1, 146, 140, 212, 148, 25, 5, 78, 209, 190, 89,
+            17, 227, 80, 179, 67, 212, 230, 160, 143, 72, 164, 250,
+        ];
+        assert_eq!(
+            nu_hash(&nu, &msg_hash),
+            Some([
+                14, 240, 184, 171, 88, 223, 205, 137, 21, 5, 101, 14, 209, 170, 18, 75, 164, 33,
+                159, 98, 87, 235, 210, 238, 248, 179, 29, 62, 208, 21, 126, 205
+            ])
+        );
+    }
+
     #[test]
     fn test_prover() {
         // Got inputs this way:
@@ -1,9 +1,12 @@
-use rocksdb::{ColumnFamilyDescriptor, Options, WriteBatch, DB};
+use anchor_lang::{prelude::borsh, AnchorDeserialize};
+use ark_bn254::Fr;
+use light_poseidon::{Poseidon, PoseidonBytesHasher};
+use rocksdb::{ColumnFamilyDescriptor, IteratorMode, Options, WriteBatch, DB};
 use smt_circom::{store::RocksStore, SparseMerkleTree};
 use solana_sdk::pubkey::Pubkey;
 use std::cell::RefCell;
 
-use crate::prover::STATE_DEPTH;
+use crate::{journal::JournalEntry, prover::STATE_DEPTH};
 
 pub struct StateStore {
     pub db: DB,
@@ -22,6 +25,14 @@ impl StateKey {
         res[32..].copy_from_slice(&self.state_id.to_be_bytes());
         res
     }
+
+    fn deserialize(bytes: &[u8]) -> Option<Self> {
+        let bytes: &[u8; 40] = bytes.try_into().ok()?;
+        Some(Self {
+            program: Pubkey::try_from(&bytes[..32]).ok()?,
+            state_id: u64::from_be_bytes(bytes[32..].try_into().ok()?),
+        })
+    }
 }
 
 pub struct LoadedState<'a> {
@@ -29,6 +40,17 @@ pub struct LoadedState<'a> {
     pub uniq_tree: SparseMerkleTree<STATE_DEPTH, RocksStore<'a, [u8; 40]>>,
 }
 
+impl LoadedState<'_> {
+    /// `Poseidon(quota_root, uniq_root)`, the relayer's `root_state` in the
+    /// `RelayerState` the trees are in sync with.
+    pub fn root_state(&self) -> Result<[u8; 32], rocksdb::Error> {
+        Ok(Poseidon::<Fr>::new_circom(2)
+            .unwrap()
+            .hash_bytes_be(&[&self.quota_tree.root()?, &self.uniq_tree.root()?])
+            .expect("roots are field elements"))
+    }
+}
+
 impl StateStore {
     pub fn open(path: &str) -> Result<Self, rocksdb::Error> {
         let mut opts = Options::default();
@@ -37,9 +59,45 @@ impl StateStore {
         let cfs = [
             ColumnFamilyDescriptor::new("nodes:quota", Options::default()),
             ColumnFamilyDescriptor::new("nodes:uniq", Options::default()),
+            ColumnFamilyDescriptor::new("journal", Options::default()),
+            ColumnFamilyDescriptor::new("states", Options::default()),
         ];
         let db = DB::open_cf_descriptors(&opts, path, cfs)?;
-        Ok(Self { db })
+        let store = Self { db };
+        store.register_existing_states()?;
+        Ok(store)
+    }
+
+    /// Lists states with trees written before they were registered, going by
+    /// the quota tree roots, which are kept under the zero key.
+    fn register_existing_states(&self) -> Result<(), rocksdb::Error> {
+        let cf_states = self.db.cf_handle("states").unwrap();
+        if self
+            .db
+            .iterator_cf(cf_states, IteratorMode::Start)
+            .next()
+            .is_some()
+        {
+            return Ok(());
+        }
+        let cf_quota = self.db.cf_handle("nodes:quota").unwrap();
+        let mut batch = WriteBatch::new();
+        for item in self.db.iterator_cf(cf_quota, IteratorMode::Start) {
+            let (k, _) = item?;
+            if k.len() == 40 + 32 && k[40..] == [0; 32] {
+                batch.put_cf(cf_states, &k[..40], []);
+            }
+        }
+        self.db.write(batch)
+    }
+
+    /// States the relayer has trees for.
+    pub fn state_keys(&self) -> Result<Vec<StateKey>, rocksdb::Error> {
+        let cf = self.db.cf_handle("states").unwrap();
+        self.db
+            .iterator_cf(cf, IteratorMode::Start)
+            .map(|item| Ok(StateKey::deserialize(&item?.0).expect("state keys are 40 bytes")))
+            .collect()
     }
 
     pub fn load_state<'a>(
@@ -49,8 +107,11 @@ impl StateStore {
     ) -> Result<LoadedState<'a>, rocksdb::Error> {
         let cf_quota = self.db.cf_handle("nodes:quota").unwrap();
         let cf_uniq = self.db.cf_handle("nodes:uniq").unwrap();
+        let cf_states = self.db.cf_handle("states").unwrap();
 
         let key = key.serialize();
+        // written along with the trees, if they are
+        batch.borrow_mut().put_cf(cf_states, key, []);
         let q_store = RocksStore::new(&self.db, cf_quota, batch, key)?;
         let quota_tree = SparseMerkleTree::<STATE_DEPTH, _>::new(q_store)?;
 
@@ -62,4 +123,40 @@ impl StateStore {
             uniq_tree,
         })
     }
+
+    pub fn journal_entry(&self, key: &StateKey) -> Result<Option<JournalEntry>, rocksdb::Error> {
+        let cf = self.db.cf_handle("journal").unwrap();
+        Ok(self
+            .db
+            .get_cf(cf, key.serialize())?
+            .map(|v| JournalEntry::try_from_slice(&v).expect("journal entries are borsh")))
+    }
+
+    pub fn journal_entries(&self) -> Result<Vec<(StateKey, JournalEntry)>, rocksdb::Error> {
+        let cf = self.db.cf_handle("journal").unwrap();
+        self.db
+            .iterator_cf(cf, IteratorMode::Start)
+            .map(|item| {
+                let (k, v) = item?;
+                Ok((
+                    StateKey::deserialize(&k).expect("journal keys are state keys"),
+                    JournalEntry::try_from_slice(&v).expect("journal entries are borsh"),
+                ))
+            })
+            .collect()
+    }
+
+    /// Written on its own, before the relay transaction is sent.
+    pub fn put_journal(&self, key: &StateKey, entry: &JournalEntry) -> Result<(), rocksdb::Error> {
+        let cf = self.db.cf_handle("journal").unwrap();
+        self.db
+            .put_cf(cf, key.serialize(), borsh::to_vec(entry).unwrap())
+    }
+
+    /// Added to the batch updating the trees, so that the entry is gone
+    /// exactly when they're caught up.
+    pub fn delete_journal(&self, key: &StateKey, batch: &RefCell<WriteBatch>) {
+        let cf = self.db.cf_handle("journal").unwrap();
+        batch.borrow_mut().delete_cf(cf, key.serialize());
+    }
 }
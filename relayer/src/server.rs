@@ -1,7 +1,8 @@
 use actix_cors::Cors;
-use actix_web::{post, web, App, HttpResponse, HttpServer};
-use anchor_lang::{system_program, InstructionData, ToAccountMetas};
-use core::mem::transmute;
+use actix_web::{
+    http::header::AUTHORIZATION, post, web, App, HttpRequest, HttpResponse, HttpServer,
+};
+use anchor_lang::{system_program, AccountDeserialize, InstructionData, ToAccountMetas};
 use dashmap::DashMap;
 use openssl::ssl::{SslAcceptor, SslFiletype, SslMethod};
 use rocksdb::WriteBatch;
@@ -12,6 +13,8 @@ use smt_circom::{
     CircomProof,
 };
 use solana_sdk::{
+    account::Account,
+    commitment_config::CommitmentConfig,
     instruction::{AccountMeta, Instruction},
     pubkey,
     pubkey::Pubkey,
@@ -20,17 +23,26 @@ use solana_sdk::{
     transaction::TransactionError,
 };
 use solana_tools::solana_transactor::{
-    ix_compiler::InstructionBundle, SolanaTransactor, TransactorError,
+    ix_compiler::InstructionBundle, RpcPool, SolanaTransactor, TransactorError,
 };
 use std::{cell::RefCell, sync::Arc};
 use thiserror::Error;
 use tokio::sync::Mutex;
 use tracing::{debug, warn};
+use zk_relayer::{
+    state::{AllowedPrograms, RelayerState},
+    token::{is_token_account_data, TOKEN_PROGRAMS},
+};
 
 use crate::{
+    adapters::{self, AdapterError, Adapters, RelayCall},
     config::SslConfig,
-    prover::{compress_proof, prove_relay, RelayInputs, RelayPublicInputs, STATE_DEPTH},
+    consistency::{self, ConsistencyError, Quarantine, StateCheck, StateLocks},
+    journal::{self, JournalEntry, JournalError, Outcome},
+    preflight::{self, SimulationError},
+    prover::{compress_proof, nu_hash, prove_relay, RelayInputs, RelayPublicInputs, STATE_DEPTH},
     rocks::{StateKey, StateStore},
+    utils::now,
 };
 
 // TODO delete nodes on tally finish
@@ -38,15 +50,22 @@ use crate::{
 const MSG_LIMIT: u64 = 3; // const for now
 const RELAY_CU: u32 = 200_000;
 
-/// Relayer config PDA. Address derivation is tested below.
-const RELAYER_CONFIG: Pubkey = pubkey!("HdeFrMkEy82nL3F9udVPtu4uPshdU3EaAKNgSYH7yS7n");
+/// Allowed programs PDA. Address derivation is tested below.
+pub(crate) const ALLOWED_PROGRAMS: Pubkey = pubkey!("8W7iNLVZHkuAKEvHv7SXw6GFypBDoC7peajYvQRmbEaD");
+
+const ASSOCIATED_TOKEN_PROGRAM: Pubkey = pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
 
 #[derive(Clone)]
 struct AppState {
     relayer: Arc<Keypair>,
+    rpc_pool: RpcPool,
     transactor: SolanaTransactor,
     store: Arc<StateStore>,
-    locks: Arc<DashMap<StateKey, Arc<Mutex<()>>>>,
+    locks: Arc<StateLocks>,
+    accepted_mints: Arc<Vec<Pubkey>>,
+    adapters: Arc<Adapters>,
+    quarantine: Arc<Quarantine>,
+    admin_token: Option<Arc<str>>,
 }
 
 pub struct Server {
@@ -54,17 +73,49 @@ pub struct Server {
 }
 
 impl Server {
-    pub fn new(relayer: Keypair, transactor: SolanaTransactor, rocksdb_path: &str) -> Self {
+    pub fn new(
+        relayer: Keypair,
+        rpc_pool: RpcPool,
+        transactor: SolanaTransactor,
+        rocksdb_path: &str,
+        accepted_mints: Vec<Pubkey>,
+        admin_token: Option<String>,
+    ) -> Self {
         Self {
             app_state: AppState {
                 relayer: Arc::new(relayer),
+                rpc_pool,
                 transactor,
                 store: Arc::new(StateStore::open(rocksdb_path).unwrap()),
                 locks: Arc::new(DashMap::new()),
+                accepted_mints: Arc::new(accepted_mints),
+                adapters: Arc::new(adapters::adapters()),
+                quarantine: Arc::new(Quarantine::new()),
+                admin_token: admin_token.map(Into::into),
             },
         }
     }
 
+    /// Catches the trees up with relays of a previous run that may have
+    /// landed.
+    pub async fn reconcile(&self) -> Result<(), JournalError> {
+        let app = &self.app_state;
+        journal::reconcile(&app.store, &app.rpc_pool, &app.relayer.pubkey()).await
+    }
+
+    /// Quarantines the states whose trees diverged from the chain.
+    pub async fn check_states(&self) -> Result<Vec<StateCheck>, ConsistencyError> {
+        let app = &self.app_state;
+        consistency::check_states(
+            &app.store,
+            &app.rpc_pool,
+            &app.relayer.pubkey(),
+            &app.locks,
+            &app.quarantine,
+        )
+        .await
+    }
+
     pub async fn execute(
         self,
         addrs: &str,
@@ -83,6 +134,7 @@ impl Server {
                 .wrap(Cors::permissive())
                 .wrap(actix_web::middleware::Compress::default())
                 .service(relay)
+                .service(check_states)
         })
         .bind_openssl(addrs, ssl_builder)?
         .workers(workers)
@@ -92,8 +144,6 @@ impl Server {
 }
 
 #[serde_as]
-#[allow(dead_code)]
-#[repr(C)]
 #[derive(Debug, Deserialize)]
 pub struct AccountMetaFromStr {
     #[serde_as(as = "DisplayFromStr")]
@@ -102,6 +152,16 @@ pub struct AccountMetaFromStr {
     pub is_writable: bool,
 }
 
+impl From<&AccountMetaFromStr> for AccountMeta {
+    fn from(meta: &AccountMetaFromStr) -> Self {
+        AccountMeta {
+            pubkey: meta.pubkey,
+            is_signer: meta.is_signer,
+            is_writable: meta.is_writable,
+        }
+    }
+}
+
 #[serde_as]
 #[derive(Debug, Deserialize)]
 struct RelayRequest {
@@ -118,6 +178,12 @@ struct RelayRequest {
     state_id: u64,
     cu_limit: Option<u32>,
     accounts: Vec<AccountMetaFromStr>,
+    /// Token account the target program pays the fee from, if it pays in an
+    /// SPL token. The relayer's associated token account of the mint has to
+    /// be among `accounts`.
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    fee_vault: Option<Pubkey>,
 }
 
 #[post("/relay")]
@@ -131,6 +197,10 @@ async fn relay(
             debug!("executed message {}", hex::encode(msg_hash));
             Ok(HttpResponse::Ok().json(resp))
         }
+        Err(RelayError::Simulation(err)) => {
+            debug!("relay simulation failed: {}", err);
+            Ok(HttpResponse::UnprocessableEntity().json(err))
+        }
         Err(err) => {
             warn!("relay error: {}", err);
             Ok(HttpResponse::BadRequest().body(err.to_string()))
@@ -138,6 +208,42 @@ async fn relay(
     }
 }
 
+/// Runs the consistency check of the states on demand.
+#[post("/admin/check-states")]
+async fn check_states(app: web::Data<AppState>, req: HttpRequest) -> HttpResponse {
+    if !authorized(app.admin_token.as_deref(), &req) {
+        return HttpResponse::Unauthorized().finish();
+    }
+    match consistency::check_states(
+        &app.store,
+        &app.rpc_pool,
+        &app.relayer.pubkey(),
+        &app.locks,
+        &app.quarantine,
+    )
+    .await
+    {
+        Ok(checks) => HttpResponse::Ok().json(checks),
+        Err(err) => {
+            warn!("state check error: {}", err);
+            HttpResponse::InternalServerError().body(err.to_string())
+        }
+    }
+}
+
+/// Whether the request carries the admin token, never if there is none.
+fn authorized(admin_token: Option<&str>, req: &HttpRequest) -> bool {
+    admin_token.is_some_and(|token| {
+        req.headers()
+            .get(AUTHORIZATION)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.strip_prefix("Bearer "))
+            .is_some_and(|t| {
+                t.len() == token.len() && openssl::memcmp::eq(t.as_bytes(), token.as_bytes())
+            })
+    })
+}
+
 #[derive(Debug, Serialize)]
 struct RelayResponse {
     signature: String,
@@ -149,6 +255,32 @@ enum RelayError {
     MessageDuplicated,
     #[error("Relayer message limit exceeded")]
     MessageLimitExceeded,
+    #[error("A previous relay for this state may still land, try again later")]
+    RelayPending,
+    #[error("Relayer state is quarantined: {0}")]
+    StateQuarantined(String),
+    #[error("Target program is not allowed")]
+    ProgramNotAllowed,
+    #[error("Account {0} can't be relayed as a signer")]
+    SignerAccount(Pubkey),
+    #[error("Nullifier is not a field element")]
+    InvalidNu,
+    #[error("Rejected by the target program: {0}")]
+    Rejected(#[from] AdapterError),
+    #[error("Allowed programs account is missing or malformed")]
+    AllowedProgramsUnavailable,
+    #[error("Relayer state is missing, malformed or doesn't fund this relayer")]
+    RelayerStateUnavailable,
+    #[error("Fee vault is missing or not a token account")]
+    FeeVaultInvalid,
+    #[error("Fee mint {0} is not accepted")]
+    MintNotAccepted(Pubkey),
+    #[error("Fee vault holds {balance}, less than the fee of {fee}")]
+    FeeVaultInsufficient { balance: u64, fee: u64 },
+    #[error("Accounts don't include the relayer token account {0}")]
+    RelayerTokensMissing(Pubkey),
+    #[error("Simulation failed: {0}")]
+    Simulation(SimulationError),
     #[error("Solana transaction error: {0}")]
     TransactionError(#[from] TransactionError),
     #[error("Solana transactor error: {0}")]
@@ -157,15 +289,52 @@ enum RelayError {
     Smt(#[from] smt_circom::Error<<RocksStore<'static, [u8; 0]> as NodeStore>::Error>),
     #[error("RocksDB error: {0}")]
     RocksDb(#[from] rocksdb::Error),
+    #[error("Journal error: {0}")]
+    Journal(#[from] JournalError),
     #[error("Prover error: {0}")]
     Prover(#[from] anyhow::Error),
 }
 
 async fn relay_inner(app: &AppState, req: RelayRequest) -> Result<RelayResponse, RelayError> {
+    let allowed_programs = fetch_allowed_programs(&app.rpc_pool).await?;
+    if allowed_programs.get(&req.target_program).is_none() {
+        return Err(RelayError::ProgramNotAllowed);
+    }
+
+    // the relayer signs the transaction, its signature mustn't reach other
+    // accounts' instructions
+    if let Some(signer) = req.accounts.iter().find(|a| a.is_signer) {
+        return Err(RelayError::SignerAccount(signer.pubkey));
+    }
+    let req_accounts: Vec<AccountMeta> = req.accounts.iter().map(Into::into).collect();
+
+    // a relay proof is costly, rule out instructions the target would reject
+    if let Some(adapter) = app.adapters.get(&req.target_program) {
+        let call = RelayCall {
+            state_id: req.state_id,
+            relayer: app.relayer.pubkey(),
+            discriminator: req.discriminator,
+            nu_hash: nu_hash(&req.nu, &req.msg_hash).ok_or(RelayError::InvalidNu)?,
+            msg_hash: req.msg_hash,
+            data: &req.data,
+            accounts: &req_accounts,
+            fee_vault: req.fee_vault,
+        };
+        adapter.validate(&app.rpc_pool, &call).await?;
+    }
+
+    let relayer_tokens = match req.fee_vault {
+        Some(fee_vault) => Some(check_fee_vault(app, &req, fee_vault).await?),
+        None => None,
+    };
+
     let skey = StateKey {
         program: req.target_program,
         state_id: req.state_id,
     };
+    if let Some(reason) = quarantine_reason(&app.quarantine, &skey) {
+        return Err(RelayError::StateQuarantined(reason));
+    }
     let lock = app
         .locks
         .entry(skey.clone())
@@ -173,6 +342,14 @@ async fn relay_inner(app: &AppState, req: RelayRequest) -> Result<RelayResponse,
         .clone();
     let _guard = lock.lock().await;
 
+    if let Some(entry) = app.store.journal_entry(&skey)? {
+        let outcome = journal::resolve(&app.rpc_pool, &app.relayer.pubkey(), &skey, &entry).await;
+        if outcome == Outcome::Pending {
+            return Err(RelayError::RelayPending);
+        }
+        journal::settle(&app.store, &skey, &entry, outcome)?;
+    }
+
     let batch = RefCell::new(WriteBatch::new());
     let mut state = app.store.load_state(&skey, &batch)?;
 
@@ -237,15 +414,13 @@ async fn relay_inner(app: &AppState, req: RelayRequest) -> Result<RelayResponse,
 
     let mut accounts = zk_relayer::accounts::Relay {
         relayer: app.relayer.pubkey(),
-        relayer_config: RELAYER_CONFIG,
         relayer_state: find_relayer_state(req.target_program, req.state_id),
+        allowed_programs: ALLOWED_PROGRAMS,
         target_program: req.target_program,
         system_program: system_program::ID,
+        relayer_tokens,
     }
     .to_account_metas(None);
-
-    let req_accounts =
-        unsafe { transmute::<Vec<AccountMetaFromStr>, Vec<AccountMeta>>(req.accounts) };
     accounts.extend(req_accounts);
 
     let data = zk_relayer::instruction::Relay {
@@ -260,15 +435,29 @@ async fn relay_inner(app: &AppState, req: RelayRequest) -> Result<RelayResponse,
     .data();
 
     let instruction = Instruction::new_with_bytes(zk_relayer::ID, &data, accounts);
-    let ix = InstructionBundle::new(
-        instruction,
-        RELAY_CU + req.cu_limit.unwrap_or(0),
-        None,
-        vec![],
-    );
+    let cu_limit = RELAY_CU + req.cu_limit.unwrap_or(0);
 
-    // TODO preflight...
+    // a failing transaction would still cost the network fee
+    preflight::simulate(&app.rpc_pool, app.relayer.pubkey(), &instruction, cu_limit)
+        .await
+        .map_err(RelayError::Simulation)?;
+
+    let ix = InstructionBundle::new(instruction, cu_limit, None, vec![]);
+
+    let mut entry = JournalEntry {
+        root_state_before: pub_inputs.root_state_before,
+        root_state_after: pub_inputs.root_state_after,
+        root_quota_before,
+        root_uniq_before,
+        msg_hash: req.msg_hash,
+        nu_index: idx,
+        prev_count,
+        signature: None,
+        submitted_at: now(),
+    };
+    app.store.put_journal(&skey, &entry)?;
 
+    // the journal entry stays if this fails, as the transaction may land
     let tx_results = app
         .transactor
         .send_all_instructions(
@@ -282,20 +471,19 @@ async fn relay_inner(app: &AppState, req: RelayRequest) -> Result<RelayResponse,
         )
         .await?;
 
-    // TODO persist if exited here but transaction succeeded...
-
     let signature = tx_results.last().unwrap().signature;
+    entry.signature = Some(signature.into());
+    app.store.put_journal(&skey, &entry)?;
     for tx_result in tx_results {
-        tx_result.status?;
+        if let Err(err) = tx_result.status {
+            app.store.delete_journal(&skey, &batch);
+            app.store.db.write(batch.take())?;
+            return Err(err.into());
+        }
     }
 
-    let new_count = u64_to_u256_be(prev_count + 1);
-    if prev_count == 0 {
-        state.quota_tree.add(idx, new_count)?;
-    } else {
-        state.quota_tree.update(idx, new_count)?;
-    }
-    state.uniq_tree.add(req.msg_hash, one_be())?;
+    entry.apply(&mut state)?;
+    app.store.delete_journal(&skey, &batch);
     app.store.db.write(batch.take())?;
 
     Ok(RelayResponse {
@@ -303,7 +491,82 @@ async fn relay_inner(app: &AppState, req: RelayRequest) -> Result<RelayResponse,
     })
 }
 
-fn find_relayer_state(target_program: Pubkey, state_id: u64) -> Pubkey {
+async fn fetch_allowed_programs(rpc_pool: &RpcPool) -> Result<AllowedPrograms, RelayError> {
+    let account = fetch_account(rpc_pool, ALLOWED_PROGRAMS)
+        .await
+        .ok_or(RelayError::AllowedProgramsUnavailable)?;
+    AllowedPrograms::try_deserialize(&mut &account.data[..])
+        .map_err(|_| RelayError::AllowedProgramsUnavailable)
+}
+
+/// Checks that the fee vault can pay the relayer in an accepted mint and
+/// returns the relayer's token account to be paid to.
+async fn check_fee_vault(
+    app: &AppState,
+    req: &RelayRequest,
+    fee_vault: Pubkey,
+) -> Result<Pubkey, RelayError> {
+    let vault = fetch_account(&app.rpc_pool, fee_vault)
+        .await
+        .filter(|account| {
+            TOKEN_PROGRAMS.contains(&account.owner) && is_token_account_data(&account.data)
+        })
+        .ok_or(RelayError::FeeVaultInvalid)?;
+    let mint = Pubkey::try_from(&vault.data[..32]).unwrap();
+    let balance = u64::from_le_bytes(vault.data[64..72].try_into().unwrap());
+
+    if !app.accepted_mints.contains(&mint) {
+        return Err(RelayError::MintNotAccepted(mint));
+    }
+
+    let relayer_state = fetch_account(
+        &app.rpc_pool,
+        find_relayer_state(req.target_program, req.state_id),
+    )
+    .await
+    .ok_or(RelayError::RelayerStateUnavailable)?;
+    let relayer_state = RelayerState::try_deserialize(&mut &relayer_state.data[..])
+        .map_err(|_| RelayError::RelayerStateUnavailable)?;
+    // the quota fee is in base units of the state's mint
+    if relayer_state.fee_mint != Some(mint) {
+        return Err(RelayError::FeeVaultInvalid);
+    }
+    let fee = relayer_state
+        .quota(&app.relayer.pubkey())
+        .map(|q| q.fee)
+        .ok_or(RelayError::RelayerStateUnavailable)?;
+    if balance < fee {
+        return Err(RelayError::FeeVaultInsufficient { balance, fee });
+    }
+
+    let relayer_tokens = find_associated_token_account(&app.relayer.pubkey(), &vault.owner, &mint);
+    if !req.accounts.iter().any(|a| a.pubkey == relayer_tokens) {
+        return Err(RelayError::RelayerTokensMissing(relayer_tokens));
+    }
+
+    Ok(relayer_tokens)
+}
+
+/// Why relays for the state are refused, if its trees diverged from the
+/// chain.
+fn quarantine_reason(quarantine: &Quarantine, skey: &StateKey) -> Option<String> {
+    quarantine.get(skey).map(|reason| reason.clone())
+}
+
+pub(crate) async fn fetch_account(rpc_pool: &RpcPool, pubkey: Pubkey) -> Option<Account> {
+    rpc_pool
+        .with_read_rpc_loop(
+            |rpc| async move {
+                rpc.get_account_with_commitment(&pubkey, CommitmentConfig::confirmed())
+                    .await
+            },
+            CommitmentConfig::confirmed(),
+        )
+        .await
+        .value
+}
+
+pub(crate) fn find_relayer_state(target_program: Pubkey, state_id: u64) -> Pubkey {
     Pubkey::find_program_address(
         &[
             b"RELAYER_STATE",
@@ -315,13 +578,29 @@ fn find_relayer_state(target_program: Pubkey, state_id: u64) -> Pubkey {
     .0
 }
 
-fn one_be() -> [u8; 32] {
+pub(crate) fn find_associated_token_account(
+    wallet: &Pubkey,
+    token_program: &Pubkey,
+    mint: &Pubkey,
+) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            &wallet.to_bytes(),
+            &token_program.to_bytes(),
+            &mint.to_bytes(),
+        ],
+        &ASSOCIATED_TOKEN_PROGRAM,
+    )
+    .0
+}
+
+pub(crate) fn one_be() -> [u8; 32] {
     let mut b = [0u8; 32];
     b[31] = 1;
     b
 }
 
-fn u64_to_u256_be(x: u64) -> [u8; 32] {
+pub(crate) fn u64_to_u256_be(x: u64) -> [u8; 32] {
     let mut res = [0; 32];
     res[32 - 8..].copy_from_slice(&x.to_be_bytes());
     res
@@ -332,10 +611,56 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_relayer_config_pda() {
+    fn test_allowed_programs_pda() {
         assert_eq!(
-            RELAYER_CONFIG,
-            Pubkey::find_program_address(&[b"RELAYER_CONFIG"], &zk_relayer::ID).0
+            ALLOWED_PROGRAMS,
+            Pubkey::find_program_address(&[b"ALLOWED_PROGRAMS"], &zk_relayer::ID).0
         );
     }
+
+    #[test]
+    fn test_quarantined_state_refused() {
+        let skey = StateKey {
+            program: Pubkey::new_unique(),
+            state_id: 0,
+        };
+        let quarantine = Quarantine::new();
+        assert!(quarantine_reason(&quarantine, &skey).is_none());
+
+        consistency::state_status(&skey, [1; 32], Some([2; 32]), false, &quarantine);
+        let reason = quarantine_reason(&quarantine, &skey).unwrap();
+        assert!(reason.contains(&hex::encode([2; 32])));
+        // other states stay open
+        let other = StateKey {
+            program: skey.program,
+            state_id: 1,
+        };
+        assert!(quarantine_reason(&quarantine, &other).is_none());
+
+        consistency::state_status(&skey, [2; 32], Some([2; 32]), false, &quarantine);
+        assert!(quarantine_reason(&quarantine, &skey).is_none());
+    }
+
+    #[test]
+    fn test_admin_token() {
+        let request = |header: Option<&str>| {
+            let mut req = actix_web::test::TestRequest::default();
+            if let Some(header) = header {
+                req = req.insert_header((AUTHORIZATION, header));
+            }
+            req.to_http_request()
+        };
+        let token = Some("secret");
+
+        assert!(authorized(token, &request(Some("Bearer secret"))));
+        assert!(!authorized(token, &request(None)));
+        assert!(!authorized(token, &request(Some("Bearer secreT"))));
+        assert!(!authorized(token, &request(Some("Bearer secret2"))));
+        assert!(!authorized(token, &request(Some("Bearer "))));
+        assert!(!authorized(token, &request(Some("Basic secret"))));
+        assert!(!authorized(token, &request(Some("secret"))));
+        // the endpoint is disabled without a token
+        assert!(!authorized(None, &request(Some("Bearer secret"))));
+        assert!(!authorized(None, &request(Some("Bearer "))));
+    }
 }
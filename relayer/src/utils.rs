@@ -27,3 +27,11 @@ where
 {
     s.serialize_u8(*b as u8)
 }
+
+/// Unix time of the system clock.
+pub fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
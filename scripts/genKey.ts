@@ -7,13 +7,13 @@ async function main(): Promise<void> {
   const eddsa = await buildEddsa();
   const F = babyjub.F;
 
-  const { prv, pub } = genBabyJubKeypair(babyjub, eddsa);
+  const { prv, sk, pub } = genBabyJubKeypair(babyjub, eddsa);
 
   const pubHex = pub.map((x) => F.toObject(x).toString(16));
   const prvHex = bytesToHex(prv);
 
   console.log(
-    `Private key: 0x${prvHex}\nPublic key: 0x${pubHex[0]} 0x${pubHex[1]}`,
+    `Private key: 0x${prvHex}\nSecret scalar: ${sk}\nPublic key: 0x${pubHex[0]} 0x${pubHex[1]}`,
   );
 }
 
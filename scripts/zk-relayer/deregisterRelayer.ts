@@ -0,0 +1,26 @@
+import * as anchor from "@coral-xyz/anchor";
+import NodeWallet from "@coral-xyz/anchor/dist/cjs/nodewallet.js";
+import { deregisterRelayer, toTransaction } from "@lincot/zk-relayer-sdk";
+import { sendAndConfirmVersionedTx } from "../../helpers/utils.ts";
+
+async function main(): Promise<void> {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const payer = (provider.wallet as NodeWallet).payer;
+
+  const ix = await deregisterRelayer({ operator: payer.publicKey });
+  const transactionSignature = await sendAndConfirmVersionedTx(
+    provider.connection,
+    toTransaction(
+      [ix],
+      await provider.connection.getLatestBlockhash().then((b) => b.blockhash),
+      payer,
+    ),
+    [payer],
+    payer.publicKey,
+  );
+
+  console.log("Transaction signature:", transactionSignature);
+}
+
+main();
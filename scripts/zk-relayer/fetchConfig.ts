@@ -1,5 +1,9 @@
 import * as anchor from "@coral-xyz/anchor";
-import { fetchRelayerConfig } from "@lincot/zk-relayer-sdk";
+import {
+  fetchAllowedPrograms,
+  fetchRegisteredRelayers,
+  fetchRelayerConfig,
+} from "@lincot/zk-relayer-sdk";
 
 async function main(): Promise<void> {
   const provider = anchor.AnchorProvider.env();
@@ -12,9 +16,27 @@ async function main(): Promise<void> {
   }
 
   console.log("Admin:", config.admin.toString());
-  console.log("Fee:", config.fee.toString());
-  console.log("Relayer endpoint:", config.relayer.endpoint.toString());
-  console.log("Relayer fee key:", config.relayer.feeKey.toString());
+  console.log("Min bond:", config.minBond.toString());
+  console.log("Unbonding period:", config.unbondingPeriod.toString());
+
+  const allowedPrograms = await fetchAllowedPrograms(provider.connection);
+  for (const { program, feeMultiplierBps } of allowedPrograms?.programs ?? []) {
+    console.log(
+      "Allowed program:",
+      program.toString(),
+      feeMultiplierBps === null ? "" : `(fee x${feeMultiplierBps / 10_000})`,
+    );
+  }
+
+  for (const relayer of await fetchRegisteredRelayers(provider.connection)) {
+    console.log("Relayer:", relayer.feeKey.toString());
+    console.log("  endpoint:", relayer.endpoint);
+    console.log("  fee:", relayer.fee.toString());
+    console.log("  bond:", relayer.bond.toString());
+    if (!relayer.unbondingEnd.isZero()) {
+      console.log("  unbonding until:", relayer.unbondingEnd.toString());
+    }
+  }
 }
 
 main();
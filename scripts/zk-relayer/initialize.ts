@@ -1,17 +1,21 @@
 import * as anchor from "@coral-xyz/anchor";
 import NodeWallet from "@coral-xyz/anchor/dist/cjs/nodewallet.js";
-import { initialize, toTransaction } from "@lincot/zk-relayer-sdk";
+import {
+  initAllowedPrograms,
+  initialize,
+  toTransaction,
+} from "@lincot/zk-relayer-sdk";
 import { expect } from "chai";
 import { sendAndConfirmVersionedTx } from "../../helpers/utils.ts";
 
 async function main(): Promise<void> {
   if (process.argv.length < 2 + 2) {
-    console.error("Usage: initialize <fee> <endpoint>");
+    console.error("Usage: initialize <minBond> <unbondingPeriod>");
     process.exit(1);
   }
 
-  const fee = BigInt(process.argv[2]);
-  const relayerEndpoint = process.argv[3];
+  const minBond = BigInt(process.argv[2]);
+  const unbondingPeriod = BigInt(process.argv[3]);
 
   const provider = anchor.AnchorProvider.env();
   anchor.setProvider(provider);
@@ -20,15 +24,18 @@ async function main(): Promise<void> {
   try {
     const ix = await initialize({
       admin: payer.publicKey,
-      fee: fee,
+      minBond,
+      unbondingPeriod,
       payer: payer.publicKey,
-      relayerEndpoint,
-      relayerFeeKey: payer.publicKey,
+    });
+    const initAllowedProgramsIx = await initAllowedPrograms({
+      payer: payer.publicKey,
+      admin: payer.publicKey,
     });
     const transactionSignature = await sendAndConfirmVersionedTx(
       provider.connection,
       toTransaction(
-        [ix],
+        [ix, initAllowedProgramsIx],
         await provider.connection.getLatestBlockhash().then((b) => b.blockhash),
         payer,
       ),
@@ -0,0 +1,48 @@
+import * as anchor from "@coral-xyz/anchor";
+import NodeWallet from "@coral-xyz/anchor/dist/cjs/nodewallet.js";
+import {
+  initAllowedPrograms,
+  migrateConfig,
+  toTransaction,
+} from "@lincot/zk-relayer-sdk";
+import { sendAndConfirmVersionedTx } from "../../helpers/utils.ts";
+
+// upgrades a deployment from the single relayer config and the compile-time
+// allowed programs, which then have to be added with `addAllowedProgram`
+async function main(): Promise<void> {
+  if (process.argv.length < 2 + 2) {
+    console.error("Usage: migrateConfig <minBond> <unbondingPeriod>");
+    process.exit(1);
+  }
+
+  const minBond = BigInt(process.argv[2]);
+  const unbondingPeriod = BigInt(process.argv[3]);
+
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const payer = (provider.wallet as NodeWallet).payer;
+
+  const migrateConfigIx = await migrateConfig({
+    admin: payer.publicKey,
+    minBond,
+    unbondingPeriod,
+  });
+  const initAllowedProgramsIx = await initAllowedPrograms({
+    payer: payer.publicKey,
+    admin: payer.publicKey,
+  });
+  const transactionSignature = await sendAndConfirmVersionedTx(
+    provider.connection,
+    toTransaction(
+      [migrateConfigIx, initAllowedProgramsIx],
+      await provider.connection.getLatestBlockhash().then((b) => b.blockhash),
+      payer,
+    ),
+    [payer],
+    payer.publicKey,
+  );
+
+  console.log("Transaction signature:", transactionSignature);
+}
+
+main();
@@ -0,0 +1,40 @@
+import * as anchor from "@coral-xyz/anchor";
+import NodeWallet from "@coral-xyz/anchor/dist/cjs/nodewallet.js";
+import { registerRelayer, toTransaction } from "@lincot/zk-relayer-sdk";
+import { sendAndConfirmVersionedTx } from "../../helpers/utils.ts";
+
+async function main(): Promise<void> {
+  if (process.argv.length < 2 + 3) {
+    console.error("Usage: registerRelayer <endpoint> <fee> <bond>");
+    process.exit(1);
+  }
+
+  const endpoint = process.argv[2];
+  const fee = BigInt(process.argv[3]);
+  const bond = BigInt(process.argv[4]);
+
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const payer = (provider.wallet as NodeWallet).payer;
+
+  const ix = await registerRelayer({
+    operator: payer.publicKey,
+    endpoint,
+    fee,
+    bond,
+  });
+  const transactionSignature = await sendAndConfirmVersionedTx(
+    provider.connection,
+    toTransaction(
+      [ix],
+      await provider.connection.getLatestBlockhash().then((b) => b.blockhash),
+      payer,
+    ),
+    [payer],
+    payer.publicKey,
+  );
+
+  console.log("Transaction signature:", transactionSignature);
+}
+
+main();
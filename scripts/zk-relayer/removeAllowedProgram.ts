@@ -0,0 +1,38 @@
+import * as anchor from "@coral-xyz/anchor";
+import NodeWallet from "@coral-xyz/anchor/dist/cjs/nodewallet.js";
+import { removeAllowedProgram, toTransaction } from "@lincot/zk-relayer-sdk";
+import { PublicKey } from "@solana/web3.js";
+import { sendAndConfirmVersionedTx } from "../../helpers/utils.ts";
+
+async function main(): Promise<void> {
+  if (process.argv.length < 2 + 1) {
+    console.error("Usage: removeAllowedProgram <program>");
+    process.exit(1);
+  }
+
+  const program = new PublicKey(process.argv[2]);
+
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const payer = (provider.wallet as NodeWallet).payer;
+
+  const ix = await removeAllowedProgram({
+    payer: payer.publicKey,
+    admin: payer.publicKey,
+    program,
+  });
+  const transactionSignature = await sendAndConfirmVersionedTx(
+    provider.connection,
+    toTransaction(
+      [ix],
+      await provider.connection.getLatestBlockhash().then((b) => b.blockhash),
+      payer,
+    ),
+    [payer],
+    payer.publicKey,
+  );
+
+  console.log("Transaction signature:", transactionSignature);
+}
+
+main();
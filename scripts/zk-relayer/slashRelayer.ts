@@ -0,0 +1,45 @@
+import * as anchor from "@coral-xyz/anchor";
+import NodeWallet from "@coral-xyz/anchor/dist/cjs/nodewallet.js";
+import { slashRelayer, toTransaction } from "@lincot/zk-relayer-sdk";
+import { PublicKey } from "@solana/web3.js";
+import { sendAndConfirmVersionedTx } from "../../helpers/utils.ts";
+
+async function main(): Promise<void> {
+  if (process.argv.length < 2 + 4) {
+    console.error(
+      "Usage: slashRelayer <operator> <amount> <destination> <reason>",
+    );
+    process.exit(1);
+  }
+
+  const operator = new PublicKey(process.argv[2]);
+  const amount = BigInt(process.argv[3]);
+  const destination = new PublicKey(process.argv[4]);
+  const reason = process.argv[5];
+
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const payer = (provider.wallet as NodeWallet).payer;
+
+  const ix = await slashRelayer({
+    admin: payer.publicKey,
+    operator,
+    amount,
+    destination,
+    reason,
+  });
+  const transactionSignature = await sendAndConfirmVersionedTx(
+    provider.connection,
+    toTransaction(
+      [ix],
+      await provider.connection.getLatestBlockhash().then((b) => b.blockhash),
+      payer,
+    ),
+    [payer],
+    payer.publicKey,
+  );
+
+  console.log("Transaction signature:", transactionSignature);
+}
+
+main();
@@ -7,12 +7,12 @@ import { PublicKey } from "@solana/web3.js";
 
 async function main(): Promise<void> {
   if (process.argv.length < 2 + 3) {
-    console.error("Usage: updateConfig <fee> <endpoint> <newAdmin>");
+    console.error("Usage: updateConfig <minBond> <unbondingPeriod> <newAdmin>");
     process.exit(1);
   }
 
-  const fee = BigInt(process.argv[2]);
-  const relayerEndpoint = process.argv[3];
+  const minBond = BigInt(process.argv[2]);
+  const unbondingPeriod = BigInt(process.argv[3]);
   const newAdmin = new PublicKey(process.argv[4]);
 
   const provider = anchor.AnchorProvider.env();
@@ -22,10 +22,8 @@ async function main(): Promise<void> {
   const ix = await updateConfig({
     oldAdmin: payer.publicKey,
     newAdmin,
-    fee: fee,
-    payer: payer.publicKey,
-    relayerEndpoint,
-    relayerFeeKey: newAdmin,
+    minBond,
+    unbondingPeriod,
   });
   const transactionSignature = await sendAndConfirmVersionedTx(
     provider.connection,
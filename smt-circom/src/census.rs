@@ -0,0 +1,386 @@
+//! Census tree checked by the Vote circuits' `MerkleTreeInclusionProof`: a
+//! fixed-depth Poseidon tree with leaves filled from the left, empty leaves
+//! being `Poseidon(0)` and inner nodes `Poseidon(left, right)`, as built by
+//! `helpers/merkletree.ts`.
+//!
+//! The census file is the concatenation of 32-byte big-endian leaves.
+
+use ark_bn254::Fr;
+use light_poseidon::{bytes_to_prime_field_element_be, Poseidon, PoseidonBytesHasher};
+use std::sync::LazyLock;
+
+pub const CENSUS_DEPTH: usize = 40;
+
+/// Roots of empty subtrees by level.
+static ZEROS: LazyLock<[[u8; 32]; CENSUS_DEPTH + 1]> = LazyLock::new(|| {
+    let mut zeros = [hash(&[&[0; 32]]).expect("0 is a field element"); CENSUS_DEPTH + 1];
+    for i in 1..=CENSUS_DEPTH {
+        zeros[i] = hash(&[&zeros[i - 1], &zeros[i - 1]]).expect("hashes are field elements");
+    }
+    zeros
+});
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum CensusError {
+    #[error("Not a field element")]
+    NotFieldElement,
+    #[error("The tree is full")]
+    Full,
+    #[error("Census size isn't a multiple of 32")]
+    Size,
+}
+
+fn hash(inputs: &[&[u8]]) -> Result<[u8; 32], CensusError> {
+    Poseidon::<Fr>::new_circom(inputs.len())
+        .expect("poseidon init")
+        .hash_bytes_be(inputs)
+        .map_err(|_| CensusError::NotFieldElement)
+}
+
+pub fn is_field_element(bytes: &[u8; 32]) -> bool {
+    bytes_to_prime_field_element_be::<Fr>(bytes).is_ok()
+}
+
+/// Leaf of a voter with the BabyJubJub public key `key`, `Poseidon(Key)`.
+pub fn leaf(key: &[[u8; 32]; 2]) -> Result<[u8; 32], CensusError> {
+    hash(&[&key[0], &key[1]])
+}
+
+/// Node with no leaves under it at `level`.
+pub fn empty_node(level: usize) -> [u8; 32] {
+    ZEROS[level]
+}
+
+/// Decimal form of a node, as circuit inputs are given.
+pub fn to_decimal(node: &[u8; 32]) -> String {
+    bytes_to_prime_field_element_be::<Fr>(node)
+        .expect("nodes are field elements")
+        .to_string()
+}
+
+/// `(level, index)` of the siblings on the path from leaf `index` to the
+/// root, the `Path` input of the Vote circuits.
+pub fn path_siblings(index: u64) -> impl Iterator<Item = (usize, u64)> {
+    (0..CENSUS_DEPTH).map(move |level| (level, (index >> level) ^ 1))
+}
+
+/// The `PathPos` input of the Vote circuits for leaf `index`, 1 where the
+/// path goes through a right child.
+pub fn path_pos(index: u64) -> [u8; CENSUS_DEPTH] {
+    core::array::from_fn(|level| (index >> level) as u8 & 1)
+}
+
+/// Node at `level`, leaves being level 0, and `index` from the left.
+#[derive(Clone, Copy, Debug)]
+pub struct Node {
+    pub level: usize,
+    pub index: u64,
+    pub hash: [u8; 32],
+}
+
+/// Tree built while the leaves stream in, keeping only a node per level.
+/// Nodes are handed out as they're computed so that paths can be served
+/// from storage.
+pub struct CensusTree {
+    /// Left nodes waiting for their right sibling, by level.
+    frontier: [Option<[u8; 32]>; CENSUS_DEPTH],
+    len: u64,
+    /// Computed nodes below the root not yet taken.
+    nodes: Vec<Node>,
+}
+
+impl Default for CensusTree {
+    fn default() -> Self {
+        Self {
+            frontier: [None; CENSUS_DEPTH],
+            len: 0,
+            nodes: Vec::new(),
+        }
+    }
+}
+
+impl CensusTree {
+    pub fn push(&mut self, leaf: &[u8; 32]) -> Result<(), CensusError> {
+        if self.len == 1 << CENSUS_DEPTH {
+            return Err(CensusError::Full);
+        }
+        if !is_field_element(leaf) {
+            return Err(CensusError::NotFieldElement);
+        }
+        let mut node = *leaf;
+        for (level, left) in self.frontier.iter_mut().enumerate() {
+            self.nodes.push(Node {
+                level,
+                index: self.len >> level,
+                hash: node,
+            });
+            match left.take() {
+                Some(left) => node = hash(&[&left, &node])?,
+                None => {
+                    *left = Some(node);
+                    break;
+                }
+            }
+        }
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Nodes computed since the last call.
+    pub fn take_nodes(&mut self) -> Vec<Node> {
+        core::mem::take(&mut self.nodes)
+    }
+
+    /// Computes the root once all leaves are in, along with the nodes on the
+    /// right edge whose subtrees are partly empty.
+    pub fn finish(&mut self) -> [u8; 32] {
+        // the rightmost node of each level, its right sibling is empty
+        let mut node = ZEROS[0];
+        for (level, (left, zero)) in self.frontier.iter().zip(ZEROS.iter()).enumerate() {
+            node = match left {
+                Some(left) => hash(&[left, &node]),
+                None => hash(&[&node, zero]),
+            }
+            .expect("hashes are field elements");
+            let parent = level + 1;
+            if parent < CENSUS_DEPTH && !self.len.is_multiple_of(1 << parent) {
+                self.nodes.push(Node {
+                    level: parent,
+                    index: self.len >> parent,
+                    hash: node,
+                });
+            }
+        }
+        node
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CensusPath {
+    pub siblings: [[u8; 32]; CENSUS_DEPTH],
+    pub path_pos: [u8; CENSUS_DEPTH],
+}
+
+impl CensusPath {
+    /// Root the path leads to from `leaf`.
+    pub fn root(&self, leaf: &[u8; 32]) -> Result<[u8; 32], CensusError> {
+        let mut node = *leaf;
+        for (sibling, pos) in self.siblings.iter().zip(self.path_pos) {
+            node = match pos {
+                0 => hash(&[&node, sibling])?,
+                _ => hash(&[sibling, &node])?,
+            };
+        }
+        Ok(node)
+    }
+}
+
+/// Whole census kept in memory, for creating censuses and their paths.
+pub struct Census {
+    /// Nodes having leaves under them by level, leaves first.
+    levels: Vec<Vec<[u8; 32]>>,
+    root: [u8; 32],
+}
+
+impl Census {
+    pub fn new(leaves: &[[u8; 32]]) -> Result<Self, CensusError> {
+        let mut tree = CensusTree::default();
+        for leaf in leaves {
+            tree.push(leaf)?;
+        }
+        let root = tree.finish();
+        let mut levels = vec![Vec::new(); CENSUS_DEPTH];
+        for node in tree.take_nodes() {
+            levels[node.level].push(node.hash);
+        }
+        Ok(Self { levels, root })
+    }
+
+    pub fn from_keys(keys: &[[[u8; 32]; 2]]) -> Result<Self, CensusError> {
+        let leaves = keys.iter().map(leaf).collect::<Result<Vec<_>, _>>()?;
+        Self::new(&leaves)
+    }
+
+    /// Parses a census file.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CensusError> {
+        if !bytes.len().is_multiple_of(32) {
+            return Err(CensusError::Size);
+        }
+        let leaves: Vec<[u8; 32]> = bytes
+            .chunks_exact(32)
+            .map(|leaf| leaf.try_into().unwrap())
+            .collect();
+        Self::new(&leaves)
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.root
+    }
+
+    pub fn len(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels[0].is_empty()
+    }
+
+    pub fn leaves(&self) -> &[[u8; 32]] {
+        &self.levels[0]
+    }
+
+    /// Index of the first occurrence of `leaf`.
+    pub fn position(&self, leaf: &[u8; 32]) -> Option<u64> {
+        self.levels[0]
+            .iter()
+            .position(|l| l == leaf)
+            .map(|i| i as u64)
+    }
+
+    pub fn path(&self, index: u64) -> Option<CensusPath> {
+        if index >= self.len() as u64 {
+            return None;
+        }
+        Some(CensusPath {
+            siblings: core::array::from_fn(|level| {
+                let sibling = ((index >> level) ^ 1) as usize;
+                self.levels[level]
+                    .get(sibling)
+                    .copied()
+                    .unwrap_or(ZEROS[level])
+            }),
+            path_pos: path_pos(index),
+        })
+    }
+
+    /// The census file `CensusManager` of the indexer ingests.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.levels[0].concat()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(n: u64) -> [u8; 32] {
+        let mut bytes = [0; 32];
+        bytes[24..].copy_from_slice(&n.to_be_bytes());
+        bytes
+    }
+
+    /// Level-by-level construction as in `helpers/merkletree.ts`.
+    fn naive_root(leaves: &[[u8; 32]]) -> [u8; 32] {
+        let mut level = leaves.to_vec();
+        for zero in ZEROS.iter().take(CENSUS_DEPTH) {
+            if level.is_empty() {
+                level.push(*zero);
+            }
+            level = level
+                .chunks(2)
+                .map(|pair| hash(&[&pair[0], pair.get(1).unwrap_or(zero)]).unwrap())
+                .collect();
+        }
+        level[0]
+    }
+
+    #[test]
+    fn test_root_matches_naive() {
+        let leaves: Vec<_> = (1..=9).map(|i| hash(&[&field(i)]).unwrap()).collect();
+        for n in 0..=leaves.len() {
+            let mut tree = CensusTree::default();
+            for leaf in &leaves[..n] {
+                tree.push(leaf).unwrap();
+            }
+            assert_eq!(tree.finish(), naive_root(&leaves[..n]), "{n} leaves");
+        }
+    }
+
+    #[test]
+    fn test_paths() {
+        let keys: Vec<_> = (0..11).map(|i| [field(2 * i), field(2 * i + 1)]).collect();
+        let census = Census::from_keys(&keys).unwrap();
+        for (index, leaf) in census.leaves().iter().enumerate() {
+            let path = census.path(index as u64).unwrap();
+            assert_eq!(path.root(leaf).unwrap(), census.root(), "leaf {index}");
+        }
+        assert_eq!(census.path(keys.len() as u64), None);
+    }
+
+    #[test]
+    fn test_census_file() {
+        let keys: Vec<_> = (1..=5).map(|i| [field(i), field(i)]).collect();
+        let census = Census::from_keys(&keys).unwrap();
+        let bytes = census.to_bytes();
+        assert_eq!(bytes.len(), 5 * 32);
+        let parsed = Census::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed.root(), census.root());
+        assert_eq!(parsed.leaves(), census.leaves());
+        assert_eq!(parsed.to_bytes(), bytes);
+        assert_eq!(
+            Census::from_bytes(&bytes[1..]).err(),
+            Some(CensusError::Size)
+        );
+    }
+
+    #[test]
+    fn test_rejects_non_field_leaf() {
+        assert_eq!(
+            CensusTree::default().push(&[0xff; 32]),
+            Err(CensusError::NotFieldElement)
+        );
+    }
+
+    #[test]
+    fn test_vectors() {
+        let vectors: serde_json::Value =
+            serde_json::from_str(include_str!("../../helpers/census_vectors.json")).unwrap();
+        let bytes = |value: &serde_json::Value| -> [u8; 32] {
+            let mut out = [0; 32];
+            hex::decode_to_slice(&value.as_str().unwrap()[2..], &mut out).unwrap();
+            out
+        };
+
+        assert_eq!(vectors["depth"], CENSUS_DEPTH);
+        assert_eq!(empty_node(0), bytes(&vectors["empty_leaf"]));
+        for case in vectors["cases"].as_array().unwrap() {
+            let keys: Vec<_> = case["keys"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|key| [bytes(&key[0]), bytes(&key[1])])
+                .collect();
+            let census = Census::from_keys(&keys).unwrap();
+
+            let leaves: Vec<_> = case["leaves"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(bytes)
+                .collect();
+            assert_eq!(census.leaves(), leaves);
+            assert_eq!(census.root(), bytes(&case["root"]));
+            assert_eq!(
+                hex::encode(census.to_bytes()),
+                case["census"].as_str().unwrap()
+            );
+            for proof in case["proofs"].as_array().unwrap() {
+                let path = census.path(proof["index"].as_u64().unwrap()).unwrap();
+                let siblings: Vec<_> = proof["path"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(bytes)
+                    .collect();
+                let path_pos: Vec<_> = proof["path_pos"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|p| p.as_u64().unwrap() as u8)
+                    .collect();
+                assert_eq!(path.siblings[..], siblings);
+                assert_eq!(path.path_pos[..], path_pos);
+            }
+        }
+    }
+}
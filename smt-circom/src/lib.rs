@@ -4,6 +4,7 @@ use light_poseidon::{Poseidon, PoseidonBytesHasher};
 
 use crate::store::NodeStore;
 
+pub mod census;
 pub mod store;
 
 #[inline]
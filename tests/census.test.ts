@@ -0,0 +1,44 @@
+import { describe, test } from "mocha";
+import { expect } from "chai";
+import { readFileSync } from "fs";
+import { buildPoseidon } from "circomlibjs";
+import { getMerkleProof, getMerkleRoot } from "../helpers/merkletree.ts";
+import { toBytesBE32Buf } from "../helpers/utils.ts";
+
+// shared with the `census` module of smt-circom
+const vectors = JSON.parse(
+  readFileSync("helpers/census_vectors.json", "utf8"),
+);
+
+describe("Census", () => {
+  test("empty leaf", async () => {
+    const poseidon = await buildPoseidon();
+    expect(poseidon.F.toObject(poseidon([0n]))).eq(BigInt(vectors.empty_leaf));
+  });
+
+  for (const [i, c] of vectors.cases.entries()) {
+    test(`vectors ${i}`, async () => {
+      const poseidon = await buildPoseidon();
+      const F = poseidon.F;
+
+      const keys: bigint[][] = c.keys.map((key: string[]) => key.map(BigInt));
+      const leaves = keys.map((key) => F.toObject(poseidon(key)) as bigint);
+      expect(leaves).deep.eq(c.leaves.map(BigInt));
+
+      const census = Buffer.concat(leaves.map((leaf) => toBytesBE32Buf(leaf)));
+      expect(census.toString("hex")).eq(c.census);
+
+      expect(await getMerkleRoot(vectors.depth, leaves)).eq(BigInt(c.root));
+      for (const proof of c.proofs) {
+        const { root, path, pathPos } = await getMerkleProof(
+          vectors.depth,
+          leaves,
+          proof.index,
+        );
+        expect(root).eq(BigInt(c.root));
+        expect(path).deep.eq(proof.path.map(BigInt));
+        expect(pathPos).deep.eq(proof.path_pos.map(BigInt));
+      }
+    });
+  }
+});
@@ -1,19 +1,31 @@
 import {
+  closePoll,
+  closeRelayerState,
   closeTally,
+  createFeeVault,
   createPoll,
+  type CreatePollParams,
   createTally,
+  failPoll,
   fetchPlatformConfig,
+  fetchPlatformTokenFee,
   fetchPoll,
+  fetchPollResult,
   fetchTally,
+  findAssociatedTokenAccount,
   findPoll,
+  findPollResult,
   findTally,
   finishTally,
+  type FeeToken,
+  fundPoll,
   initialize,
   type InstructionWithCu,
   onVote,
   PLATFORM_NAME,
   PROGRAM_ID,
   setProvider,
+  setTokenFee,
   tallyBatch,
   toTransaction,
   updateConfig,
@@ -22,14 +34,30 @@ import {
   withdrawPoll,
 } from "@lincot/anon-vote-sdk";
 import {
+  addAllowedProgram,
+  deregisterRelayer,
+  fetchAllowedPrograms,
+  fetchRegisteredRelayer,
+  fetchRegisteredRelayers,
   fetchRelayerConfig,
   fetchRelayerState,
+  fetchRelayerTokenFee,
+  findRegisteredRelayer,
   findRelayerState,
+  initAllowedPrograms,
   initialize as initializeRelayer,
+  migrateConfig,
+  registerRelayer,
+  removeAllowedProgram,
+  requestDeregistration,
+  setTokenFee as setRelayerTokenFee,
+  slashRelayer,
   updateConfig as updateRelayerConfig,
+  updateRelayer,
 } from "@lincot/zk-relayer-sdk";
 import {
   Keypair,
+  PublicKey,
   type Signer,
   type TransactionSignature,
 } from "@solana/web3.js";
@@ -67,6 +95,13 @@ import {
   ZERO_HASH,
 } from "@iden3/js-merkletree";
 import { mulPointEscalar } from "@zk-kit/baby-jubjub";
+import {
+  createMint,
+  createTokenAccounts,
+  mintTo,
+  TOKEN_2022_PROGRAM_ID,
+  tokenBalance,
+} from "../helpers/token.ts";
 import anchor from "@coral-xyz/anchor";
 
 const { BN } = anchor;
@@ -174,50 +209,288 @@ const relayerFee = 100_000n;
 
 describe("ZK Relayer", () => {
   const tempAdmin = new Keypair();
+  const minBond = 1_000_000n;
+  const unbondingPeriod = 2n;
 
   test("initialize", async () => {
-    const relayerEndpoint = "https://test.test";
-    const fee = 123123n;
     await sendIx(
       await initializeRelayer({
         admin: tempAdmin.publicKey,
-        fee,
+        minBond: 123123n,
+        unbondingPeriod: 100n,
         payer: payer.publicKey,
-        relayerEndpoint,
-        relayerFeeKey: relayer.publicKey,
       }),
     );
 
     const relayerConfig = await fetchRelayerConfig(connection);
     expect(relayerConfig?.admin.equals(tempAdmin.publicKey)).to.be.true;
-    expect(toBigint(relayerConfig?.fee)).to.equal(fee);
-    expect(relayerConfig?.relayer).to.deep.equal({
-      feeKey: relayer.publicKey,
-      endpoint: relayerEndpoint,
-    });
+    expect(toBigint(relayerConfig?.minBond)).to.equal(123123n);
+    expect(toBigint(relayerConfig?.unbondingPeriod)).to.equal(100n);
+
+    await expect(sendIx(
+      await migrateConfig({
+        admin: tempAdmin.publicKey,
+        minBond: 123123n,
+        unbondingPeriod: 100n,
+      }),
+      [payer, tempAdmin],
+    )).to.rejectedWith("ConfigAlreadyMigrated");
+  });
+
+  test("initAllowedPrograms", async () => {
+    await expect(sendIx(
+      await initAllowedPrograms({
+        payer: payer.publicKey,
+        admin: admin.publicKey,
+      }),
+      [payer, admin],
+    )).to.rejectedWith("ConstraintHasOne");
+    await sendIx(
+      await initAllowedPrograms({
+        payer: payer.publicKey,
+        admin: tempAdmin.publicKey,
+      }),
+      [payer, tempAdmin],
+    );
+
+    const allowedPrograms = await fetchAllowedPrograms(connection);
+    expect(allowedPrograms?.programs).to.deep.equal([]);
   });
 
   test("updateRelayerConfig", async () => {
-    const relayerEndpoint = "https://test2.test";
     await sendIx(
       await updateRelayerConfig({
         oldAdmin: tempAdmin.publicKey,
         newAdmin: admin.publicKey,
-        fee: relayerFee,
-        payer: payer.publicKey,
-        relayerEndpoint,
-        relayerFeeKey: relayer.publicKey,
+        minBond,
+        unbondingPeriod,
       }),
       [payer, tempAdmin],
     );
 
     const relayerConfig = await fetchRelayerConfig(connection);
     expect(relayerConfig?.admin.equals(admin.publicKey)).to.be.true;
-    expect(toBigint(relayerConfig?.fee)).to.equal(relayerFee);
-    expect(relayerConfig?.relayer).to.deep.equal({
-      feeKey: relayer.publicKey,
-      endpoint: relayerEndpoint,
-    });
+    expect(toBigint(relayerConfig?.minBond)).to.equal(minBond);
+    expect(toBigint(relayerConfig?.unbondingPeriod)).to.equal(unbondingPeriod);
+  });
+
+  test("addAllowedProgram", async () => {
+    const otherProgram = new Keypair().publicKey;
+
+    await sendIx(
+      await addAllowedProgram({
+        payer: payer.publicKey,
+        admin: admin.publicKey,
+        program: PROGRAM_ID,
+      }),
+      [payer, admin],
+    );
+    await sendIx(
+      await addAllowedProgram({
+        payer: payer.publicKey,
+        admin: admin.publicKey,
+        program: otherProgram,
+        feeMultiplierBps: 15_000,
+      }),
+      [payer, admin],
+    );
+    await expect(sendIx(
+      await addAllowedProgram({
+        payer: payer.publicKey,
+        admin: admin.publicKey,
+        program: PROGRAM_ID,
+      }),
+      [payer, admin],
+    )).to.rejectedWith("ProgramAlreadyAllowed");
+
+    let allowedPrograms = await fetchAllowedPrograms(connection);
+    expect(allowedPrograms?.programs).to.deep.equal([
+      { program: PROGRAM_ID, feeMultiplierBps: null },
+      { program: otherProgram, feeMultiplierBps: 15_000 },
+    ]);
+
+    await sendIx(
+      await removeAllowedProgram({
+        payer: payer.publicKey,
+        admin: admin.publicKey,
+        program: otherProgram,
+      }),
+      [payer, admin],
+    );
+
+    allowedPrograms = await fetchAllowedPrograms(connection);
+    expect(allowedPrograms?.programs).to.deep.equal([
+      { program: PROGRAM_ID, feeMultiplierBps: null },
+    ]);
+  });
+
+  test("registerRelayer", async () => {
+    const endpoint = "https://test.test";
+
+    await expect(sendIx(
+      await registerRelayer({
+        operator: relayer.publicKey,
+        endpoint,
+        fee: relayerFee,
+        bond: minBond - 1n,
+      }),
+      [payer, relayer],
+    )).to.rejectedWith("BondTooLow");
+
+    await sendIx(
+      await registerRelayer({
+        operator: relayer.publicKey,
+        endpoint,
+        fee: relayerFee,
+        bond: minBond,
+      }),
+      [payer, relayer],
+    );
+
+    const registeredRelayer = await fetchRegisteredRelayer(
+      connection,
+      relayer.publicKey,
+    );
+    expect(registeredRelayer?.feeKey.equals(relayer.publicKey)).to.be.true;
+    expect(toBigint(registeredRelayer?.fee)).to.equal(relayerFee);
+    expect(toBigint(registeredRelayer?.bond)).to.equal(minBond);
+    expect(registeredRelayer?.endpoint).to.equal(endpoint);
+  });
+
+  test("updateRelayer", async () => {
+    const locked = async () =>
+      await connection.getBalance(findRegisteredRelayer(relayer.publicKey));
+    const lockedBefore = await locked();
+    const rent = (endpoint: string) =>
+      connection.getMinimumBalanceForRentExemption(
+        // discriminator, fee key, fee, bond, unbonding end, endpoint
+        1 + 32 + 8 + 8 + 8 + 4 + endpoint.length,
+      );
+
+    const endpoints = ["https://longer-endpoint.test", "https://test.test"];
+    for (const endpoint of endpoints) {
+      await sendIx(
+        await updateRelayer({
+          operator: relayer.publicKey,
+          endpoint,
+          fee: relayerFee,
+        }),
+        [payer, relayer],
+      );
+      const registeredRelayer = await fetchRegisteredRelayer(
+        connection,
+        relayer.publicKey,
+      );
+      expect(registeredRelayer?.endpoint).to.equal(endpoint);
+      expect(toBigint(registeredRelayer?.bond)).to.equal(minBond);
+      // only the rent changes, the bond stays locked
+      expect(await locked()).to.equal(
+        lockedBefore - await rent("https://test.test") + await rent(endpoint),
+      );
+    }
+  });
+
+  test("deregisterRelayer", async () => {
+    const operator = new Keypair();
+    await disperse(connection, [operator.publicKey], payer, 10_000_000);
+
+    await sendIx(
+      await registerRelayer({
+        operator: operator.publicKey,
+        endpoint: "https://test2.test",
+        fee: relayerFee,
+        bond: minBond,
+      }),
+      [payer, operator],
+    );
+    const registered = await fetchRegisteredRelayers(connection);
+    expect(registered.map((r) => r.feeKey.toBase58())).to.include.members([
+      relayer.publicKey.toBase58(),
+      operator.publicKey.toBase58(),
+    ]);
+
+    await expect(sendIx(
+      await deregisterRelayer({ operator: operator.publicKey }),
+      [payer, operator],
+    )).to.rejectedWith("BadTime");
+
+    await expect(sendIx(
+      await slashRelayer({
+        admin: admin.publicKey,
+        operator: operator.publicKey,
+        amount: minBond + 1n,
+        destination: payer.publicKey,
+        reason: "test",
+      }),
+      [payer, admin],
+    )).to.rejectedWith("SlashExceedsBond");
+    await sendIx(
+      await slashRelayer({
+        admin: admin.publicKey,
+        operator: operator.publicKey,
+        amount: minBond / 2n,
+        destination: payer.publicKey,
+        reason: "test",
+      }),
+      [payer, admin],
+    );
+    let registeredRelayer = await fetchRegisteredRelayer(
+      connection,
+      operator.publicKey,
+    );
+    expect(toBigint(registeredRelayer?.bond)).to.equal(minBond / 2n);
+
+    await sendIx(
+      await requestDeregistration({ operator: operator.publicKey }),
+      [payer, operator],
+    );
+    registeredRelayer = await fetchRegisteredRelayer(
+      connection,
+      operator.publicKey,
+    );
+    const unbondingEnd = toBigint(registeredRelayer?.unbondingEnd);
+    expect(unbondingEnd > 0n).to.be.true;
+    await expect(sendIx(
+      await requestDeregistration({ operator: operator.publicKey }),
+      [payer, operator],
+    )).to.rejectedWith("RelayerUnbonding");
+    await expect(sendIx(
+      await deregisterRelayer({ operator: operator.publicKey }),
+      [payer, operator],
+    )).to.rejectedWith("BadTime");
+
+    // still slashable while unbonding
+    await sendIx(
+      await slashRelayer({
+        admin: admin.publicKey,
+        operator: operator.publicKey,
+        amount: 1n,
+        destination: payer.publicKey,
+        reason: "test",
+      }),
+      [payer, admin],
+    );
+
+    const blockTime = async () =>
+      BigInt((await connection.getBlockTime(await connection.getSlot())) ?? 0);
+    while ((await blockTime()) < unbondingEnd) {
+      await new Promise((resolve) => setTimeout(resolve, 500));
+    }
+
+    const balanceBefore = await connection.getBalance(operator.publicKey);
+    const locked = await connection.getBalance(
+      findRegisteredRelayer(operator.publicKey),
+    );
+    await sendIx(
+      await deregisterRelayer({ operator: operator.publicKey }),
+      [payer, operator],
+    );
+
+    expect(await fetchRegisteredRelayer(connection, operator.publicKey)).to.be
+      .null;
+    expect(await connection.getBalance(operator.publicKey)).to.equal(
+      balanceBefore + locked,
+    );
   });
 });
 
@@ -228,6 +501,7 @@ describe("Anon Vote", () => {
   let SK: bigint;
   let PKx: Uint8Array;
   let PKy: Uint8Array;
+  let pollParams: CreatePollParams;
   const nChoices = 6;
 
   test("initialize", async () => {
@@ -284,22 +558,28 @@ describe("Anon Vote", () => {
       "https://ipfs.io/ipfs/bafkreicvkyr25sgsl2suwl4euwlexamplevyk7vxnai6tti2qexaexaexa";
     const votingStartTime = new BN(Math.floor(Date.now() / 1000) + 1);
     const votingEndTime = new BN(Math.floor(Date.now() / 1000) + 15);
-    await sendIx(
-      await createPoll({
-        payer: payer.publicKey,
-        id: pollId,
-        censusRoot: toBytesBE32(CensusRoot),
-        coordinatorKey,
-        nChoices,
-        votingStartTime,
-        votingEndTime,
-        fee: pollFee,
-        feeDestination: pollFeeDestination.publicKey,
-        nVoters: BigInt(N_VOTERS),
-        descriptionUrl,
-        censusUrl,
-      }),
-    );
+    const tallyDeadline = votingEndTime.addn(60 * 60);
+    const params = {
+      payer: payer.publicKey,
+      id: pollId,
+      censusRoot: toBytesBE32(CensusRoot),
+      coordinatorKey,
+      nChoices,
+      votingStartTime,
+      votingEndTime,
+      tallyDeadline,
+      fee: pollFee,
+      feeDestination: pollFeeDestination.publicKey,
+      nVoters: BigInt(N_VOTERS),
+      descriptionUrl,
+      censusUrl,
+      relayers: [relayer.publicKey],
+    };
+    await expect(sendIx(
+      await createPoll({ ...params, nVoters: 2n ** 64n - 1n }),
+    )).to.rejectedWith("RelayBudgetOverflow");
+    await sendIx(await createPoll(params));
+    pollParams = params;
 
     const poll = await fetchPoll(connection, findPoll(pollId));
     expect(toBigint(poll?.id)).to.equal(pollId);
@@ -311,6 +591,10 @@ describe("Anon Vote", () => {
     );
     expect(poll?.votingStartTime.eq(votingStartTime)).to.be.true;
     expect(poll?.votingEndTime.eq(votingEndTime)).to.be.true;
+    expect(poll?.tallyDeadline.eq(tallyDeadline)).to.be.true;
+    expect(poll?.failed).to.be.false;
+    expect(poll?.creator.equals(payer.publicKey)).to.be.true;
+    expect(toBigint(poll?.nMessages)).to.equal(0n);
     expect(toBigint(poll?.platformFee)).to.deep.equal(platformFee);
     expect(toBigint(poll?.fee)).to.equal(pollFee);
     expect(poll?.feeDestination.equals(pollFeeDestination.publicKey)).to.be
@@ -325,9 +609,34 @@ describe("Anon Vote", () => {
     );
     expect(relayerState?.endTime.eq(votingEndTime)).to.be.true;
     expect(toBigint(relayerState?.msgLimit)).to.equal(MSG_LIMIT);
-    expect(relayerState?.rootState).to.not.deep.equal(
+    expect(relayerState?.relayers).to.have.lengthOf(1);
+    expect(relayerState?.relayers[0].relayer.equals(relayer.publicKey)).to.be
+      .true;
+    expect(toBigint(relayerState?.relayers[0].fee)).to.equal(relayerFee);
+    expect(relayerState?.relayers[0].rootState).to.not.deep.equal(
       Array.from({ length: 32 }, () => 0),
     );
+    expect(toBigint(poll?.relayFee)).to.equal(relayerFee + platformFee);
+  });
+
+  test("fundPoll", async () => {
+    const balanceBefore = await connection.getBalance(findPoll(pollId));
+
+    await sendIx(
+      await fundPoll({ id: pollId, funder: payer.publicKey, amount: 1000n }),
+    );
+
+    expect(await connection.getBalance(findPoll(pollId))).to.equal(
+      balanceBefore + 1000,
+    );
+
+    // the deposit backs the tally
+    await expect(sendIx(
+      await withdrawPoll({
+        id: pollId,
+        feeDestination: pollFeeDestination.publicKey,
+      }),
+    )).to.rejectedWith("NotTallied");
   });
 
   test("vote", async () => {
@@ -850,6 +1159,10 @@ describe("Anon Vote", () => {
       Array.from({ length: 32 }, () => 0),
     );
     expect(tallyAcc?.root).to.deep.equal(Array.from({ length: 32 }, () => 0));
+    expect(toBigint(tallyAcc?.pollId)).to.equal(pollId);
+    expect(tallyAcc?.coordinatorKey).to.deep.equal(
+      (await fetchPoll(connection, findPoll(pollId)))?.coordinatorKey,
+    );
 
     await sendIx(
       await closeTally({
@@ -921,6 +1234,15 @@ describe("Anon Vote", () => {
     expect(poll?.tally.map(toBigint)).to.deep.equal(tally.slice(0, nChoices));
   });
 
+  test("failPoll", async () => {
+    await expect(sendIx(
+      await failPoll({
+        id: pollId,
+        feeDestination: pollFeeDestination.publicKey,
+      }),
+    )).to.rejectedWith("BadTime");
+  });
+
   test("withdrawPoll", async () => {
     await sendIx(
       await withdrawPoll({
@@ -929,4 +1251,506 @@ describe("Anon Vote", () => {
       }),
     );
   });
+
+  test("closeRelayerState", async () => {
+    const relayerStateAddress = findRelayerState(PROGRAM_ID, pollId);
+    const rent = await connection.getBalance(relayerStateAddress);
+    const balanceBefore = await connection.getBalance(payer.publicKey);
+
+    const signature = await sendIx(
+      await closeRelayerState({
+        pollId,
+        creator: payer.publicKey,
+      }),
+    );
+    const tx = await connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+
+    expect(await fetchRelayerState(connection, relayerStateAddress)).to.be
+      .null;
+    // the creator paid the rent and the transaction fee
+    expect(await connection.getBalance(payer.publicKey)).to.equal(
+      balanceBefore + rent - tx!.meta!.fee,
+    );
+  });
+
+  test("closePoll", async () => {
+    const poll = await fetchPoll(connection, findPoll(pollId));
+
+    await sendIx(
+      await closePoll({
+        id: pollId,
+        creator: payer.publicKey,
+        feeDestination: pollFeeDestination.publicKey,
+      }),
+    );
+
+    expect(await fetchPoll(connection, findPoll(pollId))).to.be.null;
+    const result = await fetchPollResult(connection, findPollResult(pollId));
+    expect(toBigint(result?.pollId)).to.equal(pollId);
+    expect(result?.nChoices).to.equal(nChoices);
+    expect(result?.failed).to.be.false;
+    expect(toBigint(result?.nMessages)).to.equal(toBigint(poll?.nMessages));
+    expect(result?.runningMsgHash).to.deep.equal(poll?.runningMsgHash);
+    expect(result?.tally.slice(0, nChoices).map(toBigint)).to.deep.equal(
+      poll?.tally.map(toBigint),
+    );
+
+    // the result keeps the id from being reused
+    await expect(sendIx(await createPoll(pollParams))).to.rejectedWith(
+      "PollIdUsed",
+    );
+  });
+});
+
+describe("Token poll", () => {
+  const pollId = 6n;
+  const nChoices = 3;
+  const platformTokenFee = 3_000n;
+  const relayerTokenFee = 5_000n;
+  const pollTokenFee = 7_000n;
+  const relayBudget = (relayerTokenFee + platformTokenFee) * MSG_LIMIT *
+    BigInt(N_VOTERS);
+
+  let mint: PublicKey;
+  let payerTokens: PublicKey;
+  let relayerTokens: PublicKey;
+  let platformFeeTokens: PublicKey;
+  let pollFeeTokens: PublicKey;
+  let feeToken: FeeToken;
+  let coordinatorPK: [bigint, bigint];
+  let tallyDeadline: bigint;
+
+  const toProof = (proof: ReturnType<typeof compressProof>) => ({
+    a: Array.from(proof.a),
+    b: Array.from(proof.b),
+    c: Array.from(proof.c),
+  });
+
+  /** Proves a first vote of `voters[voterIndex]`, for `relayer` if relayed. */
+  const proveVote = async (
+    voterIndex: number,
+    Choice: bigint,
+    Nonce: bigint,
+    relayed: boolean,
+  ) => {
+    const { prv, pub } = voters[voterIndex];
+    const sig = eddsa.signPoseidon(prv, poseidon([PLATFORM_NAME, pollId]));
+    const SignaturePoint = [F.toObject(sig.R8[0]), F.toObject(sig.R8[1])];
+    const sigHash = F.toObject(
+      poseidon([sig.S, SignaturePoint[0], SignaturePoint[1]]),
+    );
+
+    const r = randomScalar(babyjub.subOrder);
+    const Rraw = babyjub.mulPointEscalar(babyjub.Base8, r);
+    const Sraw = babyjub.mulPointEscalar(
+      coordinatorPK.map((x) => F.e(x)),
+      r,
+    );
+    const R: [bigint, bigint] = [F.toObject(Rraw[0]), F.toObject(Rraw[1])];
+    const CT = poseidonEncrypt(
+      [F.toObject(poseidon([sigHash])), Choice, 0n, 0n, 42n, 42n],
+      [F.toObject(Sraw[0]), F.toObject(Sraw[1])],
+      Nonce,
+    );
+
+    let RelayerId = 0n;
+    let relayerNu = 0n;
+    if (relayed) {
+      const relayerIdBuf = relayer.publicKey.toBuffer();
+      relayerIdBuf[0] &= (1 << 5) - 1;
+      RelayerId = BigInt("0x" + relayerIdBuf.toString("hex"));
+      relayerNu = F.toObject(poseidon([sigHash, RelayerId]));
+    }
+
+    const { path, pathPos } = await getMerkleProof(
+      CENSUS_DEPTH,
+      census,
+      voterIndex,
+    );
+    const { proof, publicSignals } = await groth16.fullProve(
+      {
+        CensusRoot,
+        PollId: pollId,
+        N_choices: BigInt(nChoices),
+        RevotingKeyNew: [42n, 42n],
+        RevotingKeyOld: [0n, 0n],
+        RevotingSignaturePoint: [0n, 0n],
+        RevotingSignatureScalar: 0n,
+        Key: [F.toObject(pub[0]), F.toObject(pub[1])],
+        SignaturePoint,
+        SignatureScalar: sig.S,
+        Path: path,
+        PathPos: pathPos,
+        Choice,
+        ephR: r,
+        CoordinatorPK: coordinatorPK,
+        RelayerId,
+        Nonce,
+        CT,
+      },
+      "build/Vote/Vote_js/Vote.wasm",
+      "build/Vote/groth16_pkey.zkey",
+    );
+
+    return {
+      proof: toProof(compressProof(proof)),
+      msgHash: BigInt(publicSignals[0]),
+      relayerNuHash: BigInt(publicSignals[1]),
+      relayerNu,
+      ephKey: { x: toBytesBE32(R[0]), y: toBytesBE32(R[1]) },
+      ciphertext: CT.map((x) => toBytesBE32(x)),
+    };
+  };
+
+  /** Proves the first relayed message to a fresh relayer state. */
+  const proveRelay = async (relayerNu: bigint, MsgHash: bigint) => {
+    const quotaMt = new Merkletree(
+      new InMemoryDB(new Uint8Array(3)),
+      true,
+      STATE_DEPTH,
+    );
+    const uniqMt = new Merkletree(
+      new InMemoryDB(new Uint8Array(4)),
+      true,
+      STATE_DEPTH,
+    );
+    const RootQuota_before = (await quotaMt.root()).bigInt();
+    const RootUniq_before = (await uniqMt.root()).bigInt();
+
+    const idx = relayerNu & ((1n << BigInt(STATE_DEPTH)) - 1n);
+    const proofQuota = await quotaMt.addAndGetCircomProof(idx, 1n);
+    const proofUniq = await uniqMt.addAndGetCircomProof(MsgHash, 1n);
+
+    const { proof, publicSignals } = await groth16.fullProve(
+      {
+        RootQuota_before,
+        RootUniq_before,
+        MsgHash,
+        MsgLimit: MSG_LIMIT,
+        Nu: relayerNu,
+        PrevCount: 0n,
+        SiblingsQuota: proofQuota.siblings.map((h) => h.bigInt()),
+        NoAuxQuota: BigInt(proofQuota.isOld0),
+        AuxKeyQuota: proofQuota.oldKey.bigInt(),
+        AuxValueQuota: proofQuota.oldValue.bigInt(),
+        SiblingsUniq: proofUniq.siblings.map((h) => h.bigInt()),
+        NoAuxUniq: BigInt(proofUniq.isOld0),
+        AuxKeyUniq: proofUniq.oldKey.bigInt(),
+        AuxValueUniq: proofUniq.oldValue.bigInt(),
+      },
+      "build/Relay/Relay_js/Relay.wasm",
+      "build/Relay/groth16_pkey.zkey",
+    );
+
+    return {
+      proof: toProof(compressProof(proof)),
+      rootStateAfter: BigInt(publicSignals[1]),
+    };
+  };
+
+  test("setTokenFee", async () => {
+    mint = await createMint(connection, payer, payer.publicKey, 6);
+    [payerTokens, relayerTokens, platformFeeTokens, pollFeeTokens] =
+      await createTokenAccounts(connection, payer, [
+        payer.publicKey,
+        relayer.publicKey,
+        platformFeeDestination.publicKey,
+        pollFeeDestination.publicKey,
+      ], mint);
+    await mintTo(connection, payer, mint, payerTokens, 1_000_000_000n);
+
+    await sendIx(
+      await setTokenFee({ admin: admin.publicKey, mint, fee: 1n }),
+      [payer, admin],
+    );
+    // setting it again updates it
+    await sendIx(
+      await setTokenFee({
+        admin: admin.publicKey,
+        mint,
+        fee: platformTokenFee,
+      }),
+      [payer, admin],
+    );
+    await sendIx(
+      await setRelayerTokenFee({
+        operator: relayer.publicKey,
+        mint,
+        fee: relayerTokenFee,
+      }),
+      [payer, relayer],
+    );
+
+    const platformToken = await fetchPlatformTokenFee(connection, mint);
+    expect(platformToken?.mint.equals(mint)).to.be.true;
+    expect(toBigint(platformToken?.fee)).to.equal(platformTokenFee);
+    const relayerToken = await fetchRelayerTokenFee(
+      connection,
+      relayer.publicKey,
+      mint,
+    );
+    expect(relayerToken?.feeKey.equals(relayer.publicKey)).to.be.true;
+    expect(relayerToken?.mint.equals(mint)).to.be.true;
+    expect(toBigint(relayerToken?.fee)).to.equal(relayerTokenFee);
+  });
+
+  test("createPoll", async () => {
+    const { pub } = genBabyJubKeypair(babyjub, eddsa);
+    coordinatorPK = [F.toObject(pub[0]), F.toObject(pub[1])];
+
+    const now = BigInt(Math.floor(Date.now() / 1000));
+    const votingEndTime = now + 20n;
+    tallyDeadline = votingEndTime + 1n;
+    const params = {
+      payer: payer.publicKey,
+      id: pollId,
+      nChoices,
+      descriptionUrl: "https://test.test/description",
+      censusUrl: "https://test.test/census",
+      coordinatorKey: {
+        x: toBytesBE32(coordinatorPK[0]),
+        y: toBytesBE32(coordinatorPK[1]),
+      },
+      censusRoot: toBytesBE32(CensusRoot),
+      votingStartTime: now + 1n,
+      votingEndTime,
+      tallyDeadline,
+      fee: pollTokenFee,
+      feeDestination: pollFeeDestination.publicKey,
+      nVoters: BigInt(N_VOTERS),
+      relayers: [relayer.publicKey],
+    };
+
+    // the platform has no fee for an unknown mint
+    const otherMint = await createMint(connection, payer, payer.publicKey, 6);
+    await sendIx(
+      await createFeeVault({
+        payer: payer.publicKey,
+        pollId,
+        mint: otherMint,
+      }),
+    );
+    await expect(sendIx(
+      await createPoll({ ...params, feeToken: { mint: otherMint } }),
+    )).to.rejectedWith("NoTokenFee");
+    // nor does the relayer
+    await sendIx(
+      await setTokenFee({ admin: admin.publicKey, mint: otherMint, fee: 1n }),
+      [payer, admin],
+    );
+    await expect(sendIx(
+      await createPoll({ ...params, feeToken: { mint: otherMint } }),
+    )).to.rejectedWith("NoTokenFee");
+
+    // Token-2022 mints aren't taken
+    const mint2022 = await createMint(
+      connection,
+      payer,
+      payer.publicKey,
+      6,
+      TOKEN_2022_PROGRAM_ID,
+    );
+    const feeToken2022 = {
+      mint: mint2022,
+      tokenProgram: TOKEN_2022_PROGRAM_ID,
+    };
+    await sendIx(
+      await createFeeVault({ payer: payer.publicKey, pollId, ...feeToken2022 }),
+    );
+    await expect(sendIx(
+      await createPoll({ ...params, feeToken: feeToken2022 }),
+    )).to.rejectedWith("UnsupportedToken");
+
+    const payerBefore = await tokenBalance(connection, payerTokens);
+    await sendIx(
+      await createFeeVault({ payer: payer.publicKey, pollId, mint }),
+    );
+    await sendIx(await createPoll({ ...params, feeToken: { mint } }));
+
+    const poll = await fetchPoll(connection, findPoll(pollId));
+    feeToken = poll!.feeToken!;
+    expect(feeToken.mint.equals(mint)).to.be.true;
+    expect(
+      feeToken.vault.equals(findAssociatedTokenAccount(findPoll(pollId), mint)),
+    ).to.be.true;
+    expect(toBigint(poll?.platformFee)).to.equal(platformTokenFee);
+    expect(toBigint(poll?.relayFee)).to.equal(
+      relayerTokenFee + platformTokenFee,
+    );
+
+    const relayerState = await fetchRelayerState(
+      connection,
+      findRelayerState(PROGRAM_ID, pollId),
+    );
+    expect(relayerState?.feeMint?.equals(mint)).to.be.true;
+    expect(toBigint(relayerState?.relayers[0].fee)).to.equal(relayerTokenFee);
+
+    expect(await tokenBalance(connection, feeToken.vault)).to.equal(
+      relayBudget,
+    );
+    expect(await tokenBalance(connection, payerTokens)).to.equal(
+      payerBefore - relayBudget,
+    );
+  });
+
+  test("fundPoll", async () => {
+    await sendIx(
+      await fundPoll({
+        id: pollId,
+        funder: payer.publicKey,
+        amount: 1000n,
+        feeToken,
+      }),
+    );
+    expect(await tokenBalance(connection, feeToken.vault)).to.equal(
+      relayBudget + 1000n,
+    );
+
+    await expect(sendIx(
+      await withdrawPoll({
+        id: pollId,
+        feeDestination: pollFeeDestination.publicKey,
+        feeToken,
+      }),
+    )).to.rejectedWith("NotTallied");
+  });
+
+  test("vote", async () => {
+    const payerBefore = await tokenBalance(connection, payerTokens);
+    const pollFeeBefore = await tokenBalance(connection, pollFeeTokens);
+    const platformFeeBefore = await tokenBalance(connection, platformFeeTokens);
+
+    const direct = await proveVote(0, 1n, 1n, false);
+    await sendIx(
+      await vote({
+        payer: payer.publicKey,
+        pollId,
+        ciphertext: direct.ciphertext,
+        ephKey: direct.ephKey,
+        nonce: 1n,
+        proof: direct.proof,
+        platformFeeDestination: platformFeeDestination.publicKey,
+        pollFeeDestination: pollFeeDestination.publicKey,
+        feeToken,
+      }),
+    );
+
+    // the voter pays both fees with TransferChecked from its token account
+    expect(await tokenBalance(connection, payerTokens)).to.equal(
+      payerBefore - pollTokenFee - platformTokenFee,
+    );
+    expect(await tokenBalance(connection, pollFeeTokens)).to.equal(
+      pollFeeBefore + pollTokenFee,
+    );
+    expect(await tokenBalance(connection, platformFeeTokens)).to.equal(
+      platformFeeBefore + platformTokenFee,
+    );
+
+    const vaultBefore = await tokenBalance(connection, feeToken.vault);
+    const relayerBefore = await tokenBalance(connection, relayerTokens);
+
+    const relayed = await proveVote(1, 2n, 2n, true);
+    const relayProof = await proveRelay(relayed.relayerNu, relayed.msgHash);
+    await sendIx(
+      await voteWithRelayer({
+        relayer: relayer.publicKey,
+        pollId,
+        msgHash: toBytesBE32(relayed.msgHash),
+        ciphertext: relayed.ciphertext,
+        ephKey: relayed.ephKey,
+        nonce: 2n,
+        proof: relayed.proof,
+        platformFeeDestination: platformFeeDestination.publicKey,
+        relayerNuHash: toBytesBE32(relayed.relayerNuHash),
+        relayerProof: relayProof.proof,
+        rootStateAfter: toBytesBE32(relayProof.rootStateAfter),
+        feeToken,
+      }),
+      [relayer],
+    );
+
+    // the vault pays the relayer and the platform fee
+    expect(await tokenBalance(connection, relayerTokens)).to.equal(
+      relayerBefore + relayerTokenFee,
+    );
+    expect(await tokenBalance(connection, platformFeeTokens)).to.equal(
+      platformFeeBefore + 2n * platformTokenFee,
+    );
+    expect(await tokenBalance(connection, feeToken.vault)).to.equal(
+      vaultBefore - relayerTokenFee - platformTokenFee,
+    );
+
+    const poll = await fetchPoll(connection, findPoll(pollId));
+    expect(toBigint(poll?.nMessages)).to.equal(2n);
+  });
+
+  test("failPoll", async () => {
+    const blockTime = async () =>
+      BigInt((await connection.getBlockTime(await connection.getSlot())) ?? 0);
+    while ((await blockTime()) <= tallyDeadline) {
+      await new Promise((resolve) => setTimeout(resolve, 500));
+    }
+
+    const vault = await tokenBalance(connection, feeToken.vault);
+    const pollFeeBefore = await tokenBalance(connection, pollFeeTokens);
+
+    await expect(sendIx(
+      await failPoll({
+        id: pollId,
+        feeDestination: platformFeeDestination.publicKey,
+        feeToken,
+      }),
+    )).to.rejectedWith("ConstraintHasOne");
+    await sendIx(
+      await failPoll({
+        id: pollId,
+        feeDestination: pollFeeDestination.publicKey,
+        feeToken,
+      }),
+    );
+
+    // the unspent vault is refunded to the poll's fee destination
+    expect(await tokenBalance(connection, feeToken.vault)).to.equal(0n);
+    expect(await tokenBalance(connection, pollFeeTokens)).to.equal(
+      pollFeeBefore + vault,
+    );
+    const poll = await fetchPoll(connection, findPoll(pollId));
+    expect(poll?.failed).to.be.true;
+  });
+
+  test("withdrawPoll", async () => {
+    await sendIx(
+      await withdrawPoll({
+        id: pollId,
+        feeDestination: pollFeeDestination.publicKey,
+        feeToken,
+      }),
+    );
+    expect(await tokenBalance(connection, feeToken.vault)).to.equal(0n);
+  });
+
+  test("closePoll", async () => {
+    await sendIx(
+      await closeRelayerState({ pollId, creator: payer.publicKey }),
+    );
+
+    await sendIx(
+      await closePoll({
+        id: pollId,
+        creator: payer.publicKey,
+        feeDestination: pollFeeDestination.publicKey,
+        feeToken,
+      }),
+    );
+
+    // the vault is closed with the poll
+    expect(await connection.getAccountInfo(feeToken.vault)).to.be.null;
+    expect(await fetchPoll(connection, findPoll(pollId))).to.be.null;
+    const result = await fetchPollResult(connection, findPollResult(pollId));
+    expect(result?.failed).to.be.true;
+    expect(toBigint(result?.nMessages)).to.equal(2n);
+  });
 });
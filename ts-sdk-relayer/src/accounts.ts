@@ -1,9 +1,16 @@
 import { Commitment, Connection, GetAccountInfoConfig } from "@solana/web3.js";
 import { getProgram } from "./program";
-import { RelayerConfig, RelayerState } from "./types";
+import {
+  AllowedPrograms,
+  RegisteredRelayer,
+  RelayerConfig,
+  RelayerState,
+  RelayerTokenFee,
+} from "./types";
 import { PublicKey } from "@solana/web3.js";
 import { fetchAccount } from "./utils";
-import { RELAYER_CONFIG } from "./constants";
+import { ALLOWED_PROGRAMS, PROGRAM_ID, RELAYER_CONFIG } from "./constants";
+import { findRegisteredRelayer, findRelayerTokenFee } from "./pdas";
 
 export const fetchRelayerConfig = async (
   connection: Connection,
@@ -17,6 +24,18 @@ export const fetchRelayerConfig = async (
     commitmentOrConfig,
   );
 
+export const fetchAllowedPrograms = async (
+  connection: Connection,
+  commitmentOrConfig?: Commitment | GetAccountInfoConfig,
+): Promise<AllowedPrograms | null> =>
+  await fetchAccount(
+    connection,
+    getProgram().coder,
+    ALLOWED_PROGRAMS,
+    "allowedPrograms",
+    commitmentOrConfig,
+  );
+
 export const fetchRelayerState = async (
   connection: Connection,
   publicKey: PublicKey,
@@ -29,3 +48,52 @@ export const fetchRelayerState = async (
     "relayerState",
     commitmentOrConfig,
   );
+
+export const fetchRegisteredRelayer = async (
+  connection: Connection,
+  feeKey: PublicKey,
+  commitmentOrConfig?: Commitment | GetAccountInfoConfig,
+): Promise<RegisteredRelayer | null> =>
+  await fetchAccount(
+    connection,
+    getProgram().coder,
+    findRegisteredRelayer(feeKey),
+    "registeredRelayer",
+    commitmentOrConfig,
+  );
+
+export const fetchRelayerTokenFee = async (
+  connection: Connection,
+  feeKey: PublicKey,
+  mint: PublicKey,
+  commitmentOrConfig?: Commitment | GetAccountInfoConfig,
+): Promise<RelayerTokenFee | null> =>
+  await fetchAccount(
+    connection,
+    getProgram().coder,
+    findRelayerTokenFee(feeKey, mint),
+    "relayerTokenFee",
+    commitmentOrConfig,
+  );
+
+/** All currently registered relayers. */
+export const fetchRegisteredRelayers = async (
+  connection: Connection,
+  commitment?: Commitment,
+): Promise<RegisteredRelayer[]> => {
+  const coder = getProgram().coder;
+  const accounts = await connection.getProgramAccounts(PROGRAM_ID, {
+    commitment,
+    filters: [
+      {
+        memcmp: {
+          offset: 0,
+          bytes: coder.accounts.memcmp("registeredRelayer").bytes!,
+        },
+      },
+    ],
+  });
+  return accounts.map(({ account }) =>
+    coder.accounts.decode<RegisteredRelayer>("registeredRelayer", account.data),
+  );
+};
@@ -7,3 +7,8 @@ export const RELAYER_CONFIG = PublicKey.findProgramAddressSync(
   [Buffer.from("RELAYER_CONFIG")],
   PROGRAM_ID,
 )[0];
+
+export const ALLOWED_PROGRAMS = PublicKey.findProgramAddressSync(
+  [Buffer.from("ALLOWED_PROGRAMS")],
+  PROGRAM_ID,
+)[0];
@@ -2,35 +2,90 @@ import { AccountMeta, PublicKey, SystemProgram } from "@solana/web3.js";
 import BN from "bn.js";
 import { InstructionWithCu, toBN } from "./utils";
 import { getProgram } from "./program";
-import { RELAYER_CONFIG } from "./constants";
-import { findRelayerState } from "./pdas";
+import { ALLOWED_PROGRAMS, RELAYER_CONFIG } from "./constants";
+import {
+  findRegisteredRelayer,
+  findRelayerState,
+  findRelayerTokenFee,
+} from "./pdas";
 import { CompressedProof } from "./types";
 
 export type InitializeParams = {
   payer: PublicKey;
   admin: PublicKey;
-  relayerEndpoint: string;
-  relayerFeeKey: PublicKey;
-  fee: BN | bigint;
+  minBond: BN | bigint;
+  /** Seconds between a deregistration request and the bond being returned. */
+  unbondingPeriod: BN | bigint;
 };
 
 export async function initialize({
   payer,
   admin,
-  relayerEndpoint,
-  relayerFeeKey,
-  fee,
+  minBond,
+  unbondingPeriod,
 }: InitializeParams): Promise<InstructionWithCu> {
   const instruction = await getProgram().methods
-    .initialize(
-      {
-        endpoint: relayerEndpoint,
-        feeKey: relayerFeeKey,
-      },
+    .initialize(admin, toBN(minBond), toBN(unbondingPeriod))
+    .accounts({
+      payer,
+      relayerConfig: RELAYER_CONFIG,
+    })
+    .instruction();
+
+  return {
+    instruction,
+    cuLimit: 200_000,
+  };
+}
+
+export type InitAllowedProgramsParams = {
+  payer: PublicKey;
+  admin: PublicKey;
+};
+
+/** Creates the empty list of allowed programs. */
+export async function initAllowedPrograms({
+  payer,
+  admin,
+}: InitAllowedProgramsParams): Promise<InstructionWithCu> {
+  const instruction = await getProgram().methods
+    .initAllowedPrograms()
+    .accountsStrict({
+      payer,
       admin,
-      toBN(fee),
-    )
-    .accounts({ payer, relayerConfig: RELAYER_CONFIG })
+      relayerConfig: RELAYER_CONFIG,
+      allowedPrograms: ALLOWED_PROGRAMS,
+      systemProgram: SystemProgram.programId,
+    })
+    .instruction();
+
+  return {
+    instruction,
+    cuLimit: 200_000,
+  };
+}
+
+export type MigrateConfigParams = {
+  admin: PublicKey;
+  minBond: BN | bigint;
+  unbondingPeriod: BN | bigint;
+};
+
+/**
+ * Rewrites the config of a deployment upgraded from the single relayer
+ * layout.
+ */
+export async function migrateConfig({
+  admin,
+  minBond,
+  unbondingPeriod,
+}: MigrateConfigParams): Promise<InstructionWithCu> {
+  const instruction = await getProgram().methods
+    .migrateConfig(toBN(minBond), toBN(unbondingPeriod))
+    .accountsStrict({
+      admin,
+      relayerConfig: RELAYER_CONFIG,
+    })
     .instruction();
 
   return {
@@ -40,35 +95,247 @@ export async function initialize({
 }
 
 export type UpdateConfigParams = {
-  payer: PublicKey;
   oldAdmin: PublicKey;
   newAdmin: PublicKey;
-  relayerEndpoint: string;
-  relayerFeeKey: PublicKey;
-  fee: BN | bigint;
+  minBond: BN | bigint;
+  unbondingPeriod: BN | bigint;
 };
 
 export async function updateConfig({
-  payer,
   oldAdmin,
   newAdmin,
-  relayerEndpoint,
-  relayerFeeKey,
-  fee,
+  minBond,
+  unbondingPeriod,
 }: UpdateConfigParams): Promise<InstructionWithCu> {
   const instruction = await getProgram().methods
-    .updateConfig(
-      {
-        endpoint: relayerEndpoint,
-        feeKey: relayerFeeKey,
-      },
-      newAdmin,
-      toBN(fee),
-    )
+    .updateConfig(newAdmin, toBN(minBond), toBN(unbondingPeriod))
+    .accountsStrict({
+      admin: oldAdmin,
+      relayerConfig: RELAYER_CONFIG,
+    })
+    .instruction();
+
+  return {
+    instruction,
+    cuLimit: 200_000,
+  };
+}
+
+export type AddAllowedProgramParams = {
+  payer: PublicKey;
+  admin: PublicKey;
+  program: PublicKey;
+  /** Relayer fee multiplier in basis points, 10_000 being 1x. */
+  feeMultiplierBps?: number;
+};
+
+export async function addAllowedProgram({
+  payer,
+  admin,
+  program,
+  feeMultiplierBps,
+}: AddAllowedProgramParams): Promise<InstructionWithCu> {
+  const instruction = await getProgram().methods
+    .addAllowedProgram(program, feeMultiplierBps ?? null)
     .accountsStrict({
       payer,
+      admin,
       relayerConfig: RELAYER_CONFIG,
-      admin: oldAdmin,
+      allowedPrograms: ALLOWED_PROGRAMS,
+      systemProgram: SystemProgram.programId,
+    })
+    .instruction();
+
+  return {
+    instruction,
+    cuLimit: 200_000,
+  };
+}
+
+export type RemoveAllowedProgramParams = {
+  payer: PublicKey;
+  admin: PublicKey;
+  program: PublicKey;
+};
+
+export async function removeAllowedProgram({
+  payer,
+  admin,
+  program,
+}: RemoveAllowedProgramParams): Promise<InstructionWithCu> {
+  const instruction = await getProgram().methods
+    .removeAllowedProgram(program)
+    .accountsStrict({
+      payer,
+      admin,
+      relayerConfig: RELAYER_CONFIG,
+      allowedPrograms: ALLOWED_PROGRAMS,
+      systemProgram: SystemProgram.programId,
+    })
+    .instruction();
+
+  return {
+    instruction,
+    cuLimit: 200_000,
+  };
+}
+
+export type RegisterRelayerParams = {
+  operator: PublicKey;
+  endpoint: string;
+  /** Fee per relayed message in lamports, see `setTokenFee` for tokens. */
+  fee: BN | bigint;
+  bond: BN | bigint;
+};
+
+export async function registerRelayer({
+  operator,
+  endpoint,
+  fee,
+  bond,
+}: RegisterRelayerParams): Promise<InstructionWithCu> {
+  const instruction = await getProgram().methods
+    .registerRelayer(endpoint, toBN(fee), toBN(bond))
+    .accountsStrict({
+      operator,
+      relayerConfig: RELAYER_CONFIG,
+      registeredRelayer: findRegisteredRelayer(operator),
+      systemProgram: SystemProgram.programId,
+    })
+    .instruction();
+
+  return {
+    instruction,
+    cuLimit: 200_000,
+  };
+}
+
+export type UpdateRelayerParams = {
+  operator: PublicKey;
+  endpoint: string;
+  /** Fee per relayed message in lamports, see `setTokenFee` for tokens. */
+  fee: BN | bigint;
+};
+
+/**
+ * Changes the relayer's endpoint and fee, keeping its bond. Relayer states
+ * created before keep their fees.
+ */
+export async function updateRelayer({
+  operator,
+  endpoint,
+  fee,
+}: UpdateRelayerParams): Promise<InstructionWithCu> {
+  const instruction = await getProgram().methods
+    .updateRelayer(endpoint, toBN(fee))
+    .accountsStrict({
+      operator,
+      registeredRelayer: findRegisteredRelayer(operator),
+      systemProgram: SystemProgram.programId,
+    })
+    .instruction();
+
+  return {
+    instruction,
+    cuLimit: 200_000,
+  };
+}
+
+export type RequestDeregistrationParams = {
+  operator: PublicKey;
+};
+
+export async function requestDeregistration({
+  operator,
+}: RequestDeregistrationParams): Promise<InstructionWithCu> {
+  const instruction = await getProgram().methods
+    .requestDeregistration()
+    .accountsStrict({
+      operator,
+      relayerConfig: RELAYER_CONFIG,
+      registeredRelayer: findRegisteredRelayer(operator),
+    })
+    .instruction();
+
+  return {
+    instruction,
+    cuLimit: 200_000,
+  };
+}
+
+export type DeregisterRelayerParams = {
+  operator: PublicKey;
+};
+
+export async function deregisterRelayer({
+  operator,
+}: DeregisterRelayerParams): Promise<InstructionWithCu> {
+  const instruction = await getProgram().methods
+    .deregisterRelayer()
+    .accountsStrict({
+      operator,
+      registeredRelayer: findRegisteredRelayer(operator),
+    })
+    .instruction();
+
+  return {
+    instruction,
+    cuLimit: 200_000,
+  };
+}
+
+export type SlashRelayerParams = {
+  admin: PublicKey;
+  operator: PublicKey;
+  /** At most the relayer's bond. */
+  amount: BN | bigint;
+  destination: PublicKey;
+  /** Recorded in the `RelayerSlashedEvent`. */
+  reason: string;
+};
+
+export async function slashRelayer({
+  admin,
+  operator,
+  amount,
+  destination,
+  reason,
+}: SlashRelayerParams): Promise<InstructionWithCu> {
+  const instruction = await getProgram().methods
+    .slashRelayer(operator, toBN(amount), reason)
+    .accountsStrict({
+      admin,
+      relayerConfig: RELAYER_CONFIG,
+      registeredRelayer: findRegisteredRelayer(operator),
+      destination,
+    })
+    .instruction();
+
+  return {
+    instruction,
+    cuLimit: 200_000,
+  };
+}
+
+export type SetTokenFeeParams = {
+  operator: PublicKey;
+  mint: PublicKey;
+  /** Fee per relayed message in base units of the mint. */
+  fee: BN | bigint;
+};
+
+/** Sets the relayer's fee for target programs paying in `mint`. */
+export async function setTokenFee({
+  operator,
+  mint,
+  fee,
+}: SetTokenFeeParams): Promise<InstructionWithCu> {
+  const instruction = await getProgram().methods
+    .setTokenFee(mint, toBN(fee))
+    .accountsStrict({
+      operator,
+      registeredRelayer: findRegisteredRelayer(operator),
+      tokenFee: findRelayerTokenFee(operator, mint),
       systemProgram: SystemProgram.programId,
     })
     .instruction();
@@ -91,6 +358,11 @@ export type RelayParams = {
   targetProgram: PublicKey;
   targetAccounts: AccountMeta[];
   targetCuLimit: number;
+  /**
+   * The relayer's token account of the relayer state's `feeMint`, required
+   * when it has one.
+   */
+  relayerTokens?: PublicKey;
 };
 
 export async function relay({
@@ -105,6 +377,7 @@ export async function relay({
   targetProgram,
   targetAccounts,
   targetCuLimit,
+  relayerTokens,
 }: RelayParams): Promise<InstructionWithCu> {
   const instruction = await getProgram().methods
     .relay(
@@ -118,9 +391,10 @@ export async function relay({
     )
     .accounts({
       relayer,
-      relayerConfig: RELAYER_CONFIG,
       relayerState: findRelayerState(targetProgram, stateId),
+      allowedPrograms: ALLOWED_PROGRAMS,
       targetProgram,
+      relayerTokens: relayerTokens ?? null,
     })
     .remainingAccounts(targetAccounts)
     .instruction();
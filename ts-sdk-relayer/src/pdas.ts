@@ -15,3 +15,16 @@ export const findRelayerState = (
     ],
     PROGRAM_ID,
   )[0];
+
+export const findRegisteredRelayer = (feeKey: PublicKey) =>
+  PublicKey.findProgramAddressSync(
+    [Buffer.from("RELAYER"), feeKey.toBuffer()],
+    PROGRAM_ID,
+  )[0];
+
+/** The relayer's fee for target programs paying in `mint`. */
+export const findRelayerTokenFee = (feeKey: PublicKey, mint: PublicKey) =>
+  PublicKey.findProgramAddressSync(
+    [Buffer.from("RELAYER_TOKEN_FEE"), feeKey.toBuffer(), mint.toBuffer()],
+    PROGRAM_ID,
+  )[0];
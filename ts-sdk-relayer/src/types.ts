@@ -4,3 +4,8 @@ import { ZkRelayer } from "./idl/zk_relayer";
 export type CompressedProof = IdlTypes<ZkRelayer>["compressedProof"];
 export type RelayerConfig = IdlTypes<ZkRelayer>["zkRelayerConfig"];
 export type RelayerState = IdlTypes<ZkRelayer>["relayerState"];
+export type RegisteredRelayer = IdlTypes<ZkRelayer>["registeredRelayer"];
+export type RelayerQuota = IdlTypes<ZkRelayer>["relayerQuota"];
+export type AllowedPrograms = IdlTypes<ZkRelayer>["allowedPrograms"];
+export type AllowedProgram = IdlTypes<ZkRelayer>["allowedProgram"];
+export type RelayerTokenFee = IdlTypes<ZkRelayer>["relayerTokenFee"];
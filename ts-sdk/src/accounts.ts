@@ -1,9 +1,16 @@
 import { Commitment, Connection, GetAccountInfoConfig } from "@solana/web3.js";
 import { getProgram } from "./program";
-import { PlatformConfig, Poll, Tally } from "./types";
+import {
+  PlatformConfig,
+  PlatformTokenFee,
+  Poll,
+  PollResult,
+  Tally,
+} from "./types";
 import { PublicKey } from "@solana/web3.js";
 import { fetchAccount } from "./utils";
 import { PLATFORM_CONFIG } from "./constants";
+import { findPlatformTokenFee } from "./pdas";
 
 export const fetchPoll = async (
   connection: Connection,
@@ -18,6 +25,19 @@ export const fetchPoll = async (
     commitmentOrConfig,
   );
 
+export const fetchPollResult = async (
+  connection: Connection,
+  publicKey: PublicKey,
+  commitmentOrConfig?: Commitment | GetAccountInfoConfig,
+): Promise<PollResult | null> =>
+  await fetchAccount(
+    connection,
+    getProgram().coder,
+    publicKey,
+    "pollResult",
+    commitmentOrConfig,
+  );
+
 export const fetchTally = async (
   connection: Connection,
   publicKey: PublicKey,
@@ -42,3 +62,16 @@ export const fetchPlatformConfig = async (
     "platformConfig",
     commitmentOrConfig,
   );
+
+export const fetchPlatformTokenFee = async (
+  connection: Connection,
+  mint: PublicKey,
+  commitmentOrConfig?: Commitment | GetAccountInfoConfig,
+): Promise<PlatformTokenFee | null> =>
+  await fetchAccount(
+    connection,
+    getProgram().coder,
+    findPlatformTokenFee(mint),
+    "platformTokenFee",
+    commitmentOrConfig,
+  );
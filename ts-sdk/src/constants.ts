@@ -3,6 +3,14 @@ import anonVoteIdl from "./idl/anon_vote.json";
 
 export const PROGRAM_ID = new PublicKey(anonVoteIdl.address);
 
+export const TOKEN_PROGRAM_ID = new PublicKey(
+  "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+);
+
+export const ASSOCIATED_TOKEN_PROGRAM_ID = new PublicKey(
+  "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL",
+);
+
 export const PLATFORM_NAME = 4714828379590718565n;
 
 export const PLATFORM_CONFIG = PublicKey.findProgramAddressSync(
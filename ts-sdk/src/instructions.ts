@@ -1,14 +1,31 @@
-import { PublicKey } from "@solana/web3.js";
+import {
+  PublicKey,
+  SystemProgram,
+  TransactionInstruction,
+} from "@solana/web3.js";
 import BN from "bn.js";
 import { InstructionWithCu, toBN } from "./utils";
 import { getProgram } from "./program";
-import { PLATFORM_CONFIG, PROGRAM_ID } from "./constants";
-import { findPoll, findTally } from "./pdas";
-import { CompressedProof, Point } from "./types";
 import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  PLATFORM_CONFIG,
+  PROGRAM_ID,
+  TOKEN_PROGRAM_ID,
+} from "./constants";
+import {
+  findAssociatedTokenAccount,
+  findPlatformTokenFee,
+  findPoll,
+  findPollResult,
+  findTally,
+} from "./pdas";
+import { CompressedProof, FeeToken, Point } from "./types";
+import {
+  ALLOWED_PROGRAMS,
+  findRegisteredRelayer,
   findRelayerState,
+  findRelayerTokenFee,
   relay,
-  RELAYER_CONFIG,
 } from "@lincot/zk-relayer-sdk";
 
 export type InitializeParams = {
@@ -67,6 +84,35 @@ export async function updateConfig({
   };
 }
 
+export type SetTokenFeeParams = {
+  admin: PublicKey;
+  mint: PublicKey;
+  /** Platform fee per message in base units of the mint. */
+  fee: BN | bigint;
+};
+
+/** Sets the platform fee of polls paying in `mint`. */
+export async function setTokenFee({
+  admin,
+  mint,
+  fee,
+}: SetTokenFeeParams): Promise<InstructionWithCu> {
+  const instruction = await getProgram().methods
+    .setTokenFee(mint, toBN(fee))
+    .accountsStrict({
+      admin,
+      platformConfig: PLATFORM_CONFIG,
+      tokenFee: findPlatformTokenFee(mint),
+      systemProgram: SystemProgram.programId,
+    })
+    .instruction();
+
+  return {
+    instruction,
+    cuLimit: 200_000,
+  };
+}
+
 export type CreatePollParams = {
   payer: PublicKey;
   id: BN | bigint;
@@ -77,11 +123,65 @@ export type CreatePollParams = {
   censusRoot: number[];
   votingStartTime: BN | bigint;
   votingEndTime: BN | bigint;
+  /**
+   * The tally has to be published before this time, after it the poll may
+   * be failed with `failPoll` refunding the deposit. At most a month after
+   * `votingEndTime`.
+   */
+  tallyDeadline: BN | bigint;
   fee: BN | bigint;
   feeDestination: PublicKey;
   nVoters: BN | bigint;
+  /** Registered relayers funded to relay the poll's votes. */
+  relayers: PublicKey[];
+  /**
+   * SPL token all the poll's fees are paid in instead of lamports. Its vault
+   * has to be created with `createFeeVault` first, and the platform and
+   * every relayer need a fee for the mint set with `setTokenFee`. Token-2022
+   * mints are rejected.
+   */
+  feeToken?: { mint: PublicKey; tokenProgram?: PublicKey };
 };
 
+export type CreateFeeVaultParams = {
+  payer: PublicKey;
+  pollId: BN | bigint;
+  mint: PublicKey;
+  tokenProgram?: PublicKey;
+};
+
+/** Creates the poll's associated token account holding its relay budget. */
+export async function createFeeVault({
+  payer,
+  pollId,
+  mint,
+  tokenProgram = TOKEN_PROGRAM_ID,
+}: CreateFeeVaultParams): Promise<InstructionWithCu> {
+  const poll = findPoll(pollId);
+  const instruction = new TransactionInstruction({
+    programId: ASSOCIATED_TOKEN_PROGRAM_ID,
+    keys: [
+      { pubkey: payer, isSigner: true, isWritable: true },
+      {
+        pubkey: findAssociatedTokenAccount(poll, mint, tokenProgram),
+        isSigner: false,
+        isWritable: true,
+      },
+      { pubkey: poll, isSigner: false, isWritable: false },
+      { pubkey: mint, isSigner: false, isWritable: false },
+      { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+      { pubkey: tokenProgram, isSigner: false, isWritable: false },
+    ],
+    // CreateIdempotent
+    data: Buffer.from([1]),
+  });
+
+  return {
+    instruction,
+    cuLimit: 50_000,
+  };
+}
+
 export async function createPoll({
   payer,
   id,
@@ -92,10 +192,14 @@ export async function createPoll({
   censusRoot,
   votingStartTime,
   votingEndTime,
+  tallyDeadline,
   fee,
   feeDestination,
   nVoters,
+  relayers,
+  feeToken,
 }: CreatePollParams): Promise<InstructionWithCu> {
+  const tokenProgram = feeToken && (feeToken.tokenProgram ?? TOKEN_PROGRAM_ID);
   const instruction = await getProgram().methods
     .createPoll(
       toBN(id),
@@ -106,17 +210,39 @@ export async function createPoll({
       censusRoot,
       toBN(votingStartTime),
       toBN(votingEndTime),
+      toBN(tallyDeadline),
       toBN(fee),
       feeDestination,
       toBN(nVoters),
+      relayers,
     )
     .accounts({
       payer,
       poll: findPoll(id),
+      result: findPollResult(id),
       platformConfig: PLATFORM_CONFIG,
-      relayerConfig: RELAYER_CONFIG,
       relayerState: findRelayerState(PROGRAM_ID, id),
+      allowedPrograms: ALLOWED_PROGRAMS,
+      feeVault: feeToken
+        ? findAssociatedTokenAccount(findPoll(id), feeToken.mint, tokenProgram)
+        : null,
+      payerTokens: feeToken
+        ? findAssociatedTokenAccount(payer, feeToken.mint, tokenProgram)
+        : null,
+      tokenProgram: tokenProgram ?? null,
+      feeMint: feeToken?.mint ?? null,
+      platformTokenFee: feeToken ? findPlatformTokenFee(feeToken.mint) : null,
     })
+    .remainingAccounts(
+      [
+        ...relayers.map(findRegisteredRelayer),
+        ...(feeToken
+          ? relayers.map((relayer) =>
+            findRelayerTokenFee(relayer, feeToken.mint)
+          )
+          : []),
+      ].map((pubkey) => ({ pubkey, isSigner: false, isWritable: false })),
+    )
     .instruction();
 
   return {
@@ -134,8 +260,16 @@ export type VoteParams = {
   proof: CompressedProof;
   platformFeeDestination: PublicKey;
   pollFeeDestination: PublicKey;
+  /** The poll's `feeToken`, fees are paid from the payer's token account. */
+  feeToken?: FeeToken;
 };
 
+/** Where a fee goes: the wallet itself or its token account. */
+const feeRecipient = (wallet: PublicKey, feeToken?: FeeToken) =>
+  feeToken
+    ? findAssociatedTokenAccount(wallet, feeToken.mint, feeToken.tokenProgram)
+    : wallet;
+
 export async function vote({
   payer,
   ephKey,
@@ -145,6 +279,7 @@ export async function vote({
   proof,
   platformFeeDestination,
   pollFeeDestination,
+  feeToken,
 }: VoteParams): Promise<InstructionWithCu> {
   const instruction = await getProgram().methods
     .vote(
@@ -155,12 +290,16 @@ export async function vote({
     )
     .accounts({
       payer,
-      pollFeeDestination,
+      pollFeeDestination: feeRecipient(pollFeeDestination, feeToken),
       voteCommon: {
         poll: findPoll(pollId),
-        platformFeeDestination,
+        platformFeeDestination: feeRecipient(platformFeeDestination, feeToken),
         platformConfig: PLATFORM_CONFIG,
+        feeVault: feeToken?.vault ?? null,
+        tokenProgram: feeToken?.tokenProgram ?? null,
+        feeMint: feeToken?.mint ?? null,
       },
+      payerTokens: feeToken ? feeRecipient(payer, feeToken) : null,
     })
     .instruction();
 
@@ -182,6 +321,8 @@ export type VoteWithRelayerParams = {
   relayerProof: CompressedProof;
   rootStateAfter: number[];
   platformFeeDestination: PublicKey;
+  /** The poll's `feeToken`, the relayer is paid to its token account. */
+  feeToken?: FeeToken;
 };
 
 export async function voteWithRelayer({
@@ -196,7 +337,9 @@ export async function voteWithRelayer({
   relayerProof,
   rootStateAfter,
   platformFeeDestination,
+  feeToken,
 }: VoteWithRelayerParams): Promise<InstructionWithCu> {
+  const relayerTokens = feeToken && feeRecipient(relayer, feeToken);
   const data = serializeVoteData({
     ciphertext,
     proof,
@@ -212,6 +355,8 @@ export async function voteWithRelayer({
     nuHash: relayerNuHash,
     proof: relayerProof,
     rootStateAfter,
+    relayerTokens,
+    // absent optional accounts are passed as the program id
     targetAccounts: [{
       isSigner: false,
       isWritable: false,
@@ -223,7 +368,23 @@ export async function voteWithRelayer({
     }, {
       isSigner: false,
       isWritable: true,
-      pubkey: platformFeeDestination,
+      pubkey: feeRecipient(platformFeeDestination, feeToken),
+    }, {
+      isSigner: false,
+      isWritable: !!feeToken,
+      pubkey: feeToken?.vault ?? PROGRAM_ID,
+    }, {
+      isSigner: false,
+      isWritable: false,
+      pubkey: feeToken?.tokenProgram ?? PROGRAM_ID,
+    }, {
+      isSigner: false,
+      isWritable: false,
+      pubkey: feeToken?.mint ?? PROGRAM_ID,
+    }, {
+      isSigner: false,
+      isWritable: !!feeToken,
+      pubkey: relayerTokens ?? PROGRAM_ID,
     }],
     targetCuLimit: 200_000,
     targetProgram: PROGRAM_ID,
@@ -244,7 +405,7 @@ export async function createTally(
       toBN(pollId),
       initialTallyHash,
     )
-    .accounts({ tally: findTally(pollId, payer), payer })
+    .accounts({ tally: findTally(pollId, payer), poll: findPoll(pollId), payer })
     .instruction();
 
   return {
@@ -334,19 +495,144 @@ export async function closeTally(
   };
 }
 
+/** Accounts moving what's left in a token poll's vault to `wallet`. */
+const vaultAccounts = (wallet: PublicKey, feeToken?: FeeToken) => ({
+  feeVault: feeToken?.vault ?? null,
+  feeDestinationTokens: feeToken ? feeRecipient(wallet, feeToken) : null,
+  tokenProgram: feeToken?.tokenProgram ?? null,
+  feeMint: feeToken?.mint ?? null,
+});
+
 export type WithdrawPollParams = {
   id: BN | bigint;
   feeDestination: PublicKey;
+  /** The poll's `feeToken`, for token polls. */
+  feeToken?: FeeToken;
 };
 
 export async function withdrawPoll(
-  { id, feeDestination }: WithdrawPollParams,
+  { id, feeDestination, feeToken }: WithdrawPollParams,
 ): Promise<InstructionWithCu> {
   const instruction = await getProgram().methods
     .withdrawPoll()
     .accountsStrict({
       feeDestination,
       poll: findPoll(id),
+      ...vaultAccounts(feeDestination, feeToken),
+    })
+    .instruction();
+
+  return {
+    instruction,
+    cuLimit: 200_000,
+  };
+}
+
+export type FundPollParams = {
+  id: BN | bigint;
+  funder: PublicKey;
+  /** Lamports, or fee token amount, added to pay the poll's relayers. */
+  amount: BN | bigint;
+  /** The poll's `feeToken`, for token polls. */
+  feeToken?: FeeToken;
+};
+
+export async function fundPoll(
+  { id, funder, amount, feeToken }: FundPollParams,
+): Promise<InstructionWithCu> {
+  const instruction = await getProgram().methods
+    .fundPoll(toBN(amount))
+    .accounts({
+      funder,
+      poll: findPoll(id),
+      funderTokens: feeToken ? feeRecipient(funder, feeToken) : null,
+      feeVault: feeToken?.vault ?? null,
+      tokenProgram: feeToken?.tokenProgram ?? null,
+      feeMint: feeToken?.mint ?? null,
+    })
+    .instruction();
+
+  return {
+    instruction,
+    cuLimit: 200_000,
+  };
+}
+
+export type FailPollParams = {
+  id: BN | bigint;
+  /** The poll's fee destination, receiving the refunded deposit. */
+  feeDestination: PublicKey;
+  /** The poll's `feeToken`, for token polls. */
+  feeToken?: FeeToken;
+};
+
+export async function failPoll(
+  { id, feeDestination, feeToken }: FailPollParams,
+): Promise<InstructionWithCu> {
+  const instruction = await getProgram().methods
+    .failPoll()
+    .accountsStrict({
+      feeDestination,
+      poll: findPoll(id),
+      ...vaultAccounts(feeDestination, feeToken),
+    })
+    .instruction();
+
+  return {
+    instruction,
+    cuLimit: 200_000,
+  };
+}
+
+export type CloseRelayerStateParams = {
+  pollId: BN | bigint;
+  /** The poll creator, who paid the rent. */
+  creator: PublicKey;
+};
+
+export async function closeRelayerState(
+  { pollId, creator }: CloseRelayerStateParams,
+): Promise<InstructionWithCu> {
+  const instruction = await getProgram().methods
+    .closeRelayerState()
+    .accounts({
+      creator,
+      poll: findPoll(pollId),
+      relayerState: findRelayerState(PROGRAM_ID, pollId),
+    })
+    .instruction();
+
+  return {
+    instruction,
+    cuLimit: 200_000,
+  };
+}
+
+export type ClosePollParams = {
+  id: BN | bigint;
+  creator: PublicKey;
+  feeDestination: PublicKey;
+  /** The poll's `feeToken`, for token polls. */
+  feeToken?: FeeToken;
+};
+
+/**
+ * Closes a tallied or failed poll into its `PollResult`, the relayer state
+ * has to be closed before.
+ */
+export async function closePoll(
+  { id, creator, feeDestination, feeToken }: ClosePollParams,
+): Promise<InstructionWithCu> {
+  const instruction = await getProgram().methods
+    .closePoll()
+    .accountsStrict({
+      creator,
+      feeDestination,
+      poll: findPoll(id),
+      result: findPollResult(id),
+      relayerState: findRelayerState(PROGRAM_ID, id),
+      systemProgram: SystemProgram.programId,
+      ...vaultAccounts(feeDestination, feeToken),
     })
     .instruction();
 
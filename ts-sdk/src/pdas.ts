@@ -1,5 +1,9 @@
 import { PublicKey } from "@solana/web3.js";
-import { PROGRAM_ID } from "./constants";
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  PROGRAM_ID,
+  TOKEN_PROGRAM_ID,
+} from "./constants";
 import BN from "bn.js";
 import { toBN } from "./utils";
 
@@ -11,6 +15,32 @@ export const findPoll = (
     PROGRAM_ID,
   )[0];
 
+/** The token account fees of a token poll are paid from and to. */
+export const findAssociatedTokenAccount = (
+  owner: PublicKey,
+  mint: PublicKey,
+  tokenProgram: PublicKey = TOKEN_PROGRAM_ID,
+) =>
+  PublicKey.findProgramAddressSync(
+    [owner.toBytes(), tokenProgram.toBytes(), mint.toBytes()],
+    ASSOCIATED_TOKEN_PROGRAM_ID,
+  )[0];
+
+export const findPollResult = (
+  id: bigint | BN,
+) =>
+  PublicKey.findProgramAddressSync(
+    [Buffer.from("RESULT"), toBN(id).toArrayLike(Buffer, "le", 8)],
+    PROGRAM_ID,
+  )[0];
+
+/** The platform fee of polls paying in `mint`. */
+export const findPlatformTokenFee = (mint: PublicKey) =>
+  PublicKey.findProgramAddressSync(
+    [Buffer.from("TOKEN_FEE"), mint.toBytes()],
+    PROGRAM_ID,
+  )[0];
+
 export const findTally = (
   pollId: bigint | BN,
   owner: PublicKey,
@@ -4,5 +4,8 @@ import { AnonVote } from "./idl/anon_vote";
 export type CompressedProof = IdlTypes<AnonVote>["compressedProof"];
 export type Point = IdlTypes<AnonVote>["point"];
 export type Poll = IdlTypes<AnonVote>["poll"];
+export type FeeToken = IdlTypes<AnonVote>["feeToken"];
+export type PollResult = IdlTypes<AnonVote>["pollResult"];
 export type Tally = IdlTypes<AnonVote>["tally"];
 export type PlatformConfig = IdlTypes<AnonVote>["platformConfig"];
+export type PlatformTokenFee = IdlTypes<AnonVote>["platformTokenFee"];